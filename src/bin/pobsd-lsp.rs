@@ -0,0 +1,15 @@
+//! Language Server Protocol server for the PlayOnBSD database
+//! (see [`libpobsd::lsp`]), communicating over the standard input
+//! and output.
+use std::io::{stdin, stdout, BufReader};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match libpobsd::lsp::run(BufReader::new(stdin().lock()), stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("pobsd-lsp: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
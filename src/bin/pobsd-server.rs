@@ -0,0 +1,45 @@
+//! Serves the PlayOnBSD database as a JSON API over HTTP
+//! (see [`libpobsd::server`]).
+//!
+//! Usage: `pobsd-server <database> [address]`, the address being
+//! `127.0.0.1:8080` by default.
+use libpobsd::server::{serve, DEFAULT_ADDRESS};
+use libpobsd::{GameDataBase, Parser, ParserResult, ParsingMode};
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (database, address) = match args.as_slice() {
+        [database] => (database, DEFAULT_ADDRESS),
+        [database, address] => (database, address.as_str()),
+        _ => {
+            eprintln!("Usage: pobsd-server <database> [address]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let games = match Parser::new(ParsingMode::Relaxed).load_from_file(database) {
+        Ok(ParserResult::WithoutError(games)) => games,
+        Ok(ParserResult::WithError(games, lines)) => {
+            eprintln!("Errors in the database at lines: {:?}", lines);
+            games
+        }
+        Err(e) => {
+            eprintln!("Could not open {}: {}", database, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let db = GameDataBase::new(games);
+    println!(
+        "Serving {} games on http://{}",
+        db.get_all_games().count,
+        address
+    );
+    match serve(&db, address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Server error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
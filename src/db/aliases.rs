@@ -0,0 +1,221 @@
+//! Provides an [`AliasRegistry`] mapping the different spellings of a
+//! developer, publisher, engine or runtime to a canonical name.
+//!
+//! When a [`crate::GameDataBase`] uses an [`AliasRegistry`], the games are
+//! indexed under the canonical name of their items, so that querying any
+//! alias returns the whole group.
+//!
+//! The registry can be loaded from a simple local file made of sections
+//! (`[devs]`, `[publis]`, `[engines]` or `[runtimes]`, the singular forms
+//! and the keys used in the PlayOnBSD database such as `[Pub]` being also
+//! accepted), each line of a section giving a canonical name followed
+//! by its comma-separated aliases. Empty lines and lines starting with
+//! `#` are ignored.
+//!
+//! ## Examples
+//! ```
+//! use libpobsd::db::AliasRegistry;
+//!
+//! let aliases = AliasRegistry::load_from_string(r#"
+//! ## Publishers
+//! [publis]
+//! Devolver Digital = Devolver, Devolver Digital Inc.
+//! [engines]
+//! FNA = FNA/XNA
+//! "#).expect("Invalid alias file");
+//! assert_eq!(aliases.get_canonical_publis("devolver"), Some(&"Devolver Digital".to_string()));
+//! assert_eq!(aliases.get_canonical_engines("fna/xna"), Some(&"FNA".to_string()));
+//! assert_eq!(aliases.get_canonical_engines("godot"), None);
+//! ```
+use crate::db::index::normalize_item;
+
+use paste::paste;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+macro_rules! alias_methods {
+    ($field:ident) => {
+        paste! {
+            /// Registers an alias of the given canonical name for the chosen field.
+            /// The canonical name is registered as an alias of itself.
+            pub fn [<add_ $field _alias>](&mut self, canonical: &str, alias: &str) -> &mut Self {
+                let canonical = canonical.trim().to_string();
+                self.$field.insert(normalize_item(&canonical), canonical.clone());
+                self.$field.insert(normalize_item(alias), canonical);
+                self
+            }
+            /// Returns the canonical name of the given item for the chosen field
+            /// if it is a registered alias (the comparison is not case sensitive).
+            pub fn [<get_canonical_ $field>](&self, item: &str) -> Option<&String> {
+                self.$field.get(&normalize_item(item))
+            }
+        }
+    };
+}
+
+/// Maps aliases of developers, publishers, engines and runtimes to
+/// their canonical names.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AliasRegistry {
+    /// HashMap using the normalized engine alias as key and the canonical name as value
+    pub(crate) engines: HashMap<String, String>,
+    /// HashMap using the normalized runtime alias as key and the canonical name as value
+    pub(crate) runtimes: HashMap<String, String>,
+    /// HashMap using the normalized dev alias as key and the canonical name as value
+    pub(crate) devs: HashMap<String, String>,
+    /// HashMap using the normalized pub alias as key and the canonical name as value
+    pub(crate) publis: HashMap<String, String>,
+}
+
+impl AliasRegistry {
+    /// Creates an empty [`AliasRegistry`]. Equivalent to Default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Loads an [`AliasRegistry`] from a file (see the module documentation
+    /// for the format).
+    pub fn load_from_file(file: impl AsRef<Path>) -> Result<Self, Error> {
+        let data = fs::read_to_string(file)?;
+        Self::load_from_string(&data)
+    }
+    /// Loads an [`AliasRegistry`] from a [`&str`] (see the module documentation
+    /// for the format). An error is returned for an unknown section, a line
+    /// outside of a section or without `=`, or an alias given to two
+    /// different canonical names.
+    pub fn load_from_string(data: &str) -> Result<Self, Error> {
+        let mut registry = Self::default();
+        let mut section: Option<&str> = None;
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            let error = |msg: &str| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", index + 1, msg),
+                )
+            };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = match name.trim().to_lowercase().as_str() {
+                    "engines" | "engine" => Some("engines"),
+                    "runtimes" | "runtime" => Some("runtimes"),
+                    "devs" | "dev" => Some("devs"),
+                    "publis" | "pub" => Some("publis"),
+                    _ => return Err(error("unknown section")),
+                };
+                continue;
+            }
+            let section = section.ok_or_else(|| error("alias outside of a section"))?;
+            let (canonical, aliases) = line
+                .split_once('=')
+                .ok_or_else(|| error("missing '=' after the canonical name"))?;
+            let canonical = canonical.trim();
+            if canonical.is_empty() {
+                return Err(error("empty canonical name"));
+            }
+            let field = match section {
+                "engines" => &mut registry.engines,
+                "runtimes" => &mut registry.runtimes,
+                "devs" => &mut registry.devs,
+                _ => &mut registry.publis,
+            };
+            let aliases = aliases
+                .split(',')
+                .map(|a| a.trim())
+                .filter(|a| !a.is_empty());
+            for alias in std::iter::once(canonical).chain(aliases) {
+                match field.insert(normalize_item(alias), canonical.into()) {
+                    Some(previous) if previous.ne(canonical) => {
+                        return Err(error(&format!(
+                            "\"{}\" is already an alias of \"{}\"",
+                            alias, previous
+                        )))
+                    }
+                    _ => continue,
+                }
+            }
+        }
+        Ok(registry)
+    }
+    /// Returns true if no alias is registered.
+    pub fn is_empty(&self) -> bool {
+        self.engines.is_empty()
+            && self.runtimes.is_empty()
+            && self.devs.is_empty()
+            && self.publis.is_empty()
+    }
+
+    alias_methods!(engines);
+    alias_methods!(runtimes);
+    alias_methods!(devs);
+    alias_methods!(publis);
+}
+
+#[cfg(test)]
+mod aliases_tests {
+    use super::*;
+    #[test]
+    fn test_load_from_string() {
+        let registry = AliasRegistry::load_from_string(
+            "# comment\n\n[Dev]\nDevolver Digital = Devolver,  devolver digital inc \n[engine]\nFNA = fna, FNA/XNA\n",
+        )
+        .unwrap();
+        let devolver = Some("Devolver Digital".to_string());
+        assert_eq!(registry.get_canonical_devs("Devolver"), devolver.as_ref());
+        assert_eq!(
+            registry.get_canonical_devs("DEVOLVER digital"),
+            devolver.as_ref()
+        );
+        assert_eq!(
+            registry.get_canonical_devs("Devolver Digital Inc"),
+            devolver.as_ref()
+        );
+        assert_eq!(
+            registry.get_canonical_engines("fna/xna"),
+            Some(&"FNA".to_string())
+        );
+        assert_eq!(registry.get_canonical_publis("Devolver"), None);
+        assert!(registry.runtimes.is_empty());
+    }
+    #[test]
+    fn test_load_from_string_errors() {
+        for data in [
+            "Devolver = Devolver Digital",
+            "[studios]\nDevolver = Devolver Digital",
+            "[devs]\nDevolver Digital",
+            "[devs]\n = Devolver",
+            "[devs]\nDevolver Digital = Devolver\nDevolver Inc = Devolver",
+        ] {
+            let err = AliasRegistry::load_from_string(data).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+    #[test]
+    fn test_add_alias() {
+        let mut registry = AliasRegistry::new();
+        assert!(registry.is_empty());
+        registry
+            .add_runtimes_alias("fnaify", "FNA")
+            .add_runtimes_alias("fnaify", "fna");
+        assert!(!registry.is_empty());
+        assert_eq!(
+            registry.get_canonical_runtimes("Fnaify"),
+            Some(&"fnaify".to_string())
+        );
+        assert_eq!(
+            registry.get_canonical_runtimes("FNA"),
+            Some(&"fnaify".to_string())
+        );
+    }
+    #[test]
+    fn test_load_from_file_fail() {
+        let err = AliasRegistry::load_from_file("nothere").err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+}
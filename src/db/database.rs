@@ -1,10 +1,13 @@
 //! Provides a queryable representation of the PlayOnBSD database.
+use crate::db::aliases::AliasRegistry;
+use crate::db::index::{normalize_item, IndexEntry};
 use crate::models::Game;
 
 use paste::paste;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 macro_rules! load_game {
     (items: $($item:ident),+; arrays: $($array:ident),+) => {
@@ -14,6 +17,10 @@ macro_rules! load_game {
                 let uid = game.uid;
                 self.add_game(game);
                 let game = self.games.get(&uid).unwrap().clone();
+                self.index_game(&game);
+            }
+            // Add the game to the indices
+            fn index_game(&mut self, game: &Game) {
                 $(
                     if let Some(item) = &game.$item {
                         self.[<add_game_to_ $item s>](&item, game.uid);
@@ -35,11 +42,29 @@ macro_rules! add_game_to {
     ($field:ident) => {
         paste! {
             fn [<add_game_to_ $field>](&mut self, item: &str, game_id: u32) {
-                match self.[<$field>].get_mut(item) {
-                    Some(item) => item.push(game_id),
-                    None => {
-                        let _ = self.[<$field>].insert(item.into(), vec![game_id]);
+                self.[<$field>]
+                    .entry(normalize_item(item))
+                    .or_default()
+                    .add(item, game_id);
+            }
+        }
+    };
+    // The item is indexed under its canonical name if it is a registered alias
+    (aliased $field:ident) => {
+        paste! {
+            fn [<add_game_to_ $field>](&mut self, item: &str, game_id: u32) {
+                match self.aliases.[<get_canonical_ $field>](item) {
+                    Some(canonical) => {
+                        let entry = self.[<$field>]
+                            .entry(normalize_item(canonical))
+                            .or_default();
+                        entry.add(item, game_id);
+                        entry.display = canonical.clone();
                     }
+                    None => self.[<$field>]
+                        .entry(normalize_item(item))
+                        .or_default()
+                        .add(item, game_id),
                 }
             }
         }
@@ -51,21 +76,27 @@ macro_rules! add_game_to {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GameDataBase {
     /// HashMap using the game uid as key and the corresponding game as value
-    pub(crate) games: HashMap<u32, Game>,
-    /// HashMap using the engine name as key and vector of game uid corresponding to said engine as value
-    pub(crate) engines: HashMap<String, Vec<u32>>,
-    /// HashMap using the runtime name as key and vector of game uid corresponding to said engine as value
-    pub(crate) runtimes: HashMap<String, Vec<u32>>,
-    /// HashMap using the genre name as key and vector of game uid corresponding to said engine as value
-    pub(crate) genres: HashMap<String, Vec<u32>>,
-    /// HashMap using the tag name as key and vector of game uid corresponding to said engine as value
-    pub(crate) tags: HashMap<String, Vec<u32>>,
-    /// HashMap using the year as key and vector of game uid corresponding to said engine as value
-    pub(crate) years: HashMap<String, Vec<u32>>,
-    /// HashMap using the dev name as key and vector of game uid corresponding to said engine as value
-    pub(crate) devs: HashMap<String, Vec<u32>>,
-    /// HashMap using the pub name as key and vector of game uid corresponding to said engine as value
-    pub(crate) publis: HashMap<String, Vec<u32>>,
+    pub(crate) games: HashMap<u32, Arc<Game>>,
+    /// HashMap using the normalized engine name as key and the corresponding index entry as value
+    pub(crate) engines: HashMap<String, IndexEntry>,
+    /// HashMap using the normalized runtime name as key and the corresponding index entry as value
+    pub(crate) runtimes: HashMap<String, IndexEntry>,
+    /// HashMap using the normalized genre name as key and the corresponding index entry as value
+    pub(crate) genres: HashMap<String, IndexEntry>,
+    /// HashMap using the normalized tag name as key and the corresponding index entry as value
+    pub(crate) tags: HashMap<String, IndexEntry>,
+    /// HashMap using the year as key and the corresponding index entry as value
+    pub(crate) years: HashMap<String, IndexEntry>,
+    /// HashMap using the normalized dev name as key and the corresponding index entry as value
+    pub(crate) devs: HashMap<String, IndexEntry>,
+    /// HashMap using the normalized pub name as key and the corresponding index entry as value
+    pub(crate) publis: HashMap<String, IndexEntry>,
+    /// Aliases applied when indexing the engines, runtimes, devs and publis
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) aliases: AliasRegistry,
+    /// HashMap using a previous uid of a game as key and its current uid as value
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) uid_aliases: HashMap<u32, u32>,
 }
 
 impl GameDataBase {
@@ -77,20 +108,135 @@ impl GameDataBase {
         }
         db
     }
+    /// Create a database from a vector of games, indexing the engines,
+    /// runtimes, devs and publis under their canonical name according
+    /// to the given [`AliasRegistry`].
+    pub fn with_aliases(games: Vec<Game>, aliases: AliasRegistry) -> Self {
+        let mut db = GameDataBase {
+            aliases,
+            ..Default::default()
+        };
+        for game in games {
+            db.load_game(game);
+        }
+        db
+    }
+    /// Returns the [`AliasRegistry`] used by the database.
+    pub fn get_aliases(&self) -> &AliasRegistry {
+        &self.aliases
+    }
+    /// Replaces the [`AliasRegistry`] used by the database, rebuilding
+    /// the indices accordingly.
+    pub fn set_aliases(&mut self, aliases: AliasRegistry) {
+        if self.aliases.eq(&aliases) {
+            return;
+        }
+        self.aliases = aliases;
+        self.engines.clear();
+        self.runtimes.clear();
+        self.genres.clear();
+        self.tags.clear();
+        self.years.clear();
+        self.devs.clear();
+        self.publis.clear();
+        let mut games: Vec<Arc<Game>> = self.games.values().cloned().collect();
+        games.sort();
+        for game in games {
+            self.index_game(&game);
+        }
+    }
+    /// Registers a previous uid of a game (e.g. before its name or its
+    /// `Added` date were corrected) so that [`GameDataBase::get_game_by_id`]
+    /// returns the game with the current uid when given the previous one.
+    pub fn add_uid_alias(&mut self, previous: u32, current: u32) -> &mut Self {
+        if previous != current {
+            self.uid_aliases.insert(previous, current);
+        }
+        self
+    }
+    /// Returns the previous uids of the games along with their current uids.
+    pub fn get_uid_aliases(&self) -> &HashMap<u32, u32> {
+        &self.uid_aliases
+    }
+    /// Returns the current uid of the game with the given uid, following
+    /// the registered uid aliases, None if no game is found.
+    pub fn resolve_uid(&self, game_id: u32) -> Option<u32> {
+        let mut uid = game_id;
+        // the number of steps is bounded in case of cycle
+        for _ in 0..=self.uid_aliases.len() {
+            if self.games.contains_key(&uid) {
+                return Some(uid);
+            }
+            uid = *self.uid_aliases.get(&uid)?;
+        }
+        None
+    }
+    /// Registers the uids of the games of a previous version of the
+    /// database that are no longer used as aliases of the uids of the
+    /// same games in this database, the games being matched by IGDB id,
+    /// Steam id and finally normalized name. The uid aliases of the
+    /// previous version are kept. Returns the registered aliases.
+    pub fn link_previous_uids(&mut self, previous: &GameDataBase) -> Vec<(u32, u32)> {
+        let mut igdb_ids: HashMap<usize, u32> = HashMap::new();
+        let mut steam_ids: HashMap<usize, u32> = HashMap::new();
+        let mut names: HashMap<String, u32> = HashMap::new();
+        for game in self.games.values() {
+            if let Some(igdb_id) = game.igdb_id {
+                igdb_ids.insert(igdb_id, game.uid);
+            }
+            if let Some(steam_id) = game.get_steam_id() {
+                steam_ids.insert(steam_id, game.uid);
+            }
+            names.insert(game.get_normalized_name(), game.uid);
+        }
+        let mut links = Vec::new();
+        for game in previous.games.values() {
+            if self.games.contains_key(&game.uid) {
+                continue;
+            }
+            let current = game
+                .igdb_id
+                .and_then(|id| igdb_ids.get(&id))
+                .or_else(|| game.get_steam_id().and_then(|id| steam_ids.get(&id)))
+                .or_else(|| names.get(&game.get_normalized_name()));
+            if let Some(current) = current {
+                links.push((game.uid, *current));
+            }
+        }
+        for (old, uid) in &previous.uid_aliases {
+            if self.games.contains_key(old) {
+                continue;
+            }
+            let current = links
+                .iter()
+                .find(|(previous, _)| previous == uid)
+                .map(|(_, current)| *current)
+                .or_else(|| self.resolve_uid(*uid));
+            if let Some(current) = current {
+                links.push((*old, current));
+            }
+        }
+        links.sort_unstable();
+        links.dedup();
+        for (old, current) in &links {
+            self.add_uid_alias(*old, *current);
+        }
+        links
+    }
     load_game!(
         items: engine, runtime, year;
         arrays: devs, publis, genres, tags
     );
 
     fn add_game(&mut self, game: Game) {
-        self.games.insert(game.uid, game);
+        self.games.insert(game.uid, Arc::new(game));
     }
 
     add_game_to!(tags);
-    add_game_to!(engines);
-    add_game_to!(runtimes);
+    add_game_to!(aliased engines);
+    add_game_to!(aliased runtimes);
     add_game_to!(genres);
     add_game_to!(years);
-    add_game_to!(devs);
-    add_game_to!(publis);
+    add_game_to!(aliased devs);
+    add_game_to!(aliased publis);
 }
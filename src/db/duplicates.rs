@@ -0,0 +1,205 @@
+//! Provides a duplicate detection analysis of the [`GameDataBase`], flagging
+//! the games that are likely to be the same game entered more than once.
+//!
+//! Games are flagged when they share:
+//! * the same normalized name (see [`Game::get_normalized_name`]), e.g.
+//!   "The Adventures of Shuggy" and "Adventures of Shuggy",
+//! * the same store id (e.g. the Steam app id) or the same store url,
+//! * the same IGDB id.
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! for group in db.find_duplicates() {
+//!     let names: Vec<&str> = group.games.iter().map(|g| g.name.as_str()).collect();
+//!     println!("{:?}: {:?}", names, group.reasons);
+//! }
+//! ```
+use crate::{Game, GameDataBase, Store};
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Reason why games were flagged as duplicates.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DuplicateReason {
+    /// The games have the same normalized name (given).
+    SameName(String),
+    /// The games have the same id on the given store.
+    SameStoreId(Store, usize),
+    /// The games have the same store url (given).
+    SameStoreUrl(String),
+    /// The games have the same IGDB id (given).
+    SameIgdbId(usize),
+}
+
+impl fmt::Display for DuplicateReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuplicateReason::SameName(name) => write!(f, "same normalized name \"{}\"", name),
+            DuplicateReason::SameStoreId(store, id) => write!(f, "same {} id {}", store, id),
+            DuplicateReason::SameStoreUrl(url) => write!(f, "same store url {}", url),
+            DuplicateReason::SameIgdbId(id) => write!(f, "same IGDB id {}", id),
+        }
+    }
+}
+
+/// Group of games suspected to be duplicates of each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup<'a> {
+    /// The games of the group, ordered by name.
+    pub games: Vec<&'a Game>,
+    /// Why the games were flagged.
+    pub reasons: Vec<DuplicateReason>,
+}
+
+// Normalizes a store url so that trivial variations
+// (scheme, trailing slash, case) are ignored.
+pub(crate) fn normalize_url(url: &str) -> String {
+    let url = url.trim().to_lowercase();
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(&url);
+    let url = url.strip_prefix("www.").unwrap_or(url);
+    url.trim_end_matches('/').to_string()
+}
+
+impl GameDataBase {
+    /// Returns the groups of games suspected to be duplicates, ordered by
+    /// the name of their first game. Games flagged for several reasons
+    /// are reported once with all the reasons.
+    pub fn find_duplicates(&self) -> Vec<DuplicateGroup<'_>> {
+        let mut keys: BTreeMap<DuplicateReason, Vec<&Game>> = BTreeMap::new();
+        for game in self.games.values() {
+            let game = game.as_ref();
+            let mut reasons = vec![DuplicateReason::SameName(game.get_normalized_name())];
+            if let Some(stores) = &game.stores {
+                for link in stores.inner_ref() {
+                    match link.id {
+                        Some(id) => {
+                            reasons.push(DuplicateReason::SameStoreId(link.store.clone(), id))
+                        }
+                        None => {
+                            reasons.push(DuplicateReason::SameStoreUrl(normalize_url(&link.url)))
+                        }
+                    }
+                }
+            }
+            if let Some(igdb_id) = game.igdb_id {
+                reasons.push(DuplicateReason::SameIgdbId(igdb_id));
+            }
+            reasons.sort();
+            reasons.dedup();
+            for reason in reasons {
+                keys.entry(reason).or_default().push(game);
+            }
+        }
+        // Merge the reasons flagging the same set of games
+        let mut groups: BTreeMap<Vec<u32>, DuplicateGroup> = BTreeMap::new();
+        for (reason, mut games) in keys {
+            if games.len() < 2 {
+                continue;
+            }
+            games.sort_by(|a, b| a.cmp(b).then_with(|| a.name.cmp(&b.name)));
+            let mut uids: Vec<u32> = games.iter().map(|game| game.uid).collect();
+            uids.sort();
+            groups
+                .entry(uids)
+                .or_insert_with(|| DuplicateGroup {
+                    games,
+                    reasons: vec![],
+                })
+                .reasons
+                .push(reason);
+        }
+        let mut groups: Vec<DuplicateGroup> = groups.into_values().collect();
+        groups.sort_by(|a, b| a.games.cmp(&b.games));
+        groups
+    }
+}
+
+#[cfg(test)]
+mod duplicates_tests {
+    use super::*;
+    use crate::{StoreLink, StoreLinks};
+
+    fn create_game(uid: u32, name: &str, stores: &[&str], igdb_id: Option<usize>) -> Game {
+        let mut game = Game::new();
+        game.uid = uid;
+        game.name = name.into();
+        if !stores.is_empty() {
+            game.stores = Some(StoreLinks(
+                stores.iter().map(|url| StoreLink::from(url)).collect(),
+            ));
+        }
+        game.igdb_id = igdb_id;
+        game
+    }
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("https://www.gog.com/game/shuggy/"),
+            normalize_url("http://gog.com/game/Shuggy")
+        );
+    }
+    #[test]
+    fn test_no_duplicates() {
+        let db = GameDataBase::new(vec![
+            create_game(1, "Game 1", &["https://www.gog.com/game/game1"], Some(1)),
+            create_game(2, "Game 2", &["https://www.gog.com/game/game2"], Some(2)),
+        ]);
+        assert!(db.find_duplicates().is_empty());
+    }
+    #[test]
+    fn test_duplicates_by_name() {
+        let db = GameDataBase::new(vec![
+            create_game(1, "The Adventures of Shuggy", &[], None),
+            create_game(2, "Adventures of Shuggy", &[], None),
+            create_game(3, "Aeternum", &[], None),
+        ]);
+        let groups = db.find_duplicates();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].games.len(), 2);
+        assert_eq!(
+            groups[0].reasons,
+            vec![DuplicateReason::SameName("adventures of shuggy".into())]
+        );
+    }
+    #[test]
+    fn test_duplicates_by_ids() {
+        let steam = "https://store.steampowered.com/app/211440/Adventures_of_Shuggy/";
+        let db = GameDataBase::new(vec![
+            create_game(1, "Shuggy", &[steam], Some(12)),
+            create_game(2, "Adventures of Shuggy", &[steam], Some(12)),
+            create_game(3, "Shuggy 2", &[], Some(13)),
+            create_game(
+                4,
+                "Shuggy 3",
+                &["https://www.gog.com/game/shuggy"],
+                Some(13),
+            ),
+            create_game(5, "Shuggy 4", &["http://gog.com/game/shuggy/"], None),
+        ]);
+        let groups = db.find_duplicates();
+        assert_eq!(groups.len(), 3);
+        let names: Vec<&str> = groups[0].games.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Adventures of Shuggy", "Shuggy"]);
+        assert_eq!(
+            groups[0].reasons,
+            vec![
+                DuplicateReason::SameStoreId(Store::Steam, 211440),
+                DuplicateReason::SameIgdbId(12)
+            ]
+        );
+        assert_eq!(groups[1].reasons, vec![DuplicateReason::SameIgdbId(13)]);
+        assert_eq!(
+            groups[2].reasons,
+            vec![DuplicateReason::SameStoreUrl("gog.com/game/shuggy".into())]
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! Provides Atom and RSS 2.0 feeds of the games recently added to or
+//! updated in the [`GameDataBase`].
+//!
+//! The entries of the feed are built from the `Added` and `Updated` dates
+//! of the games, the most recent first. When the previous version of the
+//! database is given, the status transition of the updated games is
+//! included in the entries. Each entry links to the page of the game on
+//! the site given in the [`FeedConfig`] and to its store pages, and is
+//! identified by a tag URI built from the host of the site and the uid
+//! of the game.
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::db::feed::FeedConfig;
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! # let old_games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db.old")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let previous = GameDataBase::new(old_games);
+//! let mut config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+//! config.set_max_entries(10);
+//! std::fs::write("atom.xml", db.atom_feed(&config, Some(&previous)))
+//!        .expect("Could not write the feed");
+//! for entry in db.feed_entries(&config, Some(&previous)) {
+//!     println!("{}", entry.summary());
+//! }
+//! ```
+use crate::slug::game_slugs;
+use crate::{Game, GameDataBase, Status};
+
+use chrono::{NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+/// Number of entries of a feed unless set otherwise.
+const DEFAULT_MAX_ENTRIES: usize = 20;
+/// Date of the tag URIs identifying the games in the feeds.
+const TAG_DATE: &str = "2023-01-01";
+
+/// Settings of the generated feeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedConfig {
+    title: String,
+    site_url: String,
+    max_entries: usize,
+    since: Option<NaiveDate>,
+}
+
+impl FeedConfig {
+    /// Creates a [`FeedConfig`] for a feed with the given title and site
+    /// base url (e.g. `https://playonbsd.com`).
+    pub fn new(title: &str, site_url: &str) -> Self {
+        Self {
+            title: title.into(),
+            site_url: site_url.trim_end_matches('/').into(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            since: None,
+        }
+    }
+    /// Sets the maximal number of entries of the feed (20 by default).
+    pub fn set_max_entries(&mut self, max_entries: usize) -> &mut Self {
+        self.max_entries = max_entries;
+        self
+    }
+    /// Only keeps the games added or updated on or after the given date.
+    pub fn set_since(&mut self, since: NaiveDate) -> &mut Self {
+        self.since = Some(since);
+        self
+    }
+    /// Returns the title of the feed.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+    /// Returns the base url of the site, without trailing slash.
+    pub fn site_url(&self) -> &str {
+        &self.site_url
+    }
+    // Returns the stable id of the game in the feeds, a tag URI (RFC 4151)
+    // built from the uid of the game since its page url may change when a
+    // game with the same slug is added
+    fn game_id(&self, game: &Game) -> String {
+        let authority = self
+            .site_url
+            .split_once("://")
+            .map_or(self.site_url.as_str(), |(_, rest)| rest);
+        let authority = authority.split(['/', ':']).next().unwrap_or_default();
+        format!("tag:{},{}:games/{}", authority, TAG_DATE, game.uid)
+    }
+    // Returns the url of the page written by the site generator for the
+    // game with the given slug (see GameDataBase::page_slugs)
+    fn page_url(&self, slug: &str) -> String {
+        format!("{}/games/{}.html", self.site_url, slug)
+    }
+}
+
+/// Kind of change reported by a [`FeedEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedEntryKind {
+    /// The game has been added to the database.
+    Added,
+    /// The game has been updated.
+    Updated,
+}
+
+impl Display for FeedEntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedEntryKind::Added => write!(f, "Added"),
+            FeedEntryKind::Updated => write!(f, "Updated"),
+        }
+    }
+}
+
+/// Game recently added or updated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEntry<'a> {
+    /// The game added or updated.
+    pub game: &'a Game,
+    /// Whether the game has been added or updated.
+    pub kind: FeedEntryKind,
+    /// Date of the change.
+    pub date: NaiveDate,
+    /// Previous and current status of the game when it changed.
+    pub status_change: Option<(Status, Status)>,
+}
+
+impl<'a> FeedEntry<'a> {
+    /// Returns the title of the entry (e.g. "Added: Aeternum").
+    pub fn title(&self) -> String {
+        format!("{}: {}", self.kind, self.game.name)
+    }
+    /// Returns the store urls of the game.
+    pub fn links(&self) -> Vec<&'a str> {
+        match &self.game.stores {
+            Some(stores) => stores.inner_ref().iter().map(|s| s.url.as_str()).collect(),
+            None => vec![],
+        }
+    }
+    /// Returns a one line plain text description of the entry,
+    /// e.g. to be used for an announcement.
+    pub fn summary(&self) -> String {
+        let mut summary = self.title();
+        if let Some(engine) = &self.game.engine {
+            summary.push_str(&format!(" ({})", engine));
+        }
+        match &self.status_change {
+            Some((old, new)) => summary.push_str(&format!(", status: {} -> {}", old, new)),
+            None if self.game.status.status != Status::Unknown => {
+                summary.push_str(&format!(", status: {}", self.game.status.status))
+            }
+            None => {}
+        }
+        if let Some(link) = self.links().first() {
+            summary.push_str(&format!(" {}", link));
+        }
+        summary
+    }
+}
+
+// Escapes a value for XML (or HTML) text and attributes
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Formats a date as required by Atom (RFC 3339)
+fn rfc3339(date: &NaiveDate) -> String {
+    date.format("%Y-%m-%dT00:00:00Z").to_string()
+}
+
+// Formats a date as required by RSS (RFC 822)
+fn rfc822(date: &NaiveDate) -> String {
+    date.format("%a, %d %b %Y 00:00:00 +0000").to_string()
+}
+
+// Returns the HTML description of an entry
+fn entry_content(entry: &FeedEntry) -> String {
+    let game = entry.game;
+    let mut content = String::new();
+    if let Some(engine) = &game.engine {
+        content.push_str(&format!("<p>Engine: {}</p>", xml_escape(engine)));
+    }
+    if let Some(runtime) = &game.runtime {
+        content.push_str(&format!("<p>Runtime: {}</p>", xml_escape(runtime)));
+    }
+    match &entry.status_change {
+        Some((old, new)) => content.push_str(&format!("<p>Status: {} &#8594; {}</p>", old, new)),
+        None => content.push_str(&format!("<p>Status: {}</p>", game.status.status)),
+    }
+    if let Some(message) = &game.status.message {
+        content.push_str(&format!("<p>{}</p>", xml_escape(message)));
+    }
+    let links = entry.links();
+    if !links.is_empty() {
+        content.push_str("<ul>");
+        for link in links {
+            content.push_str(&format!(
+                "<li><a href=\"{0}\">{0}</a></li>",
+                xml_escape(link)
+            ));
+        }
+        content.push_str("</ul>");
+    }
+    content
+}
+
+impl GameDataBase {
+    // Returns the slug of the page of each game, shared by the site
+    // generator and the feeds
+    pub(crate) fn page_slugs(&self) -> HashMap<u32, String> {
+        game_slugs(
+            self.games.values().map(|game| game.as_ref()),
+            &mut HashSet::new(),
+        )
+    }
+    /// Returns the games recently added or updated, the most recent
+    /// first, according to the [`FeedConfig`]. When the previous version
+    /// of the database is given, the status transitions are reported.
+    /// The games whose `Added` and `Updated` dates are unknown are left out.
+    pub fn feed_entries<'a>(
+        &'a self,
+        config: &FeedConfig,
+        previous: Option<&GameDataBase>,
+    ) -> Vec<FeedEntry<'a>> {
+        let mut entries: Vec<FeedEntry<'a>> = self
+            .games
+            .values()
+            .filter_map(|game| {
+                let game = game.as_ref();
+                // the games without known date are left out
+                let (kind, date) = if game.updated > game.added {
+                    (FeedEntryKind::Updated, game.updated.get_date()?)
+                } else {
+                    (FeedEntryKind::Added, game.added.get_date()?)
+                };
+                let status_change = previous
+                    .and_then(|db| db.get_game_by_id(game.uid))
+                    .filter(|old| old.status.status != game.status.status)
+                    .map(|old| (old.status.status.clone(), game.status.status.clone()));
+                Some(FeedEntry {
+                    game,
+                    kind,
+                    date,
+                    status_change,
+                })
+            })
+            .filter(|entry| config.since.map_or(true, |since| entry.date >= since))
+            .collect();
+        entries.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.game.cmp(b.game)));
+        entries.truncate(config.max_entries);
+        entries
+    }
+    /// Returns an Atom feed of the games recently added or updated
+    /// (see [`GameDataBase::feed_entries`]).
+    pub fn atom_feed(&self, config: &FeedConfig, previous: Option<&GameDataBase>) -> String {
+        let entries = self.feed_entries(config, previous);
+        let slugs = self.page_slugs();
+        // the generation date when there is no entry
+        let updated = entries
+            .first()
+            .map(|entry| entry.date)
+            .unwrap_or_else(|| Utc::now().date_naive());
+        let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        feed.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        feed.push_str(&format!("  <title>{}</title>\n", xml_escape(&config.title)));
+        feed.push_str(&format!(
+            "  <link href=\"{}/\"/>\n",
+            xml_escape(&config.site_url)
+        ));
+        feed.push_str(&format!("  <id>{}/</id>\n", xml_escape(&config.site_url)));
+        feed.push_str(&format!("  <updated>{}</updated>\n", rfc3339(&updated)));
+        feed.push_str("  <generator>libpobsd</generator>\n");
+        for entry in &entries {
+            feed.push_str("  <entry>\n");
+            feed.push_str(&format!(
+                "    <title>{}</title>\n",
+                xml_escape(&entry.title())
+            ));
+            feed.push_str(&format!(
+                "    <id>{}</id>\n",
+                xml_escape(&config.game_id(entry.game))
+            ));
+            feed.push_str(&format!(
+                "    <updated>{}</updated>\n",
+                rfc3339(&entry.date)
+            ));
+            if let Some(added) = entry.game.added.get_date() {
+                feed.push_str(&format!("    <published>{}</published>\n", rfc3339(&added)));
+            }
+            feed.push_str(&format!(
+                "    <link rel=\"alternate\" href=\"{}\"/>\n",
+                xml_escape(&config.page_url(&slugs[&entry.game.uid]))
+            ));
+            for link in entry.links() {
+                feed.push_str(&format!(
+                    "    <link rel=\"related\" href=\"{}\"/>\n",
+                    xml_escape(link)
+                ));
+            }
+            if let Some(devs) = &entry.game.devs {
+                for dev in devs {
+                    feed.push_str(&format!(
+                        "    <author><name>{}</name></author>\n",
+                        xml_escape(dev)
+                    ));
+                }
+            }
+            feed.push_str(&format!(
+                "    <summary>{}</summary>\n",
+                xml_escape(&entry.summary())
+            ));
+            feed.push_str(&format!(
+                "    <content type=\"html\">{}</content>\n",
+                xml_escape(&entry_content(entry))
+            ));
+            feed.push_str("  </entry>\n");
+        }
+        feed.push_str("</feed>\n");
+        feed
+    }
+    /// Returns a RSS 2.0 feed of the games recently added or updated
+    /// (see [`GameDataBase::feed_entries`]).
+    pub fn rss_feed(&self, config: &FeedConfig, previous: Option<&GameDataBase>) -> String {
+        let entries = self.feed_entries(config, previous);
+        let slugs = self.page_slugs();
+        let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        feed.push_str("<rss version=\"2.0\">\n<channel>\n");
+        feed.push_str(&format!("  <title>{}</title>\n", xml_escape(&config.title)));
+        feed.push_str(&format!(
+            "  <link>{}/</link>\n",
+            xml_escape(&config.site_url)
+        ));
+        feed.push_str(&format!(
+            "  <description>{}</description>\n",
+            xml_escape(&config.title)
+        ));
+        if let Some(entry) = entries.first() {
+            feed.push_str(&format!(
+                "  <lastBuildDate>{}</lastBuildDate>\n",
+                rfc822(&entry.date)
+            ));
+        }
+        feed.push_str("  <generator>libpobsd</generator>\n");
+        for entry in &entries {
+            let link = entry
+                .links()
+                .first()
+                .map(|link| link.to_string())
+                .unwrap_or_else(|| config.page_url(&slugs[&entry.game.uid]));
+            feed.push_str("  <item>\n");
+            feed.push_str(&format!(
+                "    <title>{}</title>\n",
+                xml_escape(&entry.title())
+            ));
+            feed.push_str(&format!("    <link>{}</link>\n", xml_escape(&link)));
+            feed.push_str(&format!(
+                "    <guid isPermaLink=\"false\">{}#{}</guid>\n",
+                xml_escape(&config.game_id(entry.game)),
+                entry.date
+            ));
+            feed.push_str(&format!("    <pubDate>{}</pubDate>\n", rfc822(&entry.date)));
+            feed.push_str(&format!(
+                "    <description>{}</description>\n",
+                xml_escape(&entry_content(entry))
+            ));
+            feed.push_str("  </item>\n");
+        }
+        feed.push_str("</channel>\n</rss>\n");
+        feed
+    }
+}
+
+#[cfg(test)]
+mod feed_tests {
+    use super::*;
+    use crate::models::{GameDate, GameStatus, StoreLink, StoreLinks};
+    use crate::SearchType;
+
+    fn create_game(name: &str, added: &str, updated: &str) -> Game {
+        let mut game = Game::new();
+        game.name = name.into();
+        game.uid = name.len() as u32;
+        game.added = GameDate::from_value(Some(added));
+        game.updated = GameDate::from_value(Some(updated));
+        game
+    }
+    fn create_db() -> GameDataBase {
+        let mut game1 = create_game("Game & Co", "2023-01-02", "2023-01-02");
+        game1.stores = Some(StoreLinks(vec![StoreLink::from(
+            "https://store.steampowered.com/app/211440/Game/",
+        )]));
+        let mut game2 = create_game("Other game", "2022-05-01", "2023-03-04");
+        game2.status = GameStatus::new(Status::Completable, None);
+        let game3 = create_game("Old", "2020-01-01", "2020-01-01");
+        GameDataBase::new(vec![game1, game2, game3])
+    }
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(xml_escape("a<b>&\"'"), "a&lt;b&gt;&amp;&quot;&apos;");
+    }
+    #[test]
+    fn test_dates() {
+        let date = NaiveDate::parse_from_str("2023-01-04", "%Y-%m-%d").unwrap();
+        assert_eq!(rfc3339(&date), "2023-01-04T00:00:00Z");
+        assert_eq!(rfc822(&date), "Wed, 04 Jan 2023 00:00:00 +0000");
+    }
+    #[test]
+    fn test_feed_entries() {
+        let db = create_db();
+        let mut config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com/");
+        config.set_max_entries(2);
+        let entries = db.feed_entries(&config, None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].game.name, "Other game");
+        assert_eq!(entries[0].kind, FeedEntryKind::Updated);
+        assert_eq!(entries[1].game.name, "Game & Co");
+        assert_eq!(entries[1].kind, FeedEntryKind::Added);
+        assert_eq!(
+            config.page_url(&db.page_slugs()[&entries[1].game.uid]),
+            "https://playonbsd.com/games/game-co.html"
+        );
+        assert_eq!(
+            config.game_id(entries[1].game),
+            format!("tag:playonbsd.com,2023-01-01:games/{}", entries[1].game.uid)
+        );
+    }
+    #[test]
+    fn test_feed_entries_since() {
+        let db = create_db();
+        let mut config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+        config.set_since(NaiveDate::parse_from_str("2021-01-01", "%Y-%m-%d").unwrap());
+        assert_eq!(db.feed_entries(&config, None).len(), 2);
+    }
+    #[test]
+    fn test_feed_entries_status_change() {
+        let db = create_db();
+        let mut old_game = create_game("Other game", "2022-05-01", "2022-05-01");
+        old_game.status = GameStatus::new(Status::MajorBugs, None);
+        let previous = GameDataBase::new(vec![old_game]);
+        let config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+        let entries = db.feed_entries(&config, Some(&previous));
+        assert_eq!(
+            entries[0].status_change,
+            Some((Status::MajorBugs, Status::Completable))
+        );
+        assert_eq!(
+            entries[0].summary(),
+            "Updated: Other game, status: majorbugs -> completable"
+        );
+        assert_eq!(entries[1].status_change, None);
+    }
+    #[test]
+    fn test_atom_feed() {
+        let db = create_db();
+        let config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+        let feed = db.atom_feed(&config, None);
+        assert!(feed.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert!(feed.contains("<updated>2023-03-04T00:00:00Z</updated>"));
+        assert!(feed.contains("<title>Added: Game &amp; Co</title>"));
+        let uid = db
+            .get_game_by_name("Game & Co", &SearchType::CaseSensitive)
+            .unwrap()
+            .uid;
+        assert!(feed.contains(&format!(
+            "<id>tag:playonbsd.com,2023-01-01:games/{}</id>",
+            uid
+        )));
+        assert!(feed.contains("href=\"https://playonbsd.com/games/game-co.html\""));
+        assert!(feed.contains(
+            "<link rel=\"related\" href=\"https://store.steampowered.com/app/211440/Game/\"/>"
+        ));
+        assert_eq!(feed.matches("<entry>").count(), 3);
+    }
+    #[test]
+    fn test_feed_without_known_dates() {
+        let mut game1 = create_game("Unknown", "1970-01-01", "1970-01-01");
+        game1.added = GameDate::Invalid("2023-13-01".into());
+        let game2 = create_game("Updated only", "1970-01-01", "2023-02-01");
+        let db = GameDataBase::new(vec![game1.clone(), game2]);
+        let config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+        let entries = db.feed_entries(&config, None);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, FeedEntryKind::Updated);
+        let feed = db.atom_feed(&config, None);
+        assert!(!feed.contains("1970"));
+        assert!(!feed.contains("<published>"));
+        // an empty feed is dated from its generation
+        let feed = GameDataBase::new(vec![game1]).atom_feed(&config, None);
+        let today = rfc3339(&Utc::now().date_naive());
+        assert!(feed.contains(&format!("<updated>{}</updated>", today)));
+        assert_eq!(feed.matches("<entry>").count(), 0);
+    }
+    #[test]
+    fn test_rss_feed() {
+        let db = create_db();
+        let config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+        let feed = db.rss_feed(&config, None);
+        assert!(feed.contains("<rss version=\"2.0\">"));
+        assert!(feed.contains("<lastBuildDate>Sat, 04 Mar 2023 00:00:00 +0000</lastBuildDate>"));
+        assert!(feed.contains("<link>https://store.steampowered.com/app/211440/Game/</link>"));
+        assert!(feed.contains("<link>https://playonbsd.com/games/other-game.html</link>"));
+        assert_eq!(feed.matches("<item>").count(), 3);
+    }
+}
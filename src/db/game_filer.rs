@@ -1,5 +1,6 @@
 //! Provide a [`GameFilter`] struct than can be used to filter games
 //! based on the value of their fields.
+use crate::db::matcher::Matcher;
 use crate::{Game, SearchType, Status};
 
 use paste::paste;
@@ -18,6 +19,18 @@ macro_rules! gf_setter {
     };
 }
 
+// Patterns of a GameFilter prepared for a given SearchType
+struct FilterMatchers {
+    name: Option<Matcher>,
+    engine: Option<Matcher>,
+    runtime: Option<Matcher>,
+    genre: Option<Matcher>,
+    tag: Option<Matcher>,
+    year: Option<Matcher>,
+    dev: Option<Matcher>,
+    publi: Option<Matcher>,
+}
+
 #[derive(Clone, Default, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Allows for easy game filtering based on field values.
@@ -91,40 +104,69 @@ impl GameFilter {
         //game: impl AsRef<Game>,
         search_type: &SearchType,
     ) -> bool {
-        let check_name = match &self.name {
-            Some(name) => game.as_ref().name_contains(name, search_type),
+        self.check_game_with(game.as_ref(), &self.matchers(search_type))
+    }
+    /// Filter a vector of [`Game`] based on the filtering rules.
+    pub fn filter_games<T: AsRef<Game>>(&self, games: Vec<T>, search_type: &SearchType) -> Vec<T> {
+        let matchers = self.matchers(search_type);
+        games
+            .into_iter()
+            .filter(|x| self.check_game_with(x.as_ref(), &matchers))
+            .collect()
+    }
+    // prepares the patterns once for all the checked games
+    fn matchers(&self, search_type: &SearchType) -> FilterMatchers {
+        let matcher = |pattern: &Option<String>| {
+            pattern
+                .as_ref()
+                .map(|pattern| Matcher::new(search_type, pattern))
+        };
+        FilterMatchers {
+            name: matcher(&self.name),
+            engine: matcher(&self.engine),
+            runtime: matcher(&self.runtime),
+            genre: matcher(&self.genre),
+            tag: matcher(&self.tag),
+            year: matcher(&self.year),
+            dev: matcher(&self.dev),
+            publi: matcher(&self.publi),
+        }
+    }
+    fn check_game_with(&self, game: &Game, matchers: &FilterMatchers) -> bool {
+        let check_name = match &matchers.name {
+            Some(name) => game.name_matches(name),
             None => false,
         };
-        let check_engine = match &self.engine {
-            Some(engine) => game.as_ref().engine_contains(engine, search_type),
+        let check_engine = match &matchers.engine {
+            Some(engine) => game.engine_matches(engine),
             None => false,
         };
-        let check_runtime = match &self.runtime {
-            Some(runtime) => game.as_ref().runtime_contains(runtime, search_type),
+        let check_runtime = match &matchers.runtime {
+            Some(runtime) => game.runtime_matches(runtime),
             None => false,
         };
-        let check_genre = match &self.genre {
-            Some(genre) => game.as_ref().genres_contains(genre, search_type),
+        let check_genre = match &matchers.genre {
+            Some(genre) => game.genres_matches(genre),
             None => false,
         };
-        let check_tag = match &self.tag {
-            Some(tag) => game.as_ref().tags_contains(tag, search_type),
+        let check_tag = match &matchers.tag {
+            Some(tag) => game.tags_matches(tag),
             None => false,
         };
-        let check_year = match &self.year {
-            Some(year) => game.as_ref().year_contains(year, search_type),
+        let check_year = match &matchers.year {
+            Some(year) => game.year_matches(year),
             None => false,
         };
-        let check_dev = match &self.dev {
-            Some(dev) => game.as_ref().devs_contains(dev, search_type),
+        let check_dev = match &matchers.dev {
+            Some(dev) => game.devs_matches(dev),
             None => false,
         };
-        let check_publi = match &self.publi {
-            Some(publi) => game.as_ref().publis_contains(publi, search_type),
+        let check_publi = match &matchers.publi {
+            Some(publi) => game.publis_matches(publi),
             None => false,
         };
         let check_status = match &self.status {
-            Some(status) => game.as_ref().status_is(status),
+            Some(status) => game.status_is(status),
             None => false,
         };
         check_name
@@ -137,13 +179,6 @@ impl GameFilter {
             || check_publi
             || check_status
     }
-    /// Filter a vector of [`Game`] based on the filtering rules.
-    pub fn filter_games<T: AsRef<Game>>(&self, games: Vec<T>, search_type: &SearchType) -> Vec<T> {
-        games
-            .into_iter()
-            .filter(|x| self.check_game(x, search_type))
-            .collect()
-    }
     /// Check if at least one field of the [`GameFilter`] is different from None.
     pub fn is_empty(&self) -> bool {
         self.name.is_none()
@@ -164,7 +199,7 @@ mod game_tests {
         game_status::GameStatus,
         store_links::{StoreLink, StoreLinks},
     };
-    use chrono::NaiveDate;
+    use crate::GameDate;
 
     use super::*;
     fn create_game() -> Game {
@@ -178,7 +213,7 @@ mod game_tests {
         game.name = "game name".to_string();
         game.cover = Some("cover.jpg".to_string());
         game.engine = Some("game engine".to_string());
-        game.setup = Some("game setup".to_string());
+        game.setup = Some("game setup".into());
         game.runtime = Some("game runtime".to_string());
         game.stores = Some(stores);
         game.hints = Some("game hints".to_string());
@@ -192,8 +227,8 @@ mod game_tests {
             crate::models::game_status::Status::Unknown,
             Some("game status".to_string()),
         );
-        game.added = NaiveDate::parse_from_str("2012-12-03", "%Y-%m-%d").unwrap();
-        game.updated = NaiveDate::parse_from_str("2014-12-03", "%Y-%m-%d").unwrap();
+        game.added = GameDate::from_value(Some("2012-12-03"));
+        game.updated = GameDate::from_value(Some("2014-12-03"));
         game
     }
     #[test]
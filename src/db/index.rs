@@ -0,0 +1,134 @@
+//! Provides the entries of the item indices (engines, tags, etc.) of the
+//! [`crate::GameDataBase`]. Items are indexed by a normalized form (trimmed
+//! and case folded) so that "godot", "Godot" and "Godot " end up in the same
+//! entry, each entry keeping track of the raw spellings it collapses.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Returns the normalized form of an item used as key in the indices.
+///
+/// The item is case folded by uppercasing it before lowercasing it, so
+/// that spellings differing under Unicode full case folding are merged
+/// (e.g. "Straße" and "STRASSE", or the final and medial Greek sigma).
+/// Unlike the Unicode folding, the dotless i is folded to i.
+pub(crate) fn normalize_item(item: &str) -> String {
+    item.trim().to_uppercase().to_lowercase()
+}
+
+/// Entry of an item index.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct IndexEntry {
+    /// Most common spelling of the item (trimmed), used for display.
+    pub(crate) display: String,
+    /// Raw spellings of the item, in the order they were first seen,
+    /// with the ids of the games using each spelling.
+    pub(crate) spellings: Vec<(String, Vec<u32>)>,
+}
+
+impl IndexEntry {
+    /// Adds a game using the given spelling of the item.
+    pub(crate) fn add(&mut self, spelling: &str, game_id: u32) {
+        match self.spellings.iter_mut().find(|(s, _)| s.eq(spelling)) {
+            Some((_, ids)) => {
+                if !ids.contains(&game_id) {
+                    ids.push(game_id);
+                }
+            }
+            None => self.spellings.push((spelling.into(), vec![game_id])),
+        }
+        self.update_display();
+    }
+    // The most common spelling wins, the first seen in case of a tie.
+    fn update_display(&mut self) {
+        let mut best: Option<&(String, Vec<u32>)> = None;
+        for spelling in &self.spellings {
+            if best.map_or(true, |best| spelling.1.len() > best.1.len()) {
+                best = Some(spelling);
+            }
+        }
+        if let Some((spelling, _)) = best {
+            self.display = spelling.trim().into();
+        }
+    }
+    /// Returns the ids of the games associated to the item, whatever
+    /// the spelling they use.
+    pub(crate) fn game_ids(&self) -> Vec<u32> {
+        let mut game_ids: Vec<u32> = Vec::new();
+        for (_, ids) in &self.spellings {
+            for id in ids {
+                if !game_ids.contains(id) {
+                    game_ids.push(*id);
+                }
+            }
+        }
+        game_ids
+    }
+    /// Returns the ids of the games using exactly the given spelling
+    /// of the item (leading and trailing whitespaces being ignored).
+    pub(crate) fn game_ids_with_spelling(&self, spelling: &str) -> Vec<u32> {
+        let mut game_ids: Vec<u32> = Vec::new();
+        for (_, ids) in self
+            .spellings
+            .iter()
+            .filter(|(s, _)| s.trim().eq(spelling.trim()))
+        {
+            for id in ids {
+                if !game_ids.contains(id) {
+                    game_ids.push(*id);
+                }
+            }
+        }
+        game_ids
+    }
+}
+
+/// Lists the raw spellings of an item that were collapsed into a single
+/// index entry, as returned by [`crate::GameDataBase::get_collapsed_spellings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollapsedSpellings {
+    /// Name of the indexed field ("engines", "tags", etc.).
+    pub field: &'static str,
+    /// Spelling used to display the item (the most common one).
+    pub display: String,
+    /// Raw spellings collapsed together with the number of games using each.
+    pub spellings: Vec<(String, usize)>,
+}
+
+#[cfg(test)]
+mod index_tests {
+    use super::*;
+    #[test]
+    fn test_normalize_item() {
+        assert_eq!(normalize_item("Godot "), "godot");
+        assert_eq!(normalize_item(" GODOT"), "godot");
+        assert_eq!(normalize_item("Étoile"), "étoile");
+        assert_eq!(normalize_item("Straße"), normalize_item("STRASSE"));
+        assert_eq!(normalize_item("ﬁre"), "fire");
+        assert_eq!(normalize_item("ΣΟΦΟΣ"), normalize_item("σοφος"));
+    }
+    #[test]
+    fn test_index_entry_display() {
+        let mut entry = IndexEntry::default();
+        entry.add("godot", 1);
+        assert_eq!(entry.display, "godot");
+        entry.add("Godot ", 2);
+        // tie, the first seen wins
+        assert_eq!(entry.display, "godot");
+        entry.add("Godot ", 3);
+        assert_eq!(entry.display, "Godot");
+        assert_eq!(entry.spellings.len(), 2);
+    }
+    #[test]
+    fn test_index_entry_game_ids() {
+        let mut entry = IndexEntry::default();
+        entry.add("godot", 1);
+        entry.add("Godot", 2);
+        entry.add("Godot", 2);
+        entry.add("godot", 2);
+        assert_eq!(entry.game_ids(), vec![1, 2]);
+        assert_eq!(entry.game_ids_with_spelling("Godot"), vec![2]);
+        assert_eq!(entry.game_ids_with_spelling("godot "), vec![1, 2]);
+        assert!(entry.game_ids_with_spelling("GODOT").is_empty());
+    }
+}
@@ -0,0 +1,445 @@
+//! Provides importers for the game libraries exported from the stores
+//! and a report of the games of a [`Library`] listed in the [`GameDataBase`].
+//!
+//! The following exports are supported:
+//! * a list of Steam app ids, one per line, optionally followed by the title
+//!   of the game (e.g. `211440 Adventures of Shuggy`),
+//! * a GOG game list, one game per line, each line being either a game name
+//!   as listed by `lgogdownloader --list` (e.g. `adventures_of_shuggy`) or a
+//!   title as exported from GOG Galaxy,
+//! * an itch.io JSON export as produced by butler, any object with a `title`
+//!   (and optionally an `url`) being considered as a game (requires the
+//!   `library` feature).
+//!
+//! Each entry is matched to a game using its store id or url and, as a
+//! fallback, its title: normalized titles are compared first (see
+//! [`Game::get_normalized_name`]) and then the closest title within a small
+//! edit distance is used, short titles having to match exactly.
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::db::library::{Library, LibraryFormat};
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let library = Library::load_from_file("steam.txt", LibraryFormat::SteamAppIds)
+//!        .expect("Invalid library export");
+//! let report = db.match_library(&library);
+//! for matched in &report.matched {
+//!     println!("{}: {}", matched.game.name, matched.game.status);
+//! }
+//! ```
+use crate::models::game::normalize_name;
+use crate::{Game, GameDataBase, Store};
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Error;
+use std::path::Path;
+
+/// Maximal edit distance between two normalized titles, per 10 characters,
+/// for them to be considered as fuzzy matching. The titles of less than
+/// 10 characters therefore have to match exactly.
+const FUZZY_DISTANCE_PER_10_CHARS: usize = 1;
+
+/// Format of a library export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibraryFormat {
+    /// List of Steam app ids.
+    SteamAppIds,
+    /// GOG Galaxy or lgogdownloader game list.
+    Gog,
+    /// itch.io butler JSON export. Loading it requires the `library`
+    /// feature, an [`std::io::ErrorKind::Unsupported`] error being
+    /// returned otherwise.
+    ItchJson,
+}
+
+/// Game listed in a library export.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryEntry {
+    /// Store the game comes from.
+    pub store: Store,
+    /// Id of the game on the store (Steam app id).
+    pub id: Option<usize>,
+    /// Store url of the game.
+    pub url: Option<String>,
+    /// Game name on the store (e.g. the lgogdownloader game name).
+    pub slug: Option<String>,
+    /// Title of the game.
+    pub title: Option<String>,
+}
+
+impl LibraryEntry {
+    /// Returns a name describing the entry (its title, slug, url or id).
+    pub fn label(&self) -> String {
+        self.title
+            .clone()
+            .or_else(|| self.slug.clone())
+            .or_else(|| self.url.clone())
+            .or_else(|| self.id.map(|id| id.to_string()))
+            .unwrap_or_default()
+    }
+}
+
+/// Collection of [`LibraryEntry`] imported from a library export.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Library {
+    /// The games of the library.
+    pub entries: Vec<LibraryEntry>,
+}
+
+// Returns the non-empty lines that are not comments
+fn content_lines(data: &str) -> impl Iterator<Item = &str> {
+    data.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+impl Library {
+    /// Loads a [`Library`] from a file with the given format.
+    pub fn load_from_file(file: impl AsRef<Path>, format: LibraryFormat) -> Result<Self, Error> {
+        let data = fs::read_to_string(file)?;
+        match format {
+            LibraryFormat::SteamAppIds => Ok(Self::from_steam_app_ids(&data)),
+            LibraryFormat::Gog => Ok(Self::from_gog_list(&data)),
+            #[cfg(feature = "library")]
+            LibraryFormat::ItchJson => Self::from_itch_json(&data),
+            #[cfg(not(feature = "library"))]
+            LibraryFormat::ItchJson => Err(Error::new(
+                std::io::ErrorKind::Unsupported,
+                "The itch.io export requires the library feature",
+            )),
+        }
+    }
+    /// Imports a list of Steam app ids, one per line, optionally followed
+    /// by the title of the game. Lines not starting with an app id are
+    /// considered as titles.
+    pub fn from_steam_app_ids(data: &str) -> Self {
+        let entries = content_lines(data)
+            .map(|line| {
+                let (id, title) = match line.split_once(|c: char| c.is_whitespace() || c == ',') {
+                    Some((id, title)) => (id, Some(title.trim())),
+                    None => (line, None),
+                };
+                match id.parse::<usize>() {
+                    Ok(id) => LibraryEntry {
+                        store: Store::Steam,
+                        id: Some(id),
+                        title: title.filter(|t| !t.is_empty()).map(String::from),
+                        ..Default::default()
+                    },
+                    Err(_) => LibraryEntry {
+                        store: Store::Steam,
+                        title: Some(line.into()),
+                        ..Default::default()
+                    },
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+    /// Imports a GOG game list, one game per line. Lines without whitespace
+    /// are considered as lgogdownloader game names (also used as titles),
+    /// other lines as titles.
+    pub fn from_gog_list(data: &str) -> Self {
+        let entries = content_lines(data)
+            .map(|line| LibraryEntry {
+                store: Store::Gog,
+                slug: match line.contains(char::is_whitespace) {
+                    true => None,
+                    false => Some(line.into()),
+                },
+                title: Some(line.into()),
+                ..Default::default()
+            })
+            .collect();
+        Self { entries }
+    }
+    /// Imports an itch.io butler JSON export, every object with a `title`
+    /// being considered as a game.
+    #[cfg(feature = "library")]
+    pub fn from_itch_json(data: &str) -> Result<Self, Error> {
+        fn collect(value: &serde_json::Value, entries: &mut Vec<LibraryEntry>) {
+            match value {
+                serde_json::Value::Object(map) => match map.get("title") {
+                    Some(serde_json::Value::String(title)) => entries.push(LibraryEntry {
+                        store: Store::ItchIo,
+                        url: map.get("url").and_then(|u| u.as_str()).map(String::from),
+                        title: Some(title.clone()),
+                        ..Default::default()
+                    }),
+                    _ => map.values().for_each(|value| collect(value, entries)),
+                },
+                serde_json::Value::Array(values) => {
+                    values.iter().for_each(|value| collect(value, entries))
+                }
+                _ => {}
+            }
+        }
+        let value: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        let mut entries = Vec::new();
+        collect(&value, &mut entries);
+        Ok(Self { entries })
+    }
+}
+
+/// How a [`LibraryEntry`] was matched to a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMethod {
+    /// Same store id.
+    StoreId,
+    /// Same store url or game name on the store.
+    StoreUrl,
+    /// Same normalized title.
+    Name,
+    /// Close normalized title, with the edit distance.
+    FuzzyName(usize),
+}
+
+/// A [`LibraryEntry`] matched to a game of the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryMatch<'a> {
+    /// The entry of the library.
+    pub entry: LibraryEntry,
+    /// The matching game, whose status tells how well it runs.
+    pub game: &'a Game,
+    /// How the entry was matched.
+    pub method: MatchMethod,
+}
+
+/// Report of the games of a [`Library`] listed in the database,
+/// as returned by [`GameDataBase::match_library`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryReport<'a> {
+    /// Entries matched to a game, ordered by game.
+    pub matched: Vec<LibraryMatch<'a>>,
+    /// Entries not matched to any game.
+    pub unmatched: Vec<LibraryEntry>,
+}
+
+impl LibraryReport<'_> {
+    /// Returns the labels of the entries not matched to any game.
+    pub fn unmatched_titles(&self) -> Vec<String> {
+        self.unmatched.iter().map(|entry| entry.label()).collect()
+    }
+}
+
+// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = match ca == *cb {
+                true => previous,
+                false => 1 + previous.min(row[j]).min(current),
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+impl GameDataBase {
+    // Returns the game with the given normalized title or, failing that,
+    // the game with the closest title if it is close enough and unique.
+    fn match_title<'a>(
+        &'a self,
+        names: &HashMap<String, &'a Game>,
+        title: &str,
+    ) -> Option<(&'a Game, MatchMethod)> {
+        let title = normalize_name(title);
+        if title.is_empty() {
+            return None;
+        }
+        if let Some(game) = names.get(&title) {
+            return Some((game, MatchMethod::Name));
+        }
+        let max_distance = title.chars().count() / 10 * FUZZY_DISTANCE_PER_10_CHARS;
+        if max_distance == 0 {
+            return None;
+        }
+        let mut best: Option<(usize, &Game)> = None;
+        let mut ambiguous = false;
+        for (name, game) in names {
+            let distance = edit_distance(&title, name);
+            if distance > max_distance {
+                continue;
+            }
+            match best {
+                Some((best_distance, _)) if distance > best_distance => {}
+                Some((best_distance, _)) if distance == best_distance => ambiguous = true,
+                _ => {
+                    best = Some((distance, game));
+                    ambiguous = false;
+                }
+            }
+        }
+        match (best, ambiguous) {
+            (Some((distance, game)), false) => Some((game, MatchMethod::FuzzyName(distance))),
+            _ => None,
+        }
+    }
+    /// Matches the entries of the [`Library`] to the games of the database
+    /// using their store id, store url or game name on the store and, as a
+    /// fallback, their title.
+    pub fn match_library(&self, library: &Library) -> LibraryReport<'_> {
+        let names: HashMap<String, &Game> = self
+            .games
+            .values()
+            .map(|game| (game.get_normalized_name(), game.as_ref()))
+            .collect();
+        let mut report = LibraryReport::default();
+        for entry in &library.entries {
+            let found = entry
+                .id
+                .and_then(|id| self.get_game_by_store_id(&entry.store, id))
+                .map(|game| (game, MatchMethod::StoreId))
+                .or_else(|| {
+                    entry
+                        .url
+                        .as_ref()
+                        .and_then(|url| self.get_game_by_store_url(url))
+                        .or_else(|| {
+                            entry
+                                .slug
+                                .as_ref()
+                                .and_then(|slug| self.get_game_by_store_slug(&entry.store, slug))
+                        })
+                        .map(|game| (game, MatchMethod::StoreUrl))
+                })
+                .or_else(|| {
+                    entry
+                        .title
+                        .as_ref()
+                        .and_then(|title| self.match_title(&names, title))
+                });
+            match found {
+                Some((game, method)) => report.matched.push(LibraryMatch {
+                    entry: entry.clone(),
+                    game,
+                    method,
+                }),
+                None => report.unmatched.push(entry.clone()),
+            }
+        }
+        report.matched.sort_by(|a, b| {
+            a.game
+                .cmp(b.game)
+                .then_with(|| a.game.name.cmp(&b.game.name))
+        });
+        report
+    }
+}
+
+#[cfg(test)]
+mod library_tests {
+    use super::*;
+    use crate::{StoreLink, StoreLinks};
+
+    fn create_db() -> GameDataBase {
+        let mut game1 = Game::new();
+        game1.uid = 1;
+        game1.name = "The Adventures of Shuggy".into();
+        game1.stores = Some(StoreLinks(vec![
+            StoreLink::from("https://store.steampowered.com/app/211440/Adventures_of_Shuggy/"),
+            StoreLink::from("https://www.gog.com/game/adventures_of_shuggy"),
+        ]));
+        let mut game2 = Game::new();
+        game2.uid = 2;
+        game2.name = "Aeternum".into();
+        game2.stores = Some(StoreLinks(vec![StoreLink::from(
+            "https://creakylantern.itch.io/aeternum",
+        )]));
+        let mut game3 = Game::new();
+        game3.uid = 3;
+        game3.name = "Airships: Conquer the Skies".into();
+        GameDataBase::new(vec![game1, game2, game3])
+    }
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+    }
+    #[test]
+    fn test_from_steam_app_ids() {
+        let library =
+            Library::from_steam_app_ids("# my games\n211440 Adventures of Shuggy\n42\nAeternum\n");
+        assert_eq!(library.entries.len(), 3);
+        assert_eq!(library.entries[0].id, Some(211440));
+        assert_eq!(
+            library.entries[0].title,
+            Some("Adventures of Shuggy".into())
+        );
+        assert_eq!(library.entries[1].title, None);
+        assert_eq!(library.entries[2].id, None);
+    }
+    #[test]
+    fn test_from_gog_list() {
+        let library = Library::from_gog_list("adventures_of_shuggy\nAirships: Conquer the Skies\n");
+        assert_eq!(library.entries[0].slug, Some("adventures_of_shuggy".into()));
+        assert_eq!(library.entries[1].slug, None);
+    }
+    #[test]
+    fn test_match_library() {
+        let db = create_db();
+        let mut library = Library::from_steam_app_ids("211440\n42\n");
+        library
+            .entries
+            .extend(Library::from_gog_list("Airship: Conquer the Skies\nUnknown Game\n").entries);
+        library.entries.push(LibraryEntry {
+            store: Store::ItchIo,
+            url: Some("https://creakylantern.itch.io/aeternum/".into()),
+            ..Default::default()
+        });
+        let report = db.match_library(&library);
+        let matched: Vec<(&str, MatchMethod)> = report
+            .matched
+            .iter()
+            .map(|m| (m.game.name.as_str(), m.method))
+            .collect();
+        assert_eq!(
+            matched,
+            vec![
+                ("The Adventures of Shuggy", MatchMethod::StoreId),
+                ("Aeternum", MatchMethod::StoreUrl),
+                ("Airships: Conquer the Skies", MatchMethod::FuzzyName(1)),
+            ]
+        );
+        assert_eq!(report.unmatched_titles(), vec!["42", "Unknown Game"]);
+    }
+    #[test]
+    fn test_match_library_by_gog_name() {
+        let db = create_db();
+        let report = db.match_library(&Library::from_gog_list("adventures-of-shuggy"));
+        assert_eq!(report.matched[0].method, MatchMethod::StoreUrl);
+    }
+    #[cfg(feature = "library")]
+    #[test]
+    fn test_from_itch_json() {
+        let json = r#"{"items": [{"game": {"title": "Aeternum", "url": "https://creakylantern.itch.io/aeternum"}}, {"id": 3}]}"#;
+        let library = Library::from_itch_json(json).unwrap();
+        assert_eq!(library.entries.len(), 1);
+        let db = create_db();
+        let report = db.match_library(&library);
+        assert_eq!(report.matched[0].method, MatchMethod::StoreUrl);
+        assert!(Library::from_itch_json("{").is_err());
+    }
+    #[cfg(not(feature = "library"))]
+    #[test]
+    fn test_itch_json_unsupported() {
+        let file = std::env::temp_dir().join(format!("libpobsd-itch-{}.json", std::process::id()));
+        fs::write(&file, "{}").unwrap();
+        let error = Library::load_from_file(&file, LibraryFormat::ItchJson).unwrap_err();
+        fs::remove_file(&file).unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,169 @@
+//! Provides a [`Matcher`] comparing the values of the fields of the games
+//! to a pattern according to a [`SearchType`], the pattern being prepared
+//! (case folded like the keys of the indices or compiled) once for all
+//! the values.
+use crate::db::index::normalize_item;
+use crate::SearchType;
+
+use regex::{Regex, RegexBuilder};
+
+pub(crate) enum Matcher {
+    Contains(String, bool),
+    Exact(String, bool),
+    Prefix(String, bool),
+    // None when the regular expression is invalid
+    Regex(Option<Regex>),
+}
+
+// Builds a regex matching the given expression, case sensitive or not
+pub(crate) fn build_regex(expression: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(expression)
+        .case_insensitive(!case_sensitive)
+        .build()
+}
+
+// Case folds the value when the search is not case sensitive, the same
+// way as the keys of the indices
+fn prepare(pattern: &str, case_sensitive: bool) -> String {
+    match case_sensitive {
+        true => pattern.into(),
+        false => normalize_item(pattern),
+    }
+}
+
+impl Matcher {
+    pub(crate) fn new(search_type: &SearchType, pattern: &str) -> Self {
+        let case_sensitive = search_type.is_case_sensitive();
+        match search_type {
+            SearchType::CaseSensitive | SearchType::NotCaseSensitive => {
+                Matcher::Contains(prepare(pattern, case_sensitive), case_sensitive)
+            }
+            SearchType::Exact { .. } => {
+                Matcher::Exact(prepare(pattern, case_sensitive), case_sensitive)
+            }
+            SearchType::Prefix { .. } => {
+                Matcher::Prefix(prepare(pattern, case_sensitive), case_sensitive)
+            }
+            // the pattern must be surrounded by non word characters
+            // or by the boundaries of the value
+            SearchType::WholeWord { .. } => Matcher::Regex(
+                build_regex(
+                    &format!(r"(?:^|\W)(?:{})(?:\W|$)", regex::escape(pattern)),
+                    case_sensitive,
+                )
+                .ok(),
+            ),
+            SearchType::Regex { .. } => Matcher::Regex(build_regex(pattern, case_sensitive).ok()),
+        }
+    }
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        match self {
+            Matcher::Contains(pattern, case_sensitive) => {
+                prepare(value, *case_sensitive).contains(pattern.as_str())
+            }
+            Matcher::Exact(pattern, case_sensitive) => prepare(value, *case_sensitive).eq(pattern),
+            Matcher::Prefix(pattern, case_sensitive) => {
+                prepare(value, *case_sensitive).starts_with(pattern.as_str())
+            }
+            Matcher::Regex(regex) => regex.as_ref().map_or(false, |regex| regex.is_match(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod matcher_tests {
+    use super::*;
+    #[test]
+    fn test_case_folding() {
+        let matcher = Matcher::new(
+            &SearchType::Exact {
+                case_sensitive: false,
+            },
+            "STRASSE",
+        );
+        assert!(matcher.is_match("Straße"));
+        let matcher = Matcher::new(&SearchType::NotCaseSensitive, "fi");
+        assert!(matcher.is_match("ﬁsh"));
+        let matcher = Matcher::new(
+            &SearchType::Prefix {
+                case_sensitive: true,
+            },
+            "STRASSE",
+        );
+        assert!(!matcher.is_match("Straße"));
+    }
+    #[test]
+    fn test_contains() {
+        let matcher = Matcher::new(&SearchType::NotCaseSensitive, "RPG");
+        assert!(matcher.is_match("Action rpg"));
+        assert!(matcher.is_match("arpg"));
+        let matcher = Matcher::new(&SearchType::CaseSensitive, "RPG");
+        assert!(!matcher.is_match("Action rpg"));
+    }
+    #[test]
+    fn test_exact_and_prefix() {
+        let matcher = Matcher::new(
+            &SearchType::Exact {
+                case_sensitive: false,
+            },
+            "rpg",
+        );
+        assert!(matcher.is_match("RPG"));
+        assert!(!matcher.is_match("Action RPG"));
+        let matcher = Matcher::new(
+            &SearchType::Prefix {
+                case_sensitive: true,
+            },
+            "Super",
+        );
+        assert!(matcher.is_match("Super Meat Boy"));
+        assert!(!matcher.is_match("super Meat Boy"));
+        assert!(!matcher.is_match("Not Super"));
+    }
+    #[test]
+    fn test_whole_word() {
+        let matcher = Matcher::new(
+            &SearchType::WholeWord {
+                case_sensitive: false,
+            },
+            "rpg",
+        );
+        assert!(matcher.is_match("RPG"));
+        assert!(matcher.is_match("Action RPG"));
+        assert!(matcher.is_match("rpg, roguelike"));
+        assert!(!matcher.is_match("arpg"));
+        let matcher = Matcher::new(
+            &SearchType::WholeWord {
+                case_sensitive: true,
+            },
+            "C++",
+        );
+        assert!(matcher.is_match("written in C++"));
+        assert!(!matcher.is_match("written in c++"));
+    }
+    #[test]
+    fn test_regex() {
+        let matcher = Matcher::new(
+            &SearchType::Regex {
+                case_sensitive: false,
+            },
+            r"^the .+ of",
+        );
+        assert!(matcher.is_match("The Adventures of Mr. Hat"));
+        assert!(!matcher.is_match("Adventures of Mr. Hat"));
+        // an invalid expression matches nothing
+        let matcher = Matcher::new(
+            &SearchType::Regex {
+                case_sensitive: true,
+            },
+            "(",
+        );
+        assert!(!matcher.is_match("("));
+        let st = SearchType::Regex {
+            case_sensitive: true,
+        };
+        assert!(st.validate("(").is_err());
+        assert!(st.validate("^the").is_ok());
+        assert!(SearchType::CaseSensitive.validate("(").is_ok());
+    }
+}
@@ -0,0 +1,378 @@
+//! Provides a [`DatabaseMerger`] combining several sources of games (e.g.
+//! the upstream PlayOnBSD database and a private addendum of unreleased
+//! entries and local corrections) into a single [`GameDataBase`].
+//!
+//! The games of the sources are matched by uid, then by IGDB id and Steam
+//! id. For each field, the value kept is chosen according to a
+//! [`MergeRule`] that can be set per field, and every field for which the
+//! sources disagree is reported as a [`MergeConflict`].
+//!
+//! ## Examples
+//! ```no_run
+//! use libpobsd::db::{DatabaseMerger, MergeRule};
+//! use libpobsd::{Game, Parser};
+//!
+//! let upstream: Vec<Game> = Parser::default()
+//!     .load_from_file("games.db")
+//!     .expect("Could not open the file")
+//!     .into();
+//! let local: Vec<Game> = Parser::default()
+//!     .load_from_file("local.db")
+//!     .expect("Could not open the file")
+//!     .into();
+//! let mut merger = DatabaseMerger::new();
+//! merger
+//!     .add_source("upstream", upstream)
+//!     .add_source("local", local);
+//! merger
+//!     .set_rule("Status", MergeRule::Prefer("local".into()))
+//!     .expect("Unknown field")
+//!     .set_rule("Tags", MergeRule::Union)
+//!     .expect("Unknown field");
+//! let (db, conflicts) = merger.merge();
+//! for conflict in conflicts {
+//!     println!("{}", conflict);
+//! }
+//! ```
+use crate::models::field::Field;
+use crate::models::StoreLinks;
+use crate::parsing::SchemaVersion;
+use crate::{Game, GameDataBase, Parser};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+/// Rule choosing the value of a field when merging games.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MergeRule {
+    /// The value of the first source (in the order the sources were
+    /// added) having a value wins. It is the default.
+    #[default]
+    First,
+    /// The value of the last source having a value wins.
+    Last,
+    /// The value of the given source wins if it has a value, the rule
+    /// falling back to [`MergeRule::First`] otherwise.
+    Prefer(String),
+    /// The items of the sources are unioned, in the order of the sources,
+    /// for the list fields (`Store`, `Genre`, `Tags`, `Dev` and `Pub`).
+    /// The rule falls back to [`MergeRule::First`] for the other fields.
+    Union,
+}
+
+/// Field for which the sources of a merged game disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// Uid of the merged game.
+    pub uid: u32,
+    /// Name of the merged game.
+    pub name: String,
+    /// Key of the field (e.g. `Status`).
+    pub key: &'static str,
+    /// Values of the sources having one, as written in the database,
+    /// along with the name of the source.
+    pub values: Vec<(String, String)>,
+    /// Source whose value was kept, None when the values were unioned.
+    pub winner: Option<String>,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(source, value)| format!("{}: \"{}\"", source, value))
+            .collect();
+        write!(f, "{} ({}): {}", self.name, self.key, values.join(", "))?;
+        match &self.winner {
+            Some(winner) => write!(f, " -> {}", winner),
+            None => write!(f, " -> union"),
+        }
+    }
+}
+
+/// Merges several sources of games into a [`GameDataBase`].
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseMerger {
+    sources: Vec<(String, Vec<Game>)>,
+    rules: HashMap<&'static str, MergeRule>,
+    default_rule: MergeRule,
+}
+
+impl DatabaseMerger {
+    /// Creates a [`DatabaseMerger`] without source, using
+    /// [`MergeRule::First`] for every field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a source of games after the previous ones.
+    pub fn add_source(&mut self, name: impl Into<String>, games: Vec<Game>) -> &mut Self {
+        self.sources.push((name.into(), games));
+        self
+    }
+    /// Sets the rule of the given field, given by its key in the database
+    /// (e.g. `Status` or `Pub`).
+    pub fn set_rule(&mut self, key: &str, rule: MergeRule) -> Result<&mut Self, Error> {
+        match SchemaVersion::LATEST
+            .fields()
+            .iter()
+            .find(|field| **field == key)
+        {
+            Some(key) => {
+                self.rules.insert(key, rule);
+                Ok(self)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown field: {}", key),
+            )),
+        }
+    }
+    /// Sets the rule of the fields without a rule of their own.
+    pub fn set_default_rule(&mut self, rule: MergeRule) -> &mut Self {
+        self.default_rule = rule;
+        self
+    }
+    /// Returns the rule of the given field.
+    pub fn get_rule(&self, key: &str) -> &MergeRule {
+        self.rules.get(key).unwrap_or(&self.default_rule)
+    }
+    /// Merges the sources, returning the [`GameDataBase`] of the merged
+    /// games along with the conflicts between the sources. A merged game
+    /// keeps the uid of its first source.
+    pub fn merge(&self) -> (GameDataBase, Vec<MergeConflict>) {
+        // games of each source matched together
+        let mut entries: Vec<Vec<(usize, &Game)>> = Vec::new();
+        let mut uids: HashMap<u32, usize> = HashMap::new();
+        let mut igdb_ids: HashMap<usize, usize> = HashMap::new();
+        let mut steam_ids: HashMap<usize, usize> = HashMap::new();
+        for (source, (_, games)) in self.sources.iter().enumerate() {
+            for game in games {
+                let index = uids
+                    .get(&game.uid)
+                    .or_else(|| game.igdb_id.and_then(|id| igdb_ids.get(&id)))
+                    .or_else(|| game.get_steam_id().and_then(|id| steam_ids.get(&id)))
+                    .copied()
+                    // an entry holds one game per source at most
+                    .filter(|index| entries[*index].iter().all(|(s, _)| *s != source));
+                let index = match index {
+                    Some(index) => {
+                        entries[index].push((source, game));
+                        index
+                    }
+                    None => {
+                        entries.push(vec![(source, game)]);
+                        entries.len() - 1
+                    }
+                };
+                uids.entry(game.uid).or_insert(index);
+                if let Some(igdb_id) = game.igdb_id {
+                    igdb_ids.entry(igdb_id).or_insert(index);
+                }
+                if let Some(steam_id) = game.get_steam_id() {
+                    steam_ids.entry(steam_id).or_insert(index);
+                }
+            }
+        }
+        let mut conflicts = Vec::new();
+        let games = entries
+            .iter()
+            .map(|entry| self.merge_entry(entry, &mut conflicts))
+            .collect();
+        (GameDataBase::new(games), conflicts)
+    }
+    fn merge_entry(&self, entry: &[(usize, &Game)], conflicts: &mut Vec<MergeConflict>) -> Game {
+        let mut game = entry[0].1.clone();
+        if entry.len() == 1 {
+            return game;
+        }
+        let fields: Vec<(usize, Vec<Field>)> = entry
+            .iter()
+            .map(|(source, game)| (*source, game.fields()))
+            .collect();
+        let mut entry_conflicts = Vec::new();
+        for (position, key) in SchemaVersion::LATEST.fields().iter().enumerate() {
+            // sources having a value for the field
+            let values: Vec<(usize, &Field)> = fields
+                .iter()
+                .map(|(source, fields)| (*source, &fields[position]))
+                .filter(|(_, field)| !field.is_empty())
+                .collect();
+            let first = match values.first() {
+                Some(first) => first,
+                None => continue,
+            };
+            let rule = self.get_rule(key);
+            let field = match rule {
+                MergeRule::Union => union(&values),
+                _ => None,
+            };
+            let (winner, field) = match field {
+                Some(field) => (None, field),
+                None => {
+                    let (source, field) = match rule {
+                        MergeRule::Last => values.last().unwrap_or(first),
+                        MergeRule::Prefer(name) => values
+                            .iter()
+                            .find(|(source, _)| self.sources[*source].0 == *name)
+                            .unwrap_or(first),
+                        _ => first,
+                    };
+                    (Some(self.sources[*source].0.clone()), (*field).clone())
+                }
+            };
+            let displayed: Vec<String> = values.iter().map(|(_, field)| value_of(field)).collect();
+            if displayed.iter().any(|value| *value != displayed[0]) {
+                entry_conflicts.push(MergeConflict {
+                    uid: game.uid,
+                    name: String::new(),
+                    key,
+                    values: values
+                        .iter()
+                        .map(|(source, _)| self.sources[*source].0.clone())
+                        .zip(displayed)
+                        .collect(),
+                    winner,
+                });
+            }
+            Parser::set_field(&mut game, field);
+        }
+        for (_, other) in &entry[1..] {
+            for (key, value) in &other.extra {
+                if !game.has_extra(key) {
+                    game.set_extra(key.clone(), value.clone());
+                }
+            }
+        }
+        for mut conflict in entry_conflicts {
+            conflict.name = game.name.clone();
+            conflicts.push(conflict);
+        }
+        game
+    }
+}
+
+// Value of a field as written in the database
+fn value_of(field: &Field) -> String {
+    let line = field.to_string();
+    match line.split_once('\t') {
+        Some((_, value)) => value.into(),
+        None => String::new(),
+    }
+}
+
+fn union_items(lists: Vec<&Vec<String>>) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    for item in lists.into_iter().flatten() {
+        if !items.contains(item) {
+            items.push(item.clone());
+        }
+    }
+    items
+}
+
+// Unions the items of a list field, None for the other fields
+fn union(values: &[(usize, &Field)]) -> Option<Field> {
+    let lists = |field: fn(&Field) -> Option<&Vec<String>>| -> Vec<&Vec<String>> {
+        values
+            .iter()
+            .filter_map(|(_, value)| field(value))
+            .collect()
+    };
+    let field = match values[0].1 {
+        Field::Store(_) => {
+            let mut stores = StoreLinks::default();
+            for (_, value) in values {
+                if let Field::Store(Some(links)) = value {
+                    for link in links.inner_ref() {
+                        if stores.inner_ref().iter().all(|s| s.url != link.url) {
+                            stores.push(link.clone());
+                        }
+                    }
+                }
+            }
+            Field::Store(Some(stores))
+        }
+        Field::Genres(_) => Field::Genres(Some(union_items(lists(|field| match field {
+            Field::Genres(items) => items.as_ref(),
+            _ => None,
+        })))),
+        Field::Tags(_) => Field::Tags(Some(union_items(lists(|field| match field {
+            Field::Tags(items) => items.as_ref(),
+            _ => None,
+        })))),
+        Field::Dev(_) => Field::Dev(Some(union_items(lists(|field| match field {
+            Field::Dev(items) => items.as_ref(),
+            _ => None,
+        })))),
+        Field::Publi(_) => Field::Publi(Some(union_items(lists(|field| match field {
+            Field::Publi(items) => items.as_ref(),
+            _ => None,
+        })))),
+        _ => return None,
+    };
+    Some(field)
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::*;
+    use crate::{GameStatus, Status};
+
+    fn create_game(uid: u32, name: &str) -> Game {
+        let mut game = Game::new();
+        game.uid = uid;
+        game.name = name.into();
+        game
+    }
+    #[test]
+    fn test_set_rule() {
+        let mut merger = DatabaseMerger::new();
+        assert!(merger.set_rule("Pub", MergeRule::Union).is_ok());
+        assert!(merger.set_rule("Publi", MergeRule::Union).is_err());
+        assert_eq!(merger.get_rule("Pub"), &MergeRule::Union);
+        assert_eq!(merger.get_rule("Tags"), &MergeRule::First);
+    }
+    #[test]
+    fn test_merge() {
+        let mut upstream = create_game(1, "Game 1");
+        upstream.tags = Some(vec!["indie".into(), "2d".into()]);
+        upstream.engine = Some("godot".into());
+        upstream.status = GameStatus::new(Status::Launches, None);
+        upstream.igdb_id = Some(12);
+        let mut local = create_game(2, "Game 1 (local)");
+        local.tags = Some(vec!["2d".into(), "puzzle".into()]);
+        local.status = GameStatus::new(Status::Completable, None);
+        local.igdb_id = Some(12);
+        let unreleased = create_game(3, "Game 3");
+        let mut merger = DatabaseMerger::new();
+        merger
+            .add_source("upstream", vec![upstream])
+            .add_source("local", vec![local, unreleased]);
+        merger
+            .set_rule("Status", MergeRule::Prefer("local".into()))
+            .unwrap()
+            .set_rule("Tags", MergeRule::Union)
+            .unwrap();
+        let (db, conflicts) = merger.merge();
+        assert_eq!(db.get_all_games().count, 2);
+        let game = db.get_game_by_id(1).unwrap();
+        assert_eq!(game.name, "Game 1");
+        assert_eq!(game.engine, Some("godot".into()));
+        assert_eq!(game.status.status, Status::Completable);
+        assert_eq!(
+            game.tags,
+            Some(vec!["indie".into(), "2d".into(), "puzzle".into()])
+        );
+        assert!(db.get_game_by_id(3).is_some());
+        let keys: Vec<&str> = conflicts.iter().map(|c| c.key).collect();
+        assert_eq!(keys, vec!["Game", "Tags", "Status"]);
+        assert_eq!(conflicts[2].winner, Some("local".into()));
+        assert_eq!(conflicts[1].winner, None);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "Game 1 (Game): upstream: \"Game 1\", local: \"Game 1 (local)\" -> upstream"
+        );
+    }
+}
@@ -48,7 +48,8 @@
 //! #       ParserResult::WithError(games, _) => games,
 //! #   };
 //! # let db = GameDataBase::new(games);
-//! let game_query = db.match_games_by_tag("indie");
+//! use libpobsd::SearchType;
+//! let game_query = db.match_games_by_tag("indie", &SearchType::NotCaseSensitive);
 //! // check the first element of the query
 //! if let Some(game) = game_query.get(0) {
 //!     if let Some(tags) = &game.tags {
@@ -56,25 +57,114 @@
 //!     };
 //! };
 //!```
+//! Load the GameDataBase from a binary snapshot, falling back to parsing
+//! the PlayOnBSD database when the snapshot is missing or outdated.
+//! ```no_run
+//! use libpobsd::GameDataBase;
+//! let db = GameDataBase::load_snapshot("games.snap", "games.db")
+//!        .expect("Could not open the database");
+//! db.save_snapshot("games.snap", "games.db")
+//!        .expect("Could not save the snapshot");
+//!```
+pub mod aliases;
 pub mod database;
+pub mod duplicates;
+pub mod feed;
 pub mod game_filer;
+pub(crate) mod index;
+pub mod library;
+pub(crate) mod matcher;
+pub mod merge;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+pub mod packages;
 pub(crate) mod queries;
 pub mod query_result;
+pub mod similarity;
+pub(crate) mod snapshot;
+pub mod timeline;
+#[cfg(feature = "watch")]
+pub mod watch;
 
+pub use aliases::AliasRegistry;
 pub use database::GameDataBase;
+pub use duplicates::{DuplicateGroup, DuplicateReason};
+pub use feed::{FeedConfig, FeedEntry, FeedEntryKind};
 pub use game_filer::GameFilter;
+pub use index::CollapsedSpellings;
+pub use merge::{DatabaseMerger, MergeConflict, MergeRule};
+pub use packages::{InstalledPackages, PackageReport, RuntimePackages};
 pub use query_result::QueryResult;
+pub use similarity::{SimilarGame, Similarity, SimilarityWeights};
+pub use timeline::{GameTimeline, Timeline, TimelineEvent, TimelineEventKind, TimelineSnapshot};
 
 /// Representation of items such as pub, tags, etc.
 pub type Item = String;
 
-#[derive(Debug, Default, Clone)]
-/// Define the type of search performed. It can be either case sensitive or
-/// cas insensitive (default).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Define the type of search performed. The pattern can be searched
+/// within the values (case sensitive or case insensitive, the default),
+/// or compared to the whole values, their beginning, their words or
+/// used as a regular expression, each case sensitive or not.
 pub enum SearchType {
     /// Correspond to a case sensitive search
     CaseSensitive,
     #[default]
     /// Correspond to a case insensitive search. It is the default.
     NotCaseSensitive,
+    /// The value is equal to the pattern.
+    Exact {
+        /// Whether the comparison is case sensitive.
+        case_sensitive: bool,
+    },
+    /// The value starts with the pattern (e.g. names starting with "Super").
+    Prefix {
+        /// Whether the comparison is case sensitive.
+        case_sensitive: bool,
+    },
+    /// The value contains the pattern as a whole word, i.e. surrounded by
+    /// non alphanumeric characters (e.g. "rpg" matches "action rpg" but
+    /// not "arpg").
+    WholeWord {
+        /// Whether the comparison is case sensitive.
+        case_sensitive: bool,
+    },
+    /// The value matches the pattern used as a regular expression (see
+    /// the syntax of the [`regex`] crate). An invalid regular expression
+    /// matches no value, [`SearchType::validate`] telling it apart from
+    /// a search without result.
+    Regex {
+        /// Whether the comparison is case sensitive.
+        case_sensitive: bool,
+    },
+}
+
+impl SearchType {
+    /// Returns true if the search is case sensitive.
+    pub fn is_case_sensitive(&self) -> bool {
+        match self {
+            SearchType::CaseSensitive => true,
+            SearchType::NotCaseSensitive => false,
+            SearchType::Exact { case_sensitive }
+            | SearchType::Prefix { case_sensitive }
+            | SearchType::WholeWord { case_sensitive }
+            | SearchType::Regex { case_sensitive } => *case_sensitive,
+        }
+    }
+    /// Checks that the pattern can be used for this type of search, i.e.
+    /// that it is a valid regular expression for [`SearchType::Regex`].
+    /// The search methods do not report invalid patterns, which match no
+    /// value.
+    pub fn validate(&self, pattern: &str) -> Result<(), regex::Error> {
+        match self {
+            SearchType::Regex { case_sensitive } => {
+                matcher::build_regex(pattern, *case_sensitive).map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Returns true if the given value matches the pattern.
+    pub fn matches(&self, value: &str, pattern: &str) -> bool {
+        matcher::Matcher::new(self, pattern).is_match(value)
+    }
 }
@@ -0,0 +1,442 @@
+//! Provides a personal [`Overlay`] recording, for each game, information
+//! that does not belong to the PlayOnBSD database: the stores on which the
+//! game is owned, whether it is installed or finished, a rating and notes.
+//!
+//! The overlay is stored in a local JSON or TOML file, each entry being
+//! keyed by the uid of the game or, as a fallback, by its name:
+//! ```toml
+//! [1595434339]
+//! owned = ["Gog"]
+//! installed = true
+//! rating = 4
+//!
+//! ["The Adventures of Shuggy"]
+//! finished = true
+//! notes = "Play it in coop"
+//! ```
+//! Since the uid of a game changes when it is renamed upstream, entries
+//! also record the IGDB and Steam ids of the game (see [`Overlay::set_entry`])
+//! which are used to find the game when its uid is no longer known.
+//!
+//! Once resolved against a [`GameDataBase`], the overlay can be used to
+//! filter games with an [`OverlayFilter`].
+//!
+//! This module is only available with the `overlay` feature.
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::db::overlay::{Overlay, OverlayFilter};
+//! use libpobsd::Status;
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let overlay = Overlay::load_from_file("overlay.toml").expect("Invalid overlay");
+//! let overlay = db.resolve_overlay(&overlay);
+//! // owned games that can be played until the end
+//! let mut filter = OverlayFilter::default();
+//! filter.set_owned(true).set_min_status(Status::Completable);
+//! let games = db.search_games_by_overlay(&filter, &overlay);
+//! ```
+use crate::models::game::normalize_name;
+use crate::{Game, GameDataBase, QueryResult, Status, Store};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Personal information about a game.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverlayEntry {
+    /// Stores on which the game is owned.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub owned: Vec<Store>,
+    /// Whether the game is installed.
+    pub installed: bool,
+    /// Whether the game was finished.
+    pub finished: bool,
+    /// Personal rating of the game.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Personal notes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// IGDB id of the game, used to find the game if it was renamed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub igdb_id: Option<usize>,
+    /// Steam id of the game, used to find the game if it was renamed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steam_id: Option<usize>,
+}
+
+impl OverlayEntry {
+    /// Returns true if the game is owned on at least one store.
+    pub fn is_owned(&self) -> bool {
+        !self.owned.is_empty()
+    }
+}
+
+fn invalid_data(error: impl ToString) -> Error {
+    Error::new(ErrorKind::InvalidData, error.to_string())
+}
+
+/// Collection of [`OverlayEntry`] keyed by game uid or name.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Overlay {
+    entries: BTreeMap<String, OverlayEntry>,
+}
+
+impl Overlay {
+    /// Creates an empty [`Overlay`]. Equivalent to Default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Loads an [`Overlay`] from a file, the file being read as TOML if its
+    /// extension is `toml` and as JSON otherwise.
+    pub fn load_from_file(file: impl AsRef<Path>) -> Result<Self, Error> {
+        let data = fs::read_to_string(file.as_ref())?;
+        match file.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::load_from_toml(&data),
+            _ => Self::load_from_json(&data),
+        }
+    }
+    /// Loads an [`Overlay`] from a JSON [`&str`].
+    pub fn load_from_json(data: &str) -> Result<Self, Error> {
+        serde_json::from_str(data).map_err(invalid_data)
+    }
+    /// Loads an [`Overlay`] from a TOML [`&str`].
+    pub fn load_from_toml(data: &str) -> Result<Self, Error> {
+        toml::from_str(data).map_err(invalid_data)
+    }
+    /// Saves the [`Overlay`] into a file, using TOML if its extension
+    /// is `toml` and JSON otherwise.
+    pub fn save_to_file(&self, file: impl AsRef<Path>) -> Result<(), Error> {
+        let data = match file.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => self.to_toml()?,
+            _ => self.to_json()?,
+        };
+        fs::write(file, data)
+    }
+    /// Returns the [`Overlay`] as JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(invalid_data)
+    }
+    /// Returns the [`Overlay`] as TOML.
+    pub fn to_toml(&self) -> Result<String, Error> {
+        toml::to_string(self).map_err(invalid_data)
+    }
+    /// Sets the entry of the given game, keyed by its uid. The IGDB and
+    /// Steam ids of the game are recorded in the entry.
+    pub fn set_entry(&mut self, game: &Game, mut entry: OverlayEntry) -> &mut Self {
+        entry.igdb_id = game.igdb_id;
+        entry.steam_id = game.get_steam_id();
+        self.entries.insert(game.uid.to_string(), entry);
+        self
+    }
+    /// Returns the entry with the given key (uid or name).
+    pub fn get_entry(&self, key: &str) -> Option<&OverlayEntry> {
+        self.entries.get(key)
+    }
+    /// Removes the entry with the given key (uid or name).
+    pub fn remove_entry(&mut self, key: &str) -> Option<OverlayEntry> {
+        self.entries.remove(key)
+    }
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// Returns true if the overlay has no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// [`Overlay`] resolved against a [`GameDataBase`], as returned by
+/// [`GameDataBase::resolve_overlay`].
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct ResolvedOverlay {
+    /// Entries keyed by the current uid of the games.
+    pub entries: HashMap<u32, OverlayEntry>,
+    /// Keys of the entries not matching any game.
+    pub unmatched: Vec<String>,
+}
+
+impl ResolvedOverlay {
+    /// Returns the entry of the given game, if any.
+    pub fn get_entry(&self, game: &Game) -> Option<&OverlayEntry> {
+        self.entries.get(&game.uid)
+    }
+    /// Returns an [`Overlay`] keyed by the current uid of the games (with
+    /// up to date IGDB and Steam ids), the unmatched entries being dropped.
+    /// Saving it keeps the overlay in sync with upstream renames.
+    pub fn to_overlay(&self, db: &GameDataBase) -> Overlay {
+        let mut overlay = Overlay::new();
+        for (uid, entry) in &self.entries {
+            if let Some(game) = db.get_game_by_id(*uid) {
+                overlay.set_entry(game, entry.clone());
+            }
+        }
+        overlay
+    }
+}
+
+/// Allows for game filtering based on the [`OverlayEntry`] of the games
+/// and on their status. Contrary to the [`crate::GameFilter`], a game must
+/// match all the rules that are set.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct OverlayFilter {
+    /// Whether the game is owned on at least one store.
+    pub owned: Option<bool>,
+    /// Store on which the game is owned.
+    pub owned_on: Option<Store>,
+    /// Whether the game is installed.
+    pub installed: Option<bool>,
+    /// Whether the game was finished.
+    pub finished: Option<bool>,
+    /// Minimum personal rating.
+    pub min_rating: Option<u8>,
+    /// Minimum status of the game.
+    pub min_status: Option<Status>,
+}
+
+impl OverlayFilter {
+    /// Sets whether the game must be owned.
+    pub fn set_owned(&mut self, owned: bool) -> &mut Self {
+        self.owned = Some(owned);
+        self
+    }
+    /// Sets the store on which the game must be owned.
+    pub fn set_owned_on(&mut self, store: Store) -> &mut Self {
+        self.owned_on = Some(store);
+        self
+    }
+    /// Sets whether the game must be installed.
+    pub fn set_installed(&mut self, installed: bool) -> &mut Self {
+        self.installed = Some(installed);
+        self
+    }
+    /// Sets whether the game must be finished.
+    pub fn set_finished(&mut self, finished: bool) -> &mut Self {
+        self.finished = Some(finished);
+        self
+    }
+    /// Sets the minimum personal rating of the game.
+    pub fn set_min_rating(&mut self, rating: u8) -> &mut Self {
+        self.min_rating = Some(rating);
+        self
+    }
+    /// Sets the minimum status of the game. It takes a
+    /// [`crate::GameStatus`] or [`Status`] as argument.
+    pub fn set_min_status<T: AsRef<Status>>(&mut self, status: T) -> &mut Self {
+        self.min_status = Some(status.as_ref().clone());
+        self
+    }
+    /// Check if a given [`Game`] matches all the filtering rules, a game
+    /// without entry in the overlay being considered as not owned, not
+    /// installed, not finished and without rating.
+    pub fn check_game<T: AsRef<Game>>(&self, game: T, overlay: &ResolvedOverlay) -> bool {
+        let game = game.as_ref();
+        let default = OverlayEntry::default();
+        let entry = overlay.get_entry(game).unwrap_or(&default);
+        self.owned.map_or(true, |owned| entry.is_owned() == owned)
+            && self
+                .owned_on
+                .as_ref()
+                .map_or(true, |store| entry.owned.contains(store))
+            && self
+                .installed
+                .map_or(true, |installed| entry.installed == installed)
+            && self
+                .finished
+                .map_or(true, |finished| entry.finished == finished)
+            && self
+                .min_rating
+                .map_or(true, |rating| entry.rating.map_or(false, |r| r >= rating))
+            && self
+                .min_status
+                .as_ref()
+                .map_or(true, |status| game.status.status.ge(status))
+    }
+    /// Filter a vector of [`Game`] based on the filtering rules.
+    pub fn filter_games<T: AsRef<Game>>(&self, games: Vec<T>, overlay: &ResolvedOverlay) -> Vec<T> {
+        games
+            .into_iter()
+            .filter(|game| self.check_game(game, overlay))
+            .collect()
+    }
+}
+
+impl GameDataBase {
+    /// Resolves the entries of the [`Overlay`] to the games of the database.
+    /// An entry is matched to a game using, in order, its key as uid (a
+    /// previous uid of the game being resolved, see
+    /// [`GameDataBase::add_uid_alias`]), its IGDB id, its Steam id and finally its key as name (compared on the
+    /// normalized form, see [`Game::get_normalized_name`]).
+    pub fn resolve_overlay(&self, overlay: &Overlay) -> ResolvedOverlay {
+        let mut igdb_ids: HashMap<usize, u32> = HashMap::new();
+        let mut steam_ids: HashMap<usize, u32> = HashMap::new();
+        let mut names: HashMap<String, u32> = HashMap::new();
+        for game in self.games.values() {
+            if let Some(igdb_id) = game.igdb_id {
+                igdb_ids.insert(igdb_id, game.uid);
+            }
+            if let Some(steam_id) = game.get_steam_id() {
+                steam_ids.insert(steam_id, game.uid);
+            }
+            names.insert(game.get_normalized_name(), game.uid);
+        }
+        let mut resolved = ResolvedOverlay::default();
+        for (key, entry) in &overlay.entries {
+            let uid = key
+                .parse::<u32>()
+                .ok()
+                .and_then(|uid| self.resolve_uid(uid))
+                .or_else(|| entry.igdb_id.and_then(|id| igdb_ids.get(&id).copied()))
+                .or_else(|| entry.steam_id.and_then(|id| steam_ids.get(&id).copied()))
+                .or_else(|| names.get(&normalize_name(key)).copied());
+            match uid {
+                Some(uid) => {
+                    resolved.entries.insert(uid, entry.clone());
+                }
+                None => resolved.unmatched.push(key.clone()),
+            }
+        }
+        resolved
+    }
+    /// Returns the games matching the [`OverlayFilter`].
+    pub fn search_games_by_overlay(
+        &self,
+        filter: &OverlayFilter,
+        overlay: &ResolvedOverlay,
+    ) -> QueryResult<&Game> {
+        let games = filter.filter_games(
+            self.games.values().map(|game| game.as_ref()).collect(),
+            overlay,
+        );
+        QueryResult::new(games)
+    }
+}
+
+impl<T: AsRef<Game> + Ord> QueryResult<T> {
+    /// Returns the games matching the [`OverlayFilter`].
+    pub fn filter_games_by_overlay(
+        self,
+        filter: &OverlayFilter,
+        overlay: &ResolvedOverlay,
+    ) -> QueryResult<T> {
+        QueryResult::new(filter.filter_games(self.items, overlay))
+    }
+}
+
+#[cfg(test)]
+mod overlay_tests {
+    use super::*;
+    use crate::{GameStatus, StoreLink, StoreLinks};
+
+    fn create_db() -> GameDataBase {
+        let mut game1 = Game::new();
+        game1.uid = 1;
+        game1.name = "Game 1".into();
+        game1.igdb_id = Some(12);
+        game1.status = GameStatus::new(Status::Completable, None);
+        let mut game2 = Game::new();
+        game2.uid = 2;
+        game2.name = "The Game 2".into();
+        game2.stores = Some(StoreLinks(vec![StoreLink::from(
+            "https://store.steampowered.com/app/1869200/Game_2/",
+        )]));
+        game2.status = GameStatus::new(Status::MinorBugs, None);
+        let mut game3 = Game::new();
+        game3.uid = 3;
+        game3.name = "Game 3".into();
+        GameDataBase::new(vec![game1, game2, game3])
+    }
+    #[test]
+    fn test_load_from_toml_and_json() {
+        let toml = "[1]\nowned = [\"Gog\"]\ninstalled = true\nrating = 4\n\n[\"Game 3\"]\nnotes = \"Great\"\n";
+        let overlay = Overlay::load_from_toml(toml).unwrap();
+        assert_eq!(overlay.len(), 2);
+        let entry = overlay.get_entry("1").unwrap();
+        assert_eq!(entry.owned, vec![Store::Gog]);
+        assert!(entry.installed);
+        assert!(!entry.finished);
+        let json = overlay.to_json().unwrap();
+        assert_eq!(Overlay::load_from_json(&json).unwrap(), overlay);
+        let toml = overlay.to_toml().unwrap();
+        assert_eq!(Overlay::load_from_toml(&toml).unwrap(), overlay);
+        let err = Overlay::load_from_json("[1, 2]").err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+    #[test]
+    fn test_resolve_overlay() {
+        let db = create_db();
+        let mut overlay = Overlay::new();
+        let entry = OverlayEntry {
+            finished: true,
+            ..Default::default()
+        };
+        // renamed games, found by IGDB and Steam ids
+        overlay.entries.insert(
+            "42".into(),
+            OverlayEntry {
+                igdb_id: Some(12),
+                ..entry.clone()
+            },
+        );
+        overlay.entries.insert(
+            "Old Game 2".into(),
+            OverlayEntry {
+                steam_id: Some(1869200),
+                ..entry.clone()
+            },
+        );
+        // found by name
+        overlay.entries.insert("game 3!".into(), entry.clone());
+        overlay.entries.insert("Game 4".into(), entry);
+        let resolved = db.resolve_overlay(&overlay);
+        let mut uids: Vec<&u32> = resolved.entries.keys().collect();
+        uids.sort();
+        assert_eq!(uids, vec![&1, &2, &3]);
+        assert_eq!(resolved.unmatched, vec!["Game 4".to_string()]);
+        let overlay = resolved.to_overlay(&db);
+        assert_eq!(overlay.len(), 3);
+        assert_eq!(overlay.get_entry("2").unwrap().steam_id, Some(1869200));
+    }
+    #[test]
+    fn test_overlay_filter() {
+        let db = create_db();
+        let mut overlay = Overlay::new();
+        let owned = OverlayEntry {
+            owned: vec![Store::Gog],
+            rating: Some(3),
+            ..Default::default()
+        };
+        overlay.set_entry(db.get_game_by_id(1).unwrap(), owned.clone());
+        overlay.set_entry(db.get_game_by_id(2).unwrap(), owned);
+        let overlay = db.resolve_overlay(&overlay);
+        let mut filter = OverlayFilter::default();
+        filter.set_owned(true);
+        assert_eq!(db.search_games_by_overlay(&filter, &overlay).count, 2);
+        filter.set_min_status(Status::Completable);
+        let games = db.search_games_by_overlay(&filter, &overlay);
+        assert_eq!(games.count, 1);
+        assert_eq!(games.get(0).unwrap().name, "Game 1");
+        let mut filter = OverlayFilter::default();
+        filter.set_owned(false);
+        let games = db
+            .get_all_games()
+            .filter_games_by_overlay(&filter, &overlay);
+        assert_eq!(games.count, 1);
+        let mut filter = OverlayFilter::default();
+        filter.set_owned_on(Store::Steam);
+        assert_eq!(db.search_games_by_overlay(&filter, &overlay).count, 0);
+        let mut filter = OverlayFilter::default();
+        filter.set_min_rating(4);
+        assert_eq!(db.search_games_by_overlay(&filter, &overlay).count, 0);
+    }
+}
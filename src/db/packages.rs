@@ -0,0 +1,352 @@
+//! Provides a mapping between the runtimes of the games and the OpenBSD
+//! packages providing them, as well as a report of the games that can be
+//! played with the packages installed on the system.
+//!
+//! The [`RuntimePackages`] table maps a runtime (e.g. "godot" or "HashLink")
+//! to the packages to install. It can be loaded from a simple local file,
+//! each line giving a runtime followed by its comma-separated packages.
+//! Empty lines and lines starting with `#` are ignored.
+//!
+//! The [`InstalledPackages`] are read from the output of `pkg_info -q`.
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::db::packages::{InstalledPackages, RuntimePackages};
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let installed = InstalledPackages::load_from_file("pkg_info.txt")
+//!        .expect("Could not open the file");
+//! let report = db.check_installed_packages(&installed, &RuntimePackages::builtin());
+//! for game in &report.installed {
+//!     println!("{} can be played", game.name);
+//! }
+//! if let Some(command) = report.pkg_add_command() {
+//!     println!("{}", command);
+//! }
+//! ```
+use crate::db::index::normalize_item;
+use crate::{Game, GameDataBase};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Runtimes known to be provided by OpenBSD packages, used by
+/// [`RuntimePackages::builtin`].
+const BUILTIN_RUNTIMES: [(&str, &str); 14] = [
+    ("godot", "godot"),
+    ("fnaify", "fnaify"),
+    ("hashlink", "hashlink"),
+    ("lwjgl", "lwjgl"),
+    ("lwjgl3", "lwjgl3"),
+    ("easyrpg", "easyrpg-player"),
+    ("mono", "mono"),
+    ("love", "love"),
+    ("scummvm", "scummvm"),
+    ("dosbox", "dosbox"),
+    ("gzdoom", "gzdoom"),
+    ("ioquake3", "ioquake3"),
+    ("java", "jdk"),
+    ("python", "python"),
+];
+
+/// Maps the runtimes of the games to the OpenBSD packages providing them.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RuntimePackages {
+    /// HashMap using the normalized runtime as key and the packages as value
+    runtimes: HashMap<String, Vec<String>>,
+}
+
+impl RuntimePackages {
+    /// Creates an empty [`RuntimePackages`]. Equivalent to Default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Creates a [`RuntimePackages`] with the runtimes known to be
+    /// provided by OpenBSD packages (godot, fnaify, HashLink, etc.).
+    pub fn builtin() -> Self {
+        let mut table = Self::default();
+        for (runtime, package) in BUILTIN_RUNTIMES {
+            table.add_runtime(runtime, &[package]);
+        }
+        table
+    }
+    /// Loads a [`RuntimePackages`] from a file (see the module documentation
+    /// for the format).
+    pub fn load_from_file(file: impl AsRef<Path>) -> Result<Self, Error> {
+        let data = fs::read_to_string(file)?;
+        Self::load_from_string(&data)
+    }
+    /// Loads a [`RuntimePackages`] from a [`&str`] (see the module documentation
+    /// for the format). An error is returned for a line without `=` or
+    /// without runtime.
+    pub fn load_from_string(data: &str) -> Result<Self, Error> {
+        let mut table = Self::default();
+        for (index, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: &str| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", index + 1, msg),
+                )
+            };
+            let (runtime, packages) = line
+                .split_once('=')
+                .ok_or_else(|| error("missing '=' after the runtime"))?;
+            if runtime.trim().is_empty() {
+                return Err(error("empty runtime"));
+            }
+            let packages: Vec<&str> = packages
+                .split(',')
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+                .collect();
+            table.add_runtime(runtime, &packages);
+        }
+        Ok(table)
+    }
+    /// Maps the given runtime to the given packages, replacing any previous
+    /// mapping. A runtime mapped to no package is considered as not requiring
+    /// any package.
+    pub fn add_runtime(&mut self, runtime: &str, packages: &[&str]) -> &mut Self {
+        self.runtimes.insert(
+            normalize_item(runtime),
+            packages.iter().map(|p| p.to_string()).collect(),
+        );
+        self
+    }
+    /// Merges the given [`RuntimePackages`] into this one, its mappings
+    /// taking precedence (e.g. a local table over the builtin one).
+    pub fn extend(&mut self, other: RuntimePackages) -> &mut Self {
+        self.runtimes.extend(other.runtimes);
+        self
+    }
+    /// Returns the packages providing the given runtime (the comparison is
+    /// not case sensitive) if it is mapped.
+    pub fn get_packages(&self, runtime: &str) -> Option<&Vec<String>> {
+        self.runtimes.get(&normalize_item(runtime))
+    }
+}
+
+/// Set of the OpenBSD packages installed on the system.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackages {
+    /// Package names without version nor flavor (the stems)
+    stems: HashSet<String>,
+}
+
+// Returns the stem of a package name, e.g. "godot" for "godot-3.5.2p0"
+// and "vim" for "vim-9.0.1677-no_x11" (the version being the first part
+// starting with a digit).
+fn package_stem(package: &str) -> &str {
+    let mut end = package.len();
+    let mut start = 0;
+    for part in package.split('-') {
+        if start > 0 && part.starts_with(|c: char| c.is_ascii_digit()) {
+            end = start - 1;
+            break;
+        }
+        start += part.len() + 1;
+    }
+    &package[..end]
+}
+
+impl InstalledPackages {
+    /// Creates an [`InstalledPackages`] from the output of `pkg_info -q`,
+    /// one package per line (package stems without version are also accepted).
+    pub fn from_pkg_info(output: &str) -> Self {
+        Self {
+            stems: output
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| package_stem(line).to_string())
+                .collect(),
+        }
+    }
+    /// Loads an [`InstalledPackages`] from a file holding the output of `pkg_info -q`.
+    pub fn load_from_file(file: impl AsRef<Path>) -> Result<Self, Error> {
+        let data = fs::read_to_string(file)?;
+        Ok(Self::from_pkg_info(&data))
+    }
+    /// Returns true if the given package is installed, whatever its
+    /// version, false otherwise.
+    pub fn contains(&self, package: &str) -> bool {
+        self.stems.contains(package_stem(package.trim()))
+    }
+}
+
+/// Report of the games that can be played with the installed packages,
+/// as returned by [`GameDataBase::check_installed_packages`].
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PackageReport<'a> {
+    /// Games whose runtime and setup packages are all installed.
+    pub installed: Vec<&'a Game>,
+    /// Games with missing packages, with the missing packages.
+    pub missing: Vec<(&'a Game, Vec<String>)>,
+    /// Games whose runtime is not in the [`RuntimePackages`] table.
+    pub unmapped: Vec<&'a Game>,
+}
+
+impl PackageReport<'_> {
+    /// Returns the packages to install to be able to play all the games
+    /// with missing packages, sorted alphabetically.
+    pub fn to_install(&self) -> Vec<&str> {
+        let mut packages: Vec<&str> = self
+            .missing
+            .iter()
+            .flat_map(|(_, packages)| packages.iter().map(|p| p.as_str()))
+            .collect();
+        packages.sort();
+        packages.dedup();
+        packages
+    }
+    /// Returns the `pkg_add` command installing the missing packages,
+    /// if any.
+    pub fn pkg_add_command(&self) -> Option<String> {
+        let packages = self.to_install();
+        match packages.is_empty() {
+            true => None,
+            false => Some(format!("pkg_add {}", packages.join(" "))),
+        }
+    }
+}
+
+impl GameDataBase {
+    // Returns the packages required by the game, None if its runtime is
+    // not mapped. Aliased runtimes are also looked up by canonical name.
+    fn required_packages(&self, game: &Game, table: &RuntimePackages) -> Option<Vec<String>> {
+        let mut packages: Vec<String> = Vec::new();
+        if let Some(runtime) = &game.runtime {
+            let mapped = table.get_packages(runtime).or_else(|| {
+                self.aliases
+                    .get_canonical_runtimes(runtime)
+                    .and_then(|canonical| table.get_packages(canonical))
+            });
+            packages.extend(mapped?.iter().cloned());
+        }
+        if let Some(setup) = &game.setup {
+            for package in setup.packages() {
+                if !packages.iter().any(|p| p.eq(package)) {
+                    packages.push(package.into());
+                }
+            }
+        }
+        Some(packages)
+    }
+    /// Reports which games can be played with the given installed packages,
+    /// which ones have missing packages and which ones have a runtime not
+    /// mapped to any package. The packages required by a game are the ones
+    /// providing its runtime and the ones listed in its setup. Games without
+    /// required package are not reported.
+    pub fn check_installed_packages(
+        &self,
+        installed: &InstalledPackages,
+        table: &RuntimePackages,
+    ) -> PackageReport<'_> {
+        let mut report = PackageReport::default();
+        for game in self.get_all_games() {
+            match self.required_packages(game, table) {
+                None => report.unmapped.push(game),
+                Some(packages) if packages.is_empty() => continue,
+                Some(packages) => {
+                    let missing: Vec<String> = packages
+                        .into_iter()
+                        .filter(|package| !installed.contains(package))
+                        .collect();
+                    match missing.is_empty() {
+                        true => report.installed.push(game),
+                        false => report.missing.push((game, missing)),
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod packages_tests {
+    use super::*;
+    #[test]
+    fn test_package_stem() {
+        assert_eq!(package_stem("godot-3.5.2p0"), "godot");
+        assert_eq!(package_stem("easyrpg-player-0.8"), "easyrpg-player");
+        assert_eq!(package_stem("vim-9.0.1677-no_x11"), "vim");
+        assert_eq!(package_stem("jdk-17.0.8.07.1v1"), "jdk");
+        assert_eq!(package_stem("fnaify"), "fnaify");
+        assert_eq!(package_stem("py3-pip"), "py3-pip");
+    }
+    #[test]
+    fn test_installed_packages() {
+        let installed = InstalledPackages::from_pkg_info("godot-3.5.2p0\n\nfnaify-2.2\n");
+        assert!(installed.contains("godot"));
+        assert!(installed.contains("fnaify-2.1"));
+        assert!(!installed.contains("hashlink"));
+    }
+    #[test]
+    fn test_runtime_packages_load_from_string() {
+        let table =
+            RuntimePackages::load_from_string("# comment\nHashLink = hashlink, sdl2\nHTML5 =\n")
+                .unwrap();
+        assert_eq!(
+            table.get_packages("hashlink"),
+            Some(&vec!["hashlink".to_string(), "sdl2".to_string()])
+        );
+        assert_eq!(table.get_packages("html5"), Some(&vec![]));
+        assert_eq!(table.get_packages("godot"), None);
+        for data in ["HashLink", " = hashlink"] {
+            let err = RuntimePackages::load_from_string(data).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+    #[test]
+    fn test_runtime_packages_extend() {
+        let mut table = RuntimePackages::builtin();
+        assert_eq!(
+            table.get_packages("easyrpg"),
+            Some(&vec!["easyrpg-player".to_string()])
+        );
+        table.extend(RuntimePackages::load_from_string("easyrpg = easyrpg-player, sdl2").unwrap());
+        assert_eq!(table.get_packages("easyrpg").unwrap().len(), 2);
+        assert!(table.get_packages("godot").is_some());
+    }
+    #[test]
+    fn test_check_installed_packages() {
+        let mut game1 = Game::new();
+        game1.uid = 1;
+        game1.name = "Game 1".into();
+        game1.runtime = Some("godot".into());
+        let mut game2 = Game::new();
+        game2.uid = 2;
+        game2.name = "Game 2".into();
+        game2.runtime = Some("HashLink".into());
+        game2.setup = Some("lwjgl".into());
+        let mut game3 = Game::new();
+        game3.uid = 3;
+        game3.name = "Game 3".into();
+        game3.runtime = Some("HumblePlay".into());
+        let game4 = Game::new();
+        let db = GameDataBase::new(vec![game1, game2, game3, game4]);
+        let installed = InstalledPackages::from_pkg_info("godot-3.5.2p0\n");
+        let report = db.check_installed_packages(&installed, &RuntimePackages::builtin());
+        assert_eq!(report.installed.len(), 1);
+        assert_eq!(report.installed[0].name, "Game 1");
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].0.name, "Game 2");
+        assert_eq!(report.to_install(), vec!["hashlink", "lwjgl"]);
+        assert_eq!(
+            report.pkg_add_command(),
+            Some("pkg_add hashlink lwjgl".to_string())
+        );
+        assert_eq!(report.unmapped.len(), 1);
+        assert_eq!(report.unmapped[0].name, "Game 3");
+    }
+}
@@ -1,20 +1,40 @@
+use crate::db::duplicates::normalize_url;
+use crate::db::index::{normalize_item, CollapsedSpellings};
+use crate::db::matcher::Matcher;
 use crate::db::Item;
 use crate::models::Store;
 use crate::{Game, GameDataBase, GameFilter, QueryResult, SearchType};
 
 use paste::paste;
+use std::sync::Arc;
+
+// Returns the last segment of an url path, e.g. the GOG game name
+fn url_slug(url: &str) -> String {
+    normalize_url(url)
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .replace('-', "_")
+}
 
 macro_rules! match_games_by {
     ($field:ident) => {
         paste! {
-            /// Returns the games for which the searched field exactly matches the given value.
-            pub fn [<match_games_by_ $field>](&self, field: &str) -> QueryResult<&Game> {
-                match self.[<$field s>].get(field) {
-                    Some(game_ids) => {
+            /// Returns the games for which the searched field matches the given value.
+            /// Items are compared on their normalized form (trimmed and case folded)
+            /// when the search is case insensitive, and on their trimmed form
+            /// otherwise, depending on the case sensitivity of the [`SearchType`].
+            pub fn [<match_games_by_ $field>](&self, field: &str, search_type: &SearchType) -> QueryResult<&Game> {
+                match self.[<$field s>].get(&normalize_item(field)) {
+                    Some(entry) => {
+                        let game_ids = match search_type.is_case_sensitive() {
+                            true => entry.game_ids_with_spelling(field),
+                            false => entry.game_ids(),
+                        };
                         let mut games: Vec<&Game> = Vec::new();
                         for game_id in game_ids {
-                            if let Some(game) = self.games.get(game_id) {
-                                games.push(game)
+                            if let Some(game) = self.games.get(&game_id) {
+                                games.push(game.as_ref())
                             }
                         }
                         QueryResult::new(games)
@@ -24,18 +44,45 @@ macro_rules! match_games_by {
             }
         }
     };
+    // Any alias of a canonical name returns the whole canonical group
+    (aliased $field:ident) => {
+        paste! {
+            /// Returns the games for which the searched field matches the given value.
+            /// Items are compared on their normalized form (trimmed and case folded)
+            /// when the search is case insensitive, and on their trimmed form
+            /// otherwise, depending on the case sensitivity of the [`SearchType`]. If the value is
+            /// a registered alias (see [`crate::db::AliasRegistry`]), the games of
+            /// the whole canonical group are returned whatever the [`SearchType`].
+            pub fn [<match_games_by_ $field>](&self, field: &str, search_type: &SearchType) -> QueryResult<&Game> {
+                let (key, aliased) = match self.aliases.[<get_canonical_ $field s>](field) {
+                    Some(canonical) => (normalize_item(canonical), true),
+                    None => (normalize_item(field), false),
+                };
+                match self.[<$field s>].get(&key) {
+                    Some(entry) => {
+                        let game_ids = match (aliased, search_type.is_case_sensitive()) {
+                            (false, true) => entry.game_ids_with_spelling(field),
+                            _ => entry.game_ids(),
+                        };
+                        self.match_games_by_ids(game_ids)
+                    }
+                    None => QueryResult::new(vec![]),
+                }
+            }
+        }
+    };
 }
 
 macro_rules! search_games_by {
     ($field:ident) => {
         paste! {
-            /// Returns the games for which the chosen field contains the given value.
-            /// It can be case sensitive or insensitive depending on the
-            /// [`SearchType`] variant.
+            /// Returns the games for which the chosen field matches the given value.
+            /// The value is searched within the field, or compared to it, depending
+            /// on the [`SearchType`] variant.
             pub fn [<search_games_by_ $field>](&self, pattern: &str, search_type: &SearchType) -> QueryResult<&Game> {
                 let games = GameFilter::default()
                         .[<set_ $field>](pattern)
-                        .filter_games(self.games.values().collect(), search_type);
+                        .filter_games(self.games.values().map(|game| game.as_ref()).collect(), search_type);
                 QueryResult::new(games)
             }
         }
@@ -45,9 +92,10 @@ macro_rules! search_games_by {
 macro_rules! get_all {
     ($field:ident) => {
         paste! {
-            /// Returns all the items for the chosen field.
+            /// Returns all the items for the chosen field, each item being
+            /// displayed using its most common spelling.
             pub fn [<get_all_ $field>](&self) -> QueryResult<&Item> {
-                let items: Vec<&Item> = self.$field.keys().collect();
+                let items: Vec<&Item> = self.$field.values().map(|entry| &entry.display).collect();
                 QueryResult::new(items)
             }
         }
@@ -60,7 +108,7 @@ macro_rules! get_all_with_ids {
             /// Returns all the items for the chosen field as well as the game ids
             /// associated to each item.
             pub fn [<get_all_ $field _with_ids>](&self) -> Vec<(String, Vec<u32>)> {
-                let mut items: Vec<(String, Vec<u32>)> = self.$field.iter().map(|a| (a.0.clone(), a.1.clone())).collect();
+                let mut items: Vec<(String, Vec<u32>)> = self.$field.values().map(|entry| (entry.display.clone(), entry.game_ids())).collect();
                 items.sort_by(|a,b| a.0.cmp(&b.0));
                 items
             }
@@ -68,33 +116,117 @@ macro_rules! get_all_with_ids {
     };
 }
 
+macro_rules! collapsed_spellings {
+    ($db:ident, $collapsed:ident; $($field:ident),+) => {
+        $(
+            for entry in $db.$field.values().filter(|entry| entry.spellings.len() > 1) {
+                $collapsed.push(CollapsedSpellings {
+                    field: stringify!($field),
+                    display: entry.display.clone(),
+                    spellings: entry
+                        .spellings
+                        .iter()
+                        .map(|(spelling, ids)| (spelling.clone(), ids.len()))
+                        .collect(),
+                });
+            }
+        )+
+    };
+}
+
 impl GameDataBase {
-    /// Returns the game with the given id.
+    /// Returns the game with the given id, a previous uid of the game
+    /// resolving to the game (see [`GameDataBase::add_uid_alias`]).
     pub fn get_game_by_id(&self, game_id: u32) -> Option<&Game> {
-        self.games.get(&game_id)
+        let uid = self.resolve_uid(game_id)?;
+        self.games.get(&uid).map(|game| game.as_ref())
+    }
+    /// Returns the game with the given id as an [`Arc<Game>`] that can
+    /// outlive the [`GameDataBase`] and be shared between threads. A
+    /// previous uid of the game resolves to the game.
+    pub fn get_shared_game_by_id(&self, game_id: u32) -> Option<Arc<Game>> {
+        let uid = self.resolve_uid(game_id)?;
+        self.games.get(&uid).cloned()
     }
-    /// Returns the first game found which names contains the given name.
-    /// It can be case sensitive or insensitive depending on the
-    /// [`SearchType`] variant.
+    /// Returns the first game found which names matches the given name.
+    /// The name is searched within the names, or compared to them,
+    /// depending on the [`SearchType`] variant.
     pub fn get_game_by_name(&self, name: &str, search_type: &SearchType) -> Option<&Game> {
-        let mut filter = GameFilter::default();
-        filter.set_name(name);
+        let matcher = Matcher::new(search_type, name);
         self.games
             .values()
-            .find(|game| filter.check_game(game, search_type))
+            .map(|game| game.as_ref())
+            .find(|game| game.name_matches(&matcher))
     }
     /// Returns the game with the given steam_id.
     pub fn get_game_by_steam_id(&self, steam_id: usize) -> Option<&Game> {
-        for game in self.games.values() {
-            if let Some(stores) = &game.stores {
-                for store in stores.inner_ref() {
-                    if store.store.eq(&Store::Steam) && store.id.eq(&Some(steam_id)) {
-                        return Some(game);
-                    }
-                }
-            }
-        }
-        None
+        self.get_game_by_store_id(&Store::Steam, steam_id)
+    }
+    /// Returns the game with the given id on the given store.
+    pub fn get_game_by_store_id(&self, store: &Store, id: usize) -> Option<&Game> {
+        self.games.values().map(|game| game.as_ref()).find(|game| {
+            game.stores.as_ref().map_or(false, |stores| {
+                stores
+                    .inner_ref()
+                    .iter()
+                    .any(|link| link.store.eq(store) && link.id.eq(&Some(id)))
+            })
+        })
+    }
+    /// Returns the game with the given store url (scheme, `www.`, case and
+    /// trailing slash being ignored).
+    pub fn get_game_by_store_url(&self, url: &str) -> Option<&Game> {
+        let url = normalize_url(url);
+        self.games.values().map(|game| game.as_ref()).find(|game| {
+            game.stores.as_ref().map_or(false, |stores| {
+                stores
+                    .inner_ref()
+                    .iter()
+                    .any(|link| normalize_url(&link.url).eq(&url))
+            })
+        })
+    }
+    /// Returns the game whose store url on the given store ends with the
+    /// given game name (e.g. `adventures_of_shuggy` for GOG, dashes and
+    /// underscores being considered equal).
+    pub fn get_game_by_store_slug(&self, store: &Store, slug: &str) -> Option<&Game> {
+        let slug = slug.trim().to_lowercase().replace('-', "_");
+        self.games.values().map(|game| game.as_ref()).find(|game| {
+            game.stores.as_ref().map_or(false, |stores| {
+                stores
+                    .inner_ref()
+                    .iter()
+                    .any(|link| link.store.eq(store) && url_slug(&link.url).eq(&slug))
+            })
+        })
+    }
+    /// Returns the games whose setup runs the given command (e.g. "fnaify").
+    pub fn match_games_needing_command(&self, command: &str) -> QueryResult<&Game> {
+        let games: Vec<&Game> = self
+            .games
+            .values()
+            .map(|game| game.as_ref())
+            .filter(|game| {
+                game.setup
+                    .as_ref()
+                    .map_or(false, |setup| setup.needs_command(command))
+            })
+            .collect();
+        QueryResult::new(games)
+    }
+    /// Returns the games whose setup requires the given OpenBSD package.
+    pub fn match_games_needing_package(&self, package: &str) -> QueryResult<&Game> {
+        let games: Vec<&Game> = self
+            .games
+            .values()
+            .map(|game| game.as_ref())
+            .filter(|game| {
+                game.setup
+                    .as_ref()
+                    .map_or(false, |setup| setup.needs_package(package))
+            })
+            .collect();
+        QueryResult::new(games)
     }
 
     /// Returns all games matching the given vector of game ids.
@@ -109,11 +241,11 @@ impl GameDataBase {
     }
     match_games_by!(tag);
     match_games_by!(year);
-    match_games_by!(engine);
-    match_games_by!(runtime);
+    match_games_by!(aliased engine);
+    match_games_by!(aliased runtime);
     match_games_by!(genre);
-    match_games_by!(dev);
-    match_games_by!(publi);
+    match_games_by!(aliased dev);
+    match_games_by!(aliased publi);
 
     search_games_by!(name);
     search_games_by!(tag);
@@ -130,13 +262,24 @@ impl GameDataBase {
         search_type: &SearchType,
         filter: &GameFilter,
     ) -> QueryResult<&Game> {
-        let games = filter.filter_games(self.games.values().collect(), search_type);
+        let games = filter.filter_games(
+            self.games.values().map(|game| game.as_ref()).collect(),
+            search_type,
+        );
         QueryResult::new(games)
     }
 
     /// Returns all games as a QueryResult.
     pub fn get_all_games(&self) -> QueryResult<&Game> {
-        let mut games: Vec<&Game> = self.games.values().collect();
+        let mut games: Vec<&Game> = self.games.values().map(|game| game.as_ref()).collect();
+        games.sort();
+        QueryResult::new(games)
+    }
+    /// Returns all games as a QueryResult of [`Arc<Game>`] that can
+    /// outlive the [`GameDataBase`] and be shared between threads,
+    /// sorted like [`GameDataBase::get_all_games`].
+    pub fn get_all_shared_games(&self) -> QueryResult<Arc<Game>> {
+        let mut games: Vec<Arc<Game>> = self.games.values().cloned().collect();
         games.sort();
         QueryResult::new(games)
     }
@@ -155,4 +298,14 @@ impl GameDataBase {
     get_all_with_ids!(years);
     get_all_with_ids!(devs);
     get_all_with_ids!(publis);
+
+    /// Returns the items whose raw spellings (e.g. "godot", "Godot" and
+    /// "Godot ") were collapsed into a single index entry, ordered by field
+    /// and display name.
+    pub fn get_collapsed_spellings(&self) -> Vec<CollapsedSpellings> {
+        let mut collapsed: Vec<CollapsedSpellings> = Vec::new();
+        collapsed_spellings!(self, collapsed; engines, runtimes, genres, tags, years, devs, publis);
+        collapsed.sort_by(|a, b| a.field.cmp(b.field).then_with(|| a.display.cmp(&b.display)));
+        collapsed
+    }
 }
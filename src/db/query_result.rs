@@ -1,20 +1,37 @@
 //! Provides a representation of the query result returned when
 //! interogating the [`crate::GameDataBase`]. [`QueryResult`] is itself queryable
 //! and return another [`QueryResult`].
+//!
+//! A [`QueryResult`] of `&Game` borrows from the [`crate::GameDataBase`]. It can
+//! be turned into a [`QueryResult`] of [`Arc<Game>`] using [`QueryResult::into_shared`]
+//! which is `Send + Sync + 'static` and can therefore be cached or sent
+//! to another thread. Both can be filtered in the same way.
+//! ```no_run
+//! # use libpobsd::{Parser, ParserResult, GameDataBase, Game, QueryResult, SearchType};
+//! # use std::sync::Arc;
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let st = SearchType::NotCaseSensitive;
+//! let games: QueryResult<Arc<Game>> = db.search_games_by_engine("godot", &st).into_shared(&db);
+//! let handle = std::thread::spawn(move || games.filter_games_by_year("2022", &st));
+//! ```
 use crate::db::Item;
-use crate::{Game, GameFilter, SearchType};
+use crate::{Game, GameDataBase, GameFilter, SearchType};
 
 use paste::paste;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 macro_rules! filter_games_by {
     ($field:ident) => {
         paste! {
-            /// Returns the games which field contains the given value.
-            /// It can be case sensitive or insensitive depending on the
-            /// [`SearchType`] variant.
-            pub fn [<filter_games_by_ $field>](self, field: &str, search_type: &SearchType) -> QueryResult<&'a Game> {
+            /// Returns the games which field matches the given value.
+            /// The value is searched within the field, or compared to it,
+            /// depending on the [`SearchType`] variant.
+            pub fn [<filter_games_by_ $field>](self, field: &str, search_type: &SearchType) -> QueryResult<T> {
                 let items = GameFilter::default().[<set_ $field>](field).filter_games(self.items, search_type);
                 QueryResult::new(items)
             }
@@ -68,11 +85,28 @@ impl<'a> QueryResult<&'a Item> {
     }
 }
 
-impl<'a> QueryResult<&'a Game> {
+impl QueryResult<&Game> {
+    /// Returns a [`QueryResult`] of [`Arc<Game>`] that does not borrow from
+    /// the [`GameDataBase`] the games come from. The games are not cloned
+    /// when they belong to the given [`GameDataBase`].
+    pub fn into_shared(self, db: &GameDataBase) -> QueryResult<Arc<Game>> {
+        let items: Vec<Arc<Game>> = self
+            .items
+            .into_iter()
+            .map(|game| match db.games.get(&game.uid) {
+                Some(shared) if std::ptr::eq(shared.as_ref(), game) => shared.clone(),
+                _ => Arc::new(game.clone()),
+            })
+            .collect();
+        QueryResult::new(items)
+    }
+}
+
+impl<T: AsRef<Game> + Ord> QueryResult<T> {
     /// Returns the game associated with the given name.
     /// It can be case sensitive or insensitive depending on the
     /// [`SearchType`] variant.
-    pub fn get_game_by_name(self, name: &str, search_type: &SearchType) -> Option<&'a Game> {
+    pub fn get_game_by_name(self, name: &str, search_type: &SearchType) -> Option<T> {
         let mut items = GameFilter::default()
             .set_name(name)
             .filter_games(self.items, search_type);
@@ -103,6 +137,11 @@ impl<T> IntoIterator for QueryResult<T> {
 mod query_results_tests {
     use crate::QueryResult;
     #[test]
+    fn test_shared_query_result_is_send_sync_static() {
+        fn is_send_sync_static<T: Send + Sync + 'static>() {}
+        is_send_sync_static::<QueryResult<std::sync::Arc<crate::Game>>>();
+    }
+    #[test]
     fn test_new() {
         let v = vec!["item1".to_string(), "item2".to_string()];
         let v2 = v.clone();
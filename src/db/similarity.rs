@@ -0,0 +1,376 @@
+//! Provides a simple recommendation engine returning the games of the
+//! [`GameDataBase`] that are the most similar to a given game.
+//!
+//! The similarity between two games combines several factors, each one
+//! being between 0 and 1 (items being compared on their normalized form,
+//! the developers, publishers, engines and runtimes being first resolved
+//! to their canonical name by the [`AliasRegistry`] of the database):
+//! * the Jaccard index of their tags and of their genres,
+//! * whether they share a developer, a publisher, an engine or a runtime,
+//! * how close their release years are.
+//!
+//! The factors are combined into a single score using [`SimilarityWeights`].
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! for similar in db.similar_games(1595434339, 5) {
+//!     println!("{}: {:.2}", similar.game.name, similar.score);
+//! }
+//! ```
+use crate::db::index::{normalize_item, IndexEntry};
+use crate::db::AliasRegistry;
+use crate::{Game, GameDataBase};
+
+use std::collections::{HashMap, HashSet};
+
+/// Number of years after which the release years of two games are
+/// no longer considered close.
+const YEAR_WINDOW: f64 = 5.0;
+
+/// Per-factor breakdown of the similarity between two games.
+/// Each factor is between 0 (nothing in common) and 1.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Similarity {
+    /// Jaccard index of the tags of the two games.
+    pub tags: f64,
+    /// Jaccard index of the genres of the two games.
+    pub genres: f64,
+    /// 1 if the two games share a developer, 0 otherwise.
+    pub devs: f64,
+    /// 1 if the two games share a publisher, 0 otherwise.
+    pub publis: f64,
+    /// 1 if the two games use the same engine, 0 otherwise.
+    pub engine: f64,
+    /// 1 if the two games use the same runtime, 0 otherwise.
+    pub runtime: f64,
+    /// Closeness of the release years of the two games, 1 for the same
+    /// year and decreasing linearly to 0 for games released five years
+    /// apart or when the year of one of them is unknown.
+    pub year: f64,
+}
+
+impl Similarity {
+    /// Computes the similarity between two games.
+    pub fn between(game: &Game, other: &Game) -> Self {
+        Self::between_with(&AliasRegistry::default(), game, other)
+    }
+    /// Computes the similarity between two games, the developers,
+    /// publishers, engines and runtimes being compared on their
+    /// canonical name according to the given [`AliasRegistry`].
+    pub fn between_with(aliases: &AliasRegistry, game: &Game, other: &Game) -> Self {
+        let devs = |item: &str| canonical(aliases.get_canonical_devs(item), item);
+        let publis = |item: &str| canonical(aliases.get_canonical_publis(item), item);
+        let engines = |item: &str| canonical(aliases.get_canonical_engines(item), item);
+        let runtimes = |item: &str| canonical(aliases.get_canonical_runtimes(item), item);
+        Self {
+            tags: jaccard(&game.tags, &other.tags),
+            genres: jaccard(&game.genres, &other.genres),
+            devs: share_item(&game.devs, &other.devs, devs),
+            publis: share_item(&game.publis, &other.publis, publis),
+            engine: same_item(&game.engine, &other.engine, engines),
+            runtime: same_item(&game.runtime, &other.runtime, runtimes),
+            year: year_closeness(&game.year, &other.year),
+        }
+    }
+    /// Combines the factors into a single score using the given weights.
+    pub fn score(&self, weights: &SimilarityWeights) -> f64 {
+        self.tags * weights.tags
+            + self.genres * weights.genres
+            + self.devs * weights.devs
+            + self.publis * weights.publis
+            + self.engine * weights.engine
+            + self.runtime * weights.runtime
+            + self.year * weights.year
+    }
+}
+
+/// Weights given to each factor of the [`Similarity`] when computing
+/// the overall score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarityWeights {
+    /// Weight of the tags factor (default: 3).
+    pub tags: f64,
+    /// Weight of the genres factor (default: 2).
+    pub genres: f64,
+    /// Weight of the developers factor (default: 1.5).
+    pub devs: f64,
+    /// Weight of the publishers factor (default: 0.5).
+    pub publis: f64,
+    /// Weight of the engine factor (default: 1).
+    pub engine: f64,
+    /// Weight of the runtime factor (default: 0.5).
+    pub runtime: f64,
+    /// Weight of the release year factor (default: 0.5).
+    pub year: f64,
+}
+
+impl Default for SimilarityWeights {
+    fn default() -> Self {
+        Self {
+            tags: 3.0,
+            genres: 2.0,
+            devs: 1.5,
+            publis: 0.5,
+            engine: 1.0,
+            runtime: 0.5,
+            year: 0.5,
+        }
+    }
+}
+
+/// A game similar to the one given to [`GameDataBase::similar_games`]
+/// with its score and the breakdown of its similarity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarGame<'a> {
+    /// The similar game.
+    pub game: &'a Game,
+    /// Overall similarity score (weighted sum of the factors).
+    pub score: f64,
+    /// Breakdown of the similarity per factor.
+    pub similarity: Similarity,
+}
+
+fn jaccard(items: &Option<Vec<String>>, others: &Option<Vec<String>>) -> f64 {
+    match (items, others) {
+        (Some(items), Some(others)) => {
+            let items: HashSet<String> = items.iter().map(|i| normalize_item(i)).collect();
+            let others: HashSet<String> = others.iter().map(|i| normalize_item(i)).collect();
+            let union = items.union(&others).count();
+            match union {
+                0 => 0.0,
+                _ => items.intersection(&others).count() as f64 / union as f64,
+            }
+        }
+        _ => 0.0,
+    }
+}
+
+// Returns the normalized canonical name of an item
+fn canonical(canonical: Option<&String>, item: &str) -> String {
+    normalize_item(canonical.map_or(item, |canonical| canonical.as_str()))
+}
+
+fn share_item(
+    items: &Option<Vec<String>>,
+    others: &Option<Vec<String>>,
+    canonical: impl Fn(&str) -> String,
+) -> f64 {
+    match (items, others) {
+        (Some(items), Some(others)) => {
+            let others: HashSet<String> = others.iter().map(|other| canonical(other)).collect();
+            if items.iter().any(|item| others.contains(&canonical(item))) {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        _ => 0.0,
+    }
+}
+
+fn same_item(
+    item: &Option<String>,
+    other: &Option<String>,
+    canonical: impl Fn(&str) -> String,
+) -> f64 {
+    match (item, other) {
+        (Some(item), Some(other)) if canonical(item).eq(&canonical(other)) => 1.0,
+        _ => 0.0,
+    }
+}
+
+fn year_closeness(year: &Option<String>, other: &Option<String>) -> f64 {
+    let year = year.as_ref().and_then(|y| y.trim().parse::<i32>().ok());
+    let other = other.as_ref().and_then(|y| y.trim().parse::<i32>().ok());
+    match (year, other) {
+        (Some(year), Some(other)) => {
+            let diff = f64::from((year - other).abs());
+            (1.0 - diff / YEAR_WINDOW).max(0.0)
+        }
+        _ => 0.0,
+    }
+}
+
+// Adds to the candidates the ids of the games associated to the given items.
+fn add_candidates<'a>(
+    candidates: &mut HashSet<u32>,
+    index: &HashMap<String, IndexEntry>,
+    items: impl IntoIterator<Item = &'a String>,
+) {
+    for item in items {
+        if let Some(entry) = index.get(&normalize_item(item)) {
+            candidates.extend(entry.game_ids());
+        }
+    }
+}
+
+impl GameDataBase {
+    // Collects the ids of the games sharing at least one indexed item
+    // with the given game.
+    fn similar_game_candidates(&self, game: &Game) -> HashSet<u32> {
+        let mut candidates: HashSet<u32> = HashSet::new();
+        add_candidates(&mut candidates, &self.tags, game.tags.iter().flatten());
+        add_candidates(&mut candidates, &self.genres, game.genres.iter().flatten());
+        // aliased items are indexed under their canonical name
+        add_candidates(
+            &mut candidates,
+            &self.devs,
+            game.devs
+                .iter()
+                .flatten()
+                .map(|dev| self.aliases.get_canonical_devs(dev).unwrap_or(dev)),
+        );
+        add_candidates(
+            &mut candidates,
+            &self.publis,
+            game.publis
+                .iter()
+                .flatten()
+                .map(|publi| self.aliases.get_canonical_publis(publi).unwrap_or(publi)),
+        );
+        add_candidates(
+            &mut candidates,
+            &self.engines,
+            game.engine
+                .iter()
+                .map(|engine| self.aliases.get_canonical_engines(engine).unwrap_or(engine)),
+        );
+        add_candidates(
+            &mut candidates,
+            &self.runtimes,
+            game.runtime.iter().map(|runtime| {
+                self.aliases
+                    .get_canonical_runtimes(runtime)
+                    .unwrap_or(runtime)
+            }),
+        );
+        candidates.remove(&game.uid);
+        candidates
+    }
+    /// Returns at most `n` games similar to the game with the given id,
+    /// ranked by decreasing score using the default [`SimilarityWeights`].
+    /// Only games sharing at least a tag, a genre, a developer, a publisher,
+    /// an engine or a runtime with the given game are considered.
+    pub fn similar_games(&self, game_id: u32, n: usize) -> Vec<SimilarGame<'_>> {
+        self.similar_games_with_weights(game_id, n, &SimilarityWeights::default())
+    }
+    /// Same as [`GameDataBase::similar_games`] but using the given
+    /// [`SimilarityWeights`].
+    pub fn similar_games_with_weights(
+        &self,
+        game_id: u32,
+        n: usize,
+        weights: &SimilarityWeights,
+    ) -> Vec<SimilarGame<'_>> {
+        let game = match self.get_game_by_id(game_id) {
+            Some(game) => game,
+            None => return vec![],
+        };
+        let mut similar_games: Vec<SimilarGame> = self
+            .similar_game_candidates(game)
+            .into_iter()
+            .filter_map(|id| self.get_game_by_id(id))
+            .map(|other| {
+                let similarity = Similarity::between_with(&self.aliases, game, other);
+                SimilarGame {
+                    game: other,
+                    score: similarity.score(weights),
+                    similarity,
+                }
+            })
+            .filter(|similar| similar.score > 0.0)
+            .collect();
+        similar_games.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.game.cmp(b.game)));
+        similar_games.truncate(n);
+        similar_games
+    }
+}
+
+#[cfg(test)]
+mod similarity_tests {
+    use super::*;
+
+    fn create_game(uid: u32, name: &str, tags: &[&str], year: &str) -> Game {
+        let mut game = Game::new();
+        game.uid = uid;
+        game.name = name.into();
+        game.tags = Some(tags.iter().map(|tag| tag.to_string()).collect());
+        game.year = Some(year.into());
+        game
+    }
+    #[test]
+    fn test_jaccard() {
+        let a = Some(vec!["a".to_string(), "b".to_string()]);
+        let b = Some(vec!["B ".to_string(), "c".to_string()]);
+        assert_eq!(jaccard(&a, &b), 1.0 / 3.0);
+        assert_eq!(jaccard(&a, &a), 1.0);
+        assert_eq!(jaccard(&a, &None), 0.0);
+        assert_eq!(jaccard(&Some(vec![]), &Some(vec![])), 0.0);
+    }
+    #[test]
+    fn test_year_closeness() {
+        let y2010 = Some("2010".to_string());
+        assert_eq!(year_closeness(&y2010, &y2010), 1.0);
+        assert_eq!(year_closeness(&y2010, &Some("2012".into())), 0.6);
+        assert_eq!(year_closeness(&y2010, &Some("2020".into())), 0.0);
+        assert_eq!(year_closeness(&y2010, &Some("early access".into())), 0.0);
+        assert_eq!(year_closeness(&y2010, &None), 0.0);
+    }
+    #[test]
+    fn test_similarity_between() {
+        let mut game1 = create_game(1, "Game 1", &["indie", "2d"], "2010");
+        let mut game2 = create_game(2, "Game 2", &["indie"], "2011");
+        game1.engine = Some("godot".into());
+        game2.engine = Some("godot".into());
+        game1.devs = Some(vec!["dev1".into(), "dev2".into()]);
+        game2.devs = Some(vec!["dev2".into()]);
+        let similarity = Similarity::between(&game1, &game2);
+        assert_eq!(similarity.tags, 0.5);
+        assert_eq!(similarity.genres, 0.0);
+        assert_eq!(similarity.devs, 1.0);
+        assert_eq!(similarity.publis, 0.0);
+        assert_eq!(similarity.engine, 1.0);
+        assert_eq!(similarity.runtime, 0.0);
+        assert_eq!(similarity.year, 0.8);
+        let score = similarity.score(&SimilarityWeights::default());
+        assert_eq!(score, 0.5 * 3.0 + 1.5 + 1.0 + 0.8 * 0.5);
+    }
+    #[test]
+    fn test_similar_games_ranking() {
+        let game1 = create_game(1, "Game 1", &["indie", "2d"], "2010");
+        let game2 = create_game(2, "Game 2", &["indie"], "2010");
+        let game3 = create_game(3, "Game 3", &["indie", "2d"], "2010");
+        let game4 = create_game(4, "Game 4", &["3d"], "2010");
+        let db = GameDataBase::new(vec![game1, game2, game3, game4]);
+        let similar = db.similar_games(1, 10);
+        let names: Vec<&str> = similar.iter().map(|s| s.game.name.as_str()).collect();
+        // Game 4 shares nothing indexed with Game 1
+        assert_eq!(names, vec!["Game 3", "Game 2"]);
+        assert_eq!(similar[0].similarity.tags, 1.0);
+        let similar = db.similar_games(1, 1);
+        assert_eq!(similar.len(), 1);
+        assert!(db.similar_games(5, 10).is_empty());
+    }
+    #[test]
+    fn test_similar_games_with_aliased_dev() {
+        let mut game1 = create_game(1, "Game 1", &["indie"], "2010");
+        let mut game2 = create_game(2, "Game 2", &["3d"], "2020");
+        game1.devs = Some(vec!["Devolver".into()]);
+        game2.devs = Some(vec!["Devolver Digital Inc.".into()]);
+        let mut aliases = AliasRegistry::new();
+        aliases.add_devs_alias("Devolver Digital", "Devolver");
+        aliases.add_devs_alias("Devolver Digital", "Devolver Digital Inc.");
+        assert_eq!(Similarity::between(&game1, &game2).devs, 0.0);
+        assert_eq!(Similarity::between_with(&aliases, &game1, &game2).devs, 1.0);
+        let db = GameDataBase::with_aliases(vec![game1, game2], aliases);
+        let similar = db.similar_games(1, 10);
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].similarity.devs, 1.0);
+        assert_eq!(similar[0].score, 1.5);
+    }
+}
@@ -0,0 +1,628 @@
+//! Provides a compact binary snapshot of the [`GameDataBase`] so that
+//! short-lived processes do not have to reparse the PlayOnBSD database
+//! and rebuild the indices each time they start.
+//!
+//! A snapshot starts with a magic number, the version of the snapshot
+//! format and a hash of the content of the database file it was built
+//! from, followed by the games, the indices and the aliases. When loading a snapshot,
+//! the hash is compared to the one of the current database file and the
+//! database is reparsed if they differ.
+//!
+//! The [`AliasRegistry`] is not derived from the database file: it has to be
+//! given again when loading a snapshot, the one saved in the snapshot only
+//! sparing the rebuild of the indices when it did not change.
+use crate::db::index::IndexEntry;
+use crate::db::AliasRegistry;
+use crate::models::{GameDate, GameStatus, Setup, Status, Store, StoreLink, StoreLinks};
+use crate::{Game, GameDataBase, Parser};
+
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Magic number at the start of every snapshot file.
+const MAGIC: &[u8; 8] = b"POBSDSNP";
+/// Version of the snapshot format. It must be bumped every time the
+/// layout of the snapshot or the normalization of the index keys changes.
+pub(crate) const SNAPSHOT_VERSION: u32 = 7;
+
+// FNV-1a 64 bits, the 32 bits FNV used for the uid being
+// too collision prone for a content hash.
+pub(crate) fn content_hash(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/* ------------------------- ENCODER --------------------------*/
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+    fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+    fn len(&mut self, value: usize) {
+        self.u32(value as u32);
+    }
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+    fn opt_str(&mut self, value: &Option<String>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.str(value);
+            }
+            None => self.u8(0),
+        }
+    }
+    fn opt_usize(&mut self, value: &Option<usize>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.u64(*value as u64);
+            }
+            None => self.u8(0),
+        }
+    }
+    fn opt_vec(&mut self, value: &Option<Vec<String>>) {
+        match value {
+            Some(items) => {
+                self.u8(1);
+                self.len(items.len());
+                for item in items {
+                    self.str(item);
+                }
+            }
+            None => self.u8(0),
+        }
+    }
+    fn date(&mut self, value: &GameDate) {
+        match value {
+            GameDate::Unknown => self.u8(0),
+            GameDate::Invalid(value) => {
+                self.u8(1);
+                self.str(value);
+            }
+            GameDate::Known(date) => {
+                self.u8(2);
+                self.u32(date.num_days_from_ce() as u32);
+            }
+        }
+    }
+    fn stores(&mut self, value: &Option<StoreLinks>) {
+        match value {
+            Some(stores) => {
+                self.u8(1);
+                self.len(stores.inner_ref().len());
+                for store in stores.inner_ref() {
+                    self.u8(store_to_u8(&store.store));
+                    self.str(&store.url);
+                    self.opt_usize(&store.id);
+                }
+            }
+            None => self.u8(0),
+        }
+    }
+    fn status(&mut self, value: &GameStatus) {
+        self.u8(status_to_u8(&value.status));
+        self.opt_str(&value.message);
+    }
+    fn game(&mut self, game: &Game) {
+        self.u32(game.uid);
+        self.str(&game.name);
+        self.opt_str(&game.cover);
+        self.opt_str(&game.engine);
+        self.opt_str(&game.setup.as_ref().map(|setup| setup.to_string()));
+        self.opt_str(&game.runtime);
+        self.stores(&game.stores);
+        self.opt_str(&game.hints);
+        self.opt_vec(&game.genres);
+        self.opt_vec(&game.tags);
+        self.opt_str(&game.year);
+        self.opt_vec(&game.devs);
+        self.opt_vec(&game.publis);
+        self.opt_str(&game.version);
+        self.status(&game.status);
+        self.date(&game.added);
+        self.date(&game.updated);
+        self.opt_usize(&game.igdb_id);
+        self.opt_usize(&game.id.map(|id| id as usize));
+        self.len(game.extra.len());
+        for (key, value) in &game.extra {
+            self.str(key);
+            self.opt_str(value);
+        }
+    }
+    fn index(&mut self, index: &HashMap<String, IndexEntry>) {
+        self.len(index.len());
+        for (item, entry) in index {
+            self.str(item);
+            self.str(&entry.display);
+            self.len(entry.spellings.len());
+            for (spelling, ids) in &entry.spellings {
+                self.str(spelling);
+                self.len(ids.len());
+                for id in ids {
+                    self.u32(*id);
+                }
+            }
+        }
+    }
+    fn aliases(&mut self, aliases: &HashMap<String, String>) {
+        self.len(aliases.len());
+        for (alias, canonical) in aliases {
+            self.str(alias);
+            self.str(canonical);
+        }
+    }
+}
+
+/* ------------------------- DECODER --------------------------*/
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid_data("Truncated snapshot"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, Error> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+    fn u64(&mut self) -> Result<u64, Error> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+    fn len(&mut self) -> Result<usize, Error> {
+        Ok(self.u32()? as usize)
+    }
+    fn flag(&mut self) -> Result<bool, Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("Invalid option flag in snapshot")),
+        }
+    }
+    fn str(&mut self) -> Result<String, Error> {
+        let len = self.len()?;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| invalid_data("Invalid string in snapshot"))
+    }
+    fn opt_str(&mut self) -> Result<Option<String>, Error> {
+        match self.flag()? {
+            true => Ok(Some(self.str()?)),
+            false => Ok(None),
+        }
+    }
+    fn opt_usize(&mut self) -> Result<Option<usize>, Error> {
+        match self.flag()? {
+            true => Ok(Some(self.u64()? as usize)),
+            false => Ok(None),
+        }
+    }
+    fn opt_vec(&mut self) -> Result<Option<Vec<String>>, Error> {
+        match self.flag()? {
+            true => {
+                let len = self.len()?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.str()?);
+                }
+                Ok(Some(items))
+            }
+            false => Ok(None),
+        }
+    }
+    fn date(&mut self) -> Result<GameDate, Error> {
+        match self.u8()? {
+            0 => Ok(GameDate::Unknown),
+            1 => Ok(GameDate::Invalid(self.str()?)),
+            2 => NaiveDate::from_num_days_from_ce_opt(self.u32()? as i32)
+                .map(GameDate::Known)
+                .ok_or_else(|| invalid_data("Invalid date in snapshot")),
+            _ => Err(invalid_data("Invalid date in snapshot")),
+        }
+    }
+    fn stores(&mut self) -> Result<Option<StoreLinks>, Error> {
+        match self.flag()? {
+            true => {
+                let len = self.len()?;
+                let mut stores = StoreLinks::default();
+                for _ in 0..len {
+                    stores.push(StoreLink {
+                        store: store_from_u8(self.u8()?)?,
+                        url: self.str()?,
+                        id: self.opt_usize()?,
+                    });
+                }
+                Ok(Some(stores))
+            }
+            false => Ok(None),
+        }
+    }
+    fn status(&mut self) -> Result<GameStatus, Error> {
+        let status = status_from_u8(self.u8()?)?;
+        Ok(GameStatus::new(status, self.opt_str()?))
+    }
+    fn game(&mut self) -> Result<Game, Error> {
+        let mut game = Game {
+            uid: self.u32()?,
+            name: self.str()?,
+            cover: self.opt_str()?,
+            engine: self.opt_str()?,
+            setup: self.opt_str()?.map(Setup::from),
+            runtime: self.opt_str()?,
+            stores: self.stores()?,
+            hints: self.opt_str()?,
+            genres: self.opt_vec()?,
+            tags: self.opt_vec()?,
+            year: self.opt_str()?,
+            devs: self.opt_vec()?,
+            publis: self.opt_vec()?,
+            version: self.opt_str()?,
+            status: self.status()?,
+            added: self.date()?,
+            updated: self.date()?,
+            igdb_id: self.opt_usize()?,
+            id: self.opt_usize()?.map(|id| id as u32),
+            extra: Vec::new(),
+        };
+        for _ in 0..self.len()? {
+            game.extra.push((self.str()?, self.opt_str()?));
+        }
+        Ok(game)
+    }
+    fn index(&mut self) -> Result<HashMap<String, IndexEntry>, Error> {
+        let len = self.len()?;
+        let mut index = HashMap::new();
+        for _ in 0..len {
+            let item = self.str()?;
+            let mut entry = IndexEntry {
+                display: self.str()?,
+                spellings: Vec::new(),
+            };
+            let spellings_len = self.len()?;
+            for _ in 0..spellings_len {
+                let spelling = self.str()?;
+                let ids_len = self.len()?;
+                let mut ids = Vec::new();
+                for _ in 0..ids_len {
+                    ids.push(self.u32()?);
+                }
+                entry.spellings.push((spelling, ids));
+            }
+            index.insert(item, entry);
+        }
+        Ok(index)
+    }
+    fn aliases(&mut self) -> Result<HashMap<String, String>, Error> {
+        let len = self.len()?;
+        let mut aliases = HashMap::new();
+        for _ in 0..len {
+            aliases.insert(self.str()?, self.str()?);
+        }
+        Ok(aliases)
+    }
+}
+
+fn store_to_u8(store: &Store) -> u8 {
+    match store {
+        Store::Steam => 0,
+        Store::Gog => 1,
+        Store::HumbleBundle => 2,
+        Store::ItchIo => 3,
+        Store::Epic => 4,
+        Store::Unknown => 5,
+    }
+}
+
+fn store_from_u8(value: u8) -> Result<Store, Error> {
+    match value {
+        0 => Ok(Store::Steam),
+        1 => Ok(Store::Gog),
+        2 => Ok(Store::HumbleBundle),
+        3 => Ok(Store::ItchIo),
+        4 => Ok(Store::Epic),
+        5 => Ok(Store::Unknown),
+        _ => Err(invalid_data("Invalid store in snapshot")),
+    }
+}
+
+fn status_to_u8(status: &Status) -> u8 {
+    match status {
+        Status::Unknown => 0,
+        Status::DoesNotRun => 1,
+        Status::Launches => 2,
+        Status::MajorBugs => 3,
+        Status::MediumImpact => 4,
+        Status::MinorBugs => 5,
+        Status::Completable => 6,
+        Status::Perfect => 7,
+    }
+}
+
+fn status_from_u8(value: u8) -> Result<Status, Error> {
+    match value {
+        0 => Ok(Status::Unknown),
+        1 => Ok(Status::DoesNotRun),
+        2 => Ok(Status::Launches),
+        3 => Ok(Status::MajorBugs),
+        4 => Ok(Status::MediumImpact),
+        5 => Ok(Status::MinorBugs),
+        6 => Ok(Status::Completable),
+        7 => Ok(Status::Perfect),
+        _ => Err(invalid_data("Invalid status in snapshot")),
+    }
+}
+
+/* ------------------------ GAMEDATABASE ----------------------*/
+
+impl GameDataBase {
+    /// Encodes the database into a snapshot tagged with the given
+    /// hash of the source database.
+    pub(crate) fn to_snapshot_bytes(&self, source_hash: u64) -> Vec<u8> {
+        let mut enc = Encoder::default();
+        enc.buf.extend_from_slice(MAGIC);
+        enc.u32(SNAPSHOT_VERSION);
+        enc.u64(source_hash);
+        enc.len(self.games.len());
+        for game in self.games.values() {
+            enc.game(game);
+        }
+        for index in [
+            &self.engines,
+            &self.runtimes,
+            &self.genres,
+            &self.tags,
+            &self.years,
+            &self.devs,
+            &self.publis,
+        ] {
+            enc.index(index);
+        }
+        for aliases in [
+            &self.aliases.engines,
+            &self.aliases.runtimes,
+            &self.aliases.devs,
+            &self.aliases.publis,
+        ] {
+            enc.aliases(aliases);
+        }
+        enc.len(self.uid_aliases.len());
+        for (old, current) in &self.uid_aliases {
+            enc.u32(*old);
+            enc.u32(*current);
+        }
+        enc.buf
+    }
+    /// Decodes a snapshot, returning an error if it is not a valid
+    /// snapshot or if it was not built from a database with the
+    /// given hash.
+    pub(crate) fn from_snapshot_bytes(data: &[u8], source_hash: u64) -> Result<Self, Error> {
+        match GameDataBase::decode_snapshot(data)? {
+            (hash, db) if hash == source_hash => Ok(db),
+            _ => Err(invalid_data("Snapshot is outdated")),
+        }
+    }
+    // Decodes a snapshot whatever the database it was built from,
+    // returning the hash of this database along with the decoded one
+    fn decode_snapshot(data: &[u8]) -> Result<(u64, Self), Error> {
+        let mut dec = Decoder::new(data);
+        if dec.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("Not a snapshot"));
+        }
+        if dec.u32()? != SNAPSHOT_VERSION {
+            return Err(invalid_data("Unsupported snapshot version"));
+        }
+        let source_hash = dec.u64()?;
+        let mut db = GameDataBase::default();
+        let len = dec.len()?;
+        for _ in 0..len {
+            let game = dec.game()?;
+            db.games.insert(game.uid, Arc::new(game));
+        }
+        db.engines = dec.index()?;
+        db.runtimes = dec.index()?;
+        db.genres = dec.index()?;
+        db.tags = dec.index()?;
+        db.years = dec.index()?;
+        db.devs = dec.index()?;
+        db.publis = dec.index()?;
+        db.aliases.engines = dec.aliases()?;
+        db.aliases.runtimes = dec.aliases()?;
+        db.aliases.devs = dec.aliases()?;
+        db.aliases.publis = dec.aliases()?;
+        for _ in 0..dec.len()? {
+            db.uid_aliases.insert(dec.u32()?, dec.u32()?);
+        }
+        if dec.pos != data.len() {
+            return Err(invalid_data("Trailing data in snapshot"));
+        }
+        Ok((source_hash, db))
+    }
+    /// Saves a binary snapshot of the database, including its indices,
+    /// into the `snapshot` file. The snapshot records a hash of the
+    /// `database` file (the PlayOnBSD database the [`GameDataBase`] was
+    /// built from) so that [`GameDataBase::load_snapshot`] can detect
+    /// when the snapshot is outdated.
+    ///
+    /// The snapshot is first written to a temporary file which is then
+    /// renamed, so that concurrent readers never see a partial snapshot.
+    pub fn save_snapshot(
+        &self,
+        snapshot: impl AsRef<Path>,
+        database: impl AsRef<Path>,
+    ) -> Result<(), Error> {
+        let snapshot: &Path = snapshot.as_ref();
+        let source_hash = content_hash(&fs::read(database)?);
+        let mut tmp = snapshot.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, self.to_snapshot_bytes(source_hash))?;
+        fs::rename(&tmp, snapshot)
+    }
+    /// Loads the database from the `snapshot` file if it was built from
+    /// the current content of the `database` file. Otherwise (missing,
+    /// corrupted or outdated snapshot), the `database` file is reparsed
+    /// using the default [`Parser`].
+    ///
+    /// The returned database does not use any alias, see
+    /// [`GameDataBase::load_snapshot_with_aliases`].
+    ///
+    /// An error is only returned if the `database` file cannot be read.
+    pub fn load_snapshot(
+        snapshot: impl AsRef<Path>,
+        database: impl AsRef<Path>,
+    ) -> Result<GameDataBase, Error> {
+        GameDataBase::load_snapshot_with_aliases(snapshot, database, AliasRegistry::default())
+    }
+    /// Same as [`GameDataBase::load_snapshot`], the returned database
+    /// using the given [`AliasRegistry`] whether it was loaded from the
+    /// snapshot or reparsed.
+    ///
+    /// When the snapshot is outdated, the uid aliases it holds are kept
+    /// and the uids of the games changed since are registered as aliases
+    /// (see [`GameDataBase::link_previous_uids`]).
+    pub fn load_snapshot_with_aliases(
+        snapshot: impl AsRef<Path>,
+        database: impl AsRef<Path>,
+        aliases: AliasRegistry,
+    ) -> Result<GameDataBase, Error> {
+        let data = fs::read_to_string(database)?;
+        let source_hash = content_hash(data.as_bytes());
+        let mut previous = None;
+        if let Ok(bytes) = fs::read(snapshot) {
+            match GameDataBase::from_snapshot_bytes(&bytes, source_hash) {
+                Ok(mut db) => {
+                    // the indices are only rebuilt if the aliases changed
+                    db.set_aliases(aliases);
+                    return Ok(db);
+                }
+                Err(_) => previous = GameDataBase::decode_snapshot(&bytes).ok().map(|(_, db)| db),
+            }
+        }
+        let mut db =
+            GameDataBase::with_aliases(Parser::default().load_from_string(&data).into(), aliases);
+        if let Some(previous) = previous {
+            db.link_previous_uids(&previous);
+        }
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use super::*;
+    use crate::SearchType;
+
+    fn create_db() -> GameDataBase {
+        let mut game1 = Game::new();
+        game1.uid = 1;
+        game1.name = "Game 1".into();
+        game1.engine = Some("godot".into());
+        game1.tags = Some(vec!["indie".into(), "2d".into()]);
+        game1.stores = Some(StoreLinks(vec![StoreLink::from(
+            "https://store.steampowered.com/app/1869200/The_Adventures_of_Mr_Hat/",
+        )]));
+        game1.status = GameStatus::new(Status::Completable, Some("(2022-05-13)".into()));
+        game1.added = NaiveDate::from_ymd_opt(2022, 5, 13).unwrap().into();
+        game1.updated = GameDate::Invalid("2022-13-05".into());
+        game1.igdb_id = Some(12);
+        game1.extra = vec![("Controller".into(), Some("full".into()))];
+        let mut game2 = Game::new();
+        game2.uid = 2;
+        game2.name = "Game 2".into();
+        game2.tags = Some(vec!["indie".into()]);
+        game2.engine = Some("Godot Engine".into());
+        let mut aliases = AliasRegistry::new();
+        aliases.add_engines_alias("Godot", "Godot Engine");
+        GameDataBase::with_aliases(vec![game1, game2], aliases)
+    }
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(content_hash(b"Game\ta"), content_hash(b"Game\tb"));
+    }
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut db = create_db();
+        db.add_uid_alias(7, 1);
+        let bytes = db.to_snapshot_bytes(42);
+        let db2 = GameDataBase::from_snapshot_bytes(&bytes, 42).unwrap();
+        assert_eq!(db.games, db2.games);
+        let game = db2.get_game_by_id(1).unwrap();
+        assert_eq!(game.status.message, Some("(2022-05-13)".into()));
+        assert_eq!(game.get_steam_id(), Some(1869200));
+        assert_eq!(db.get_all_tags_with_ids(), db2.get_all_tags_with_ids());
+        assert_eq!(
+            db.get_all_engines_with_ids(),
+            db2.get_all_engines_with_ids()
+        );
+        assert_eq!(db.aliases, db2.aliases);
+        assert_eq!(db2.get_game_by_id(7).map(|game| game.uid), Some(1));
+        assert_eq!(
+            db2.match_games_by_engine("godot engine", &SearchType::CaseSensitive)
+                .count,
+            2
+        );
+    }
+    #[test]
+    fn test_snapshot_with_wrong_hash() {
+        let bytes = create_db().to_snapshot_bytes(42);
+        let err = GameDataBase::from_snapshot_bytes(&bytes, 43).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+    #[test]
+    fn test_snapshot_with_wrong_version() {
+        let mut bytes = create_db().to_snapshot_bytes(42);
+        bytes[MAGIC.len()] = 0;
+        assert!(GameDataBase::from_snapshot_bytes(&bytes, 42).is_err());
+    }
+    #[test]
+    fn test_snapshot_truncated() {
+        let bytes = create_db().to_snapshot_bytes(42);
+        for len in [0, 10, 30, bytes.len() - 1] {
+            assert!(GameDataBase::from_snapshot_bytes(&bytes[..len], 42).is_err());
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Provides a [`Timeline`] following each game across a series of
+//! snapshots of the PlayOnBSD database (e.g. one per release of the
+//! database), recording when it was added, renamed or removed, when its
+//! status changed and when its store links appeared.
+//!
+//! The snapshots are given from the oldest to the most recent. A game of
+//! a snapshot is matched with the games of the previous snapshots by uid,
+//! then by IGDB id, Steam id and finally normalized name (see
+//! [`Game::get_normalized_name`]), so that renamed games are followed.
+//! Snapshots predating the `Added` field are migrated (see
+//! [`crate::parsing::schema::migrate_games`]) using the snapshot date.
+//!
+//! ## Examples
+//! ```no_run
+//! use chrono::NaiveDate;
+//! use libpobsd::db::Timeline;
+//!
+//! let mut timeline = Timeline::new();
+//! timeline
+//!     .add_snapshot_from_file("v1.0", NaiveDate::from_ymd_opt(2021, 1, 1), "games-1.0.db")
+//!     .expect("Could not open the database")
+//!     .add_snapshot_from_file("v2.0", NaiveDate::from_ymd_opt(2022, 1, 1), "games-2.0.db")
+//!     .expect("Could not open the database");
+//! for (game, event) in timeline.regressions() {
+//!     let snapshot = &timeline.snapshots[event.snapshot];
+//!     println!("{}: {} ({})", game.game.name, event.kind, snapshot.label);
+//! }
+//! ```
+use crate::models::game::normalize_name;
+use crate::parsing::schema::{migrate_games, SchemaVersion};
+use crate::{Game, GameDate, Parser, Status, StoreLink};
+
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+/// Snapshot of the database added to a [`Timeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineSnapshot {
+    /// Label of the snapshot (e.g. a git tag).
+    pub label: String,
+    /// Date of the snapshot, if known.
+    pub date: Option<NaiveDate>,
+}
+
+/// Change of a game between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineEventKind {
+    /// The game appeared in the database, on the given date (its `Added`
+    /// field) and with the given status. A game can be added again after
+    /// having been removed.
+    Added(GameDate, Status),
+    /// The game was removed from the database.
+    Removed,
+    /// The game was renamed (old and new names).
+    Renamed(String, String),
+    /// The status of the game changed (old and new status), along with
+    /// the date at which the game was tested if any.
+    StatusChanged(Status, Status, Option<NaiveDate>),
+    /// A store link of the game appeared.
+    StoreAdded(StoreLink),
+}
+
+impl fmt::Display for TimelineEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelineEventKind::Added(date, status) => {
+                write!(f, "added on {} ({})", date, status)
+            }
+            TimelineEventKind::Removed => write!(f, "removed"),
+            TimelineEventKind::Renamed(old, new) => {
+                write!(f, "renamed from \"{}\" to \"{}\"", old, new)
+            }
+            TimelineEventKind::StatusChanged(old, new, tested) => match tested {
+                Some(date) => write!(
+                    f,
+                    "status changed from {} to {} (tested on {})",
+                    old,
+                    new,
+                    date.format("%Y-%m-%d")
+                ),
+                None => write!(f, "status changed from {} to {}", old, new),
+            },
+            TimelineEventKind::StoreAdded(link) => write!(f, "store link {} added", link.url),
+        }
+    }
+}
+
+/// Change of a game in a given snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    /// Index of the snapshot in [`Timeline::snapshots`].
+    pub snapshot: usize,
+    /// The change.
+    pub kind: TimelineEventKind,
+}
+
+/// History of a game across the snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameTimeline {
+    /// The game as it appears in the last snapshot it belongs to.
+    pub game: Game,
+    /// Changes of the game, from the oldest to the most recent.
+    pub events: Vec<TimelineEvent>,
+}
+
+impl GameTimeline {
+    /// Returns the status of the game each time it was set, as the
+    /// index of the snapshot, the status and the tested date if any.
+    pub fn status_history(&self) -> Vec<(usize, Status, Option<NaiveDate>)> {
+        let mut history = Vec::new();
+        let mut tested = None;
+        for event in &self.events {
+            match &event.kind {
+                TimelineEventKind::Added(_, status) => {
+                    history.push((event.snapshot, status.clone(), tested))
+                }
+                TimelineEventKind::StatusChanged(_, status, date) => {
+                    tested = *date;
+                    history.push((event.snapshot, status.clone(), tested))
+                }
+                _ => {}
+            }
+        }
+        history
+    }
+    /// Returns true if the game belongs to the last snapshot.
+    pub fn is_present(&self) -> bool {
+        !matches!(
+            self.events.last().map(|event| &event.kind),
+            Some(TimelineEventKind::Removed)
+        )
+    }
+}
+
+/// Timeline of the games across a series of snapshots of the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timeline {
+    /// Snapshots, from the oldest to the most recent.
+    pub snapshots: Vec<TimelineSnapshot>,
+    /// History of each game, in the order the games first appeared.
+    pub games: Vec<GameTimeline>,
+    // timelines of the games of the last snapshot
+    present: HashSet<usize>,
+}
+
+impl Timeline {
+    /// Creates an empty [`Timeline`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Parses the database file in relaxed mode and adds its games as
+    /// the most recent snapshot. The games of a snapshot predating the
+    /// `Added` field are considered added on the date of the snapshot.
+    pub fn add_snapshot_from_file(
+        &mut self,
+        label: impl Into<String>,
+        date: Option<NaiveDate>,
+        file: impl AsRef<Path>,
+    ) -> Result<&mut Self, std::io::Error> {
+        let report = Parser::default().load_report_from_file(file)?;
+        let schema = report.schema;
+        let mut games: Vec<Game> = report.result.into();
+        if schema < SchemaVersion::LATEST {
+            games = migrate_games(games, schema, date).games;
+        }
+        Ok(self.add_snapshot(label, date, games))
+    }
+    /// Adds the given games as the most recent snapshot.
+    pub fn add_snapshot(
+        &mut self,
+        label: impl Into<String>,
+        date: Option<NaiveDate>,
+        games: Vec<Game>,
+    ) -> &mut Self {
+        let snapshot = self.snapshots.len();
+        self.snapshots.push(TimelineSnapshot {
+            label: label.into(),
+            date,
+        });
+        let mut uids: HashMap<u32, usize> = HashMap::new();
+        let mut igdb_ids: HashMap<usize, usize> = HashMap::new();
+        let mut steam_ids: HashMap<usize, usize> = HashMap::new();
+        let mut names: HashMap<String, usize> = HashMap::new();
+        for (index, timeline) in self.games.iter().enumerate() {
+            let game = &timeline.game;
+            uids.insert(game.uid, index);
+            if let Some(igdb_id) = game.igdb_id {
+                igdb_ids.insert(igdb_id, index);
+            }
+            if let Some(steam_id) = game.get_steam_id() {
+                steam_ids.insert(steam_id, index);
+            }
+            names.insert(game.get_normalized_name(), index);
+        }
+        let mut present: HashSet<usize> = HashSet::new();
+        for game in games {
+            let index = uids
+                .get(&game.uid)
+                .or_else(|| game.igdb_id.and_then(|id| igdb_ids.get(&id)))
+                .or_else(|| game.get_steam_id().and_then(|id| steam_ids.get(&id)))
+                .or_else(|| names.get(&normalize_name(&game.name)))
+                .copied()
+                // a game of the previous snapshots matches one game at most
+                .filter(|index| !present.contains(index));
+            match index {
+                Some(index) => {
+                    let was_present = self.present.contains(&index);
+                    let events = changes(&self.games[index].game, &game, was_present);
+                    let timeline = &mut self.games[index];
+                    timeline.events.extend(
+                        events
+                            .into_iter()
+                            .map(|kind| TimelineEvent { snapshot, kind }),
+                    );
+                    timeline.game = game;
+                    present.insert(index);
+                }
+                None => {
+                    let events = changes(&Game::default(), &game, false);
+                    self.games.push(GameTimeline {
+                        game,
+                        events: events
+                            .into_iter()
+                            .map(|kind| TimelineEvent { snapshot, kind })
+                            .collect(),
+                    });
+                    present.insert(self.games.len() - 1);
+                }
+            }
+        }
+        for index in self.present.difference(&present) {
+            self.games[*index].events.push(TimelineEvent {
+                snapshot,
+                kind: TimelineEventKind::Removed,
+            });
+        }
+        self.present = present;
+        self
+    }
+    /// Returns the history of the game with the given uid in the last
+    /// snapshot it belongs to.
+    pub fn get_game(&self, game_id: u32) -> Option<&GameTimeline> {
+        self.games
+            .iter()
+            .find(|timeline| timeline.game.uid == game_id)
+    }
+    /// Returns the status changes where the status went down, along with
+    /// the history of the game. A status going back to unknown (i.e. not
+    /// provided) is not considered as a regression.
+    pub fn regressions(&self) -> Vec<(&GameTimeline, &TimelineEvent)> {
+        let mut regressions = Vec::new();
+        for timeline in &self.games {
+            for event in &timeline.events {
+                if let TimelineEventKind::StatusChanged(old, new, _) = &event.kind {
+                    if new < old && *new != Status::Unknown {
+                        regressions.push((timeline, event));
+                    }
+                }
+            }
+        }
+        regressions
+    }
+}
+
+// Returns the changes between two versions of a game
+fn changes(old: &Game, new: &Game, was_present: bool) -> Vec<TimelineEventKind> {
+    let mut events = Vec::new();
+    if !was_present {
+        events.push(TimelineEventKind::Added(
+            new.added.clone(),
+            new.status.status.clone(),
+        ));
+    } else {
+        if old.name != new.name {
+            events.push(TimelineEventKind::Renamed(
+                old.name.clone(),
+                new.name.clone(),
+            ));
+        }
+        if old.status.status != new.status.status {
+            events.push(TimelineEventKind::StatusChanged(
+                old.status.status.clone(),
+                new.status.status.clone(),
+                new.status.get_tested_date(),
+            ));
+        }
+    }
+    let old_urls: Vec<&str> = match (was_present, &old.stores) {
+        (true, Some(stores)) => stores.inner_ref().iter().map(|s| s.url.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if let Some(stores) = &new.stores {
+        for store in stores.inner_ref() {
+            if !old_urls.contains(&store.url.as_str()) {
+                events.push(TimelineEventKind::StoreAdded(store.clone()));
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod timeline_tests {
+    use super::*;
+    use crate::{GameStatus, StoreLinks};
+
+    fn create_game(name: &str, status: Status, stores: &[&str]) -> Game {
+        let mut game = Game::new();
+        game.name = name.into();
+        game.uid = name.len() as u32;
+        game.status = GameStatus::new(status, Some("(2022-05-13)".into()));
+        if !stores.is_empty() {
+            game.stores = Some(StoreLinks(
+                stores.iter().map(|url| StoreLink::from(url)).collect(),
+            ));
+        }
+        game
+    }
+    #[test]
+    fn test_add_snapshot() {
+        let steam = "https://store.steampowered.com/app/1869200/Game/";
+        let gog = "https://www.gog.com/game/game";
+        let mut timeline = Timeline::new();
+        timeline
+            .add_snapshot(
+                "v1",
+                None,
+                vec![create_game("Game", Status::Completable, &[steam])],
+            )
+            .add_snapshot(
+                "v2",
+                None,
+                vec![create_game("The Game", Status::MinorBugs, &[steam, gog])],
+            )
+            .add_snapshot("v3", None, vec![]);
+        assert_eq!(timeline.games.len(), 1);
+        let game = &timeline.games[0];
+        let kinds: Vec<&TimelineEventKind> = game.events.iter().map(|e| &e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &TimelineEventKind::Added(GameDate::Unknown, Status::Completable),
+                &TimelineEventKind::StoreAdded(StoreLink::from(steam)),
+                &TimelineEventKind::Renamed("Game".into(), "The Game".into()),
+                &TimelineEventKind::StatusChanged(
+                    Status::Completable,
+                    Status::MinorBugs,
+                    NaiveDate::from_ymd_opt(2022, 5, 13)
+                ),
+                &TimelineEventKind::StoreAdded(StoreLink::from(gog)),
+                &TimelineEventKind::Removed,
+            ]
+        );
+        assert!(!game.is_present());
+        assert_eq!(
+            game.status_history(),
+            vec![
+                (0, Status::Completable, None),
+                (1, Status::MinorBugs, NaiveDate::from_ymd_opt(2022, 5, 13))
+            ]
+        );
+        let regressions = timeline.regressions();
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].1.snapshot, 1);
+    }
+}
@@ -0,0 +1,387 @@
+//! Provides a [`GameDataBaseWatcher`] holding a [`GameDataBase`] that is
+//! kept up to date with the PlayOnBSD database file it was loaded from.
+//!
+//! The watcher polls the database file in a background thread and, when
+//! the file changes, reparses it with a [`Parser`] and swaps the new
+//! [`GameDataBase`] in. Readers get an [`Arc<GameDataBase>`] and therefore
+//! either see the old or the new database, never a partially built one.
+//! The [`AliasRegistry`] given to [`GameDataBaseWatcher::set_aliases`] is
+//! applied to every reloaded database, which also keeps the uid aliases of
+//! the previous one and registers the uids of the games changed since.
+//!
+//! This module is only available with the `watch` feature.
+//!
+//! ## Examples
+//! ```no_run
+//! use libpobsd::db::watch::GameDataBaseWatcher;
+//! use libpobsd::{ParsingMode, SearchType};
+//!
+//! let watcher = GameDataBaseWatcher::new("games.db", ParsingMode::Strict)
+//!        .expect("Could not open the database");
+//! // in a request handler
+//! let db = watcher.get();
+//! let games = db.search_games_by_name("Barrow", &SearchType::NotCaseSensitive);
+//! ```
+use crate::db::snapshot::content_hash;
+use crate::db::AliasRegistry;
+use crate::{GameDataBase, Parser, ParserResult, ParsingMode};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// Default interval between two checks of the database file.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Represents the outcome of a reload of the database file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReloadStatus {
+    /// The content of the database file did not change.
+    Unchanged,
+    /// The database was reparsed without error and swapped in.
+    Reloaded,
+    /// The database was reparsed in relaxed mode and swapped in, the lines
+    /// where errors occurred being given.
+    ReloadedWithError(Vec<usize>),
+    /// The database was reparsed in strict mode but an error occurred: the
+    /// previous database is kept and the lines where errors occurred are given.
+    Rejected(Vec<usize>),
+    /// The database file could not be read: the previous database is kept.
+    Failed(std::io::ErrorKind),
+}
+
+// File metadata used to cheaply detect changes
+type Stamp = (Option<SystemTime>, u64);
+
+struct Shared {
+    path: PathBuf,
+    mode: ParsingMode,
+    db: RwLock<Arc<GameDataBase>>,
+    // aliases applied to every reloaded database
+    aliases: Mutex<AliasRegistry>,
+    // hash of the content the current database was built from
+    hash: Mutex<u64>,
+    last_status: Mutex<Option<ReloadStatus>>,
+    stop: Mutex<bool>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    fn reload(&self) -> ReloadStatus {
+        let status = match fs::read_to_string(&self.path) {
+            Ok(data) => {
+                let hash = content_hash(data.as_bytes());
+                let mut current_hash = self.hash.lock().unwrap();
+                if hash == *current_hash {
+                    ReloadStatus::Unchanged
+                } else {
+                    let (games, status) = match Parser::new(self.mode).load_from_string(&data) {
+                        ParserResult::WithoutError(games) => (Some(games), ReloadStatus::Reloaded),
+                        ParserResult::WithError(games, lines) => match self.mode {
+                            ParsingMode::Strict => (None, ReloadStatus::Rejected(lines)),
+                            _ => (Some(games), ReloadStatus::ReloadedWithError(lines)),
+                        },
+                    };
+                    if let Some(games) = games {
+                        // the database is fully built before being swapped in
+                        let aliases = self.aliases.lock().unwrap().clone();
+                        let mut db = GameDataBase::with_aliases(games, aliases);
+                        db.link_previous_uids(&self.db.read().unwrap());
+                        *self.db.write().unwrap() = Arc::new(db);
+                    }
+                    *current_hash = hash;
+                    status
+                }
+            }
+            Err(e) => ReloadStatus::Failed(e.kind()),
+        };
+        *self.last_status.lock().unwrap() = Some(status.clone());
+        status
+    }
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    fs::metadata(path)
+        .ok()
+        .map(|meta| (meta.modified().ok(), meta.len()))
+}
+
+/// Holds a [`GameDataBase`] reloaded in the background each time the
+/// PlayOnBSD database file it was loaded from changes.
+///
+/// The background thread is stopped when the watcher is dropped.
+pub struct GameDataBaseWatcher {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl GameDataBaseWatcher {
+    /// Loads the database from the given file using the given
+    /// [`ParsingMode`] and starts watching it, checking the file
+    /// every [`DEFAULT_INTERVAL`].
+    pub fn new(path: impl AsRef<Path>, mode: ParsingMode) -> Result<Self, std::io::Error> {
+        Self::with_interval(path, mode, DEFAULT_INTERVAL)
+    }
+    /// Loads the database from the given file using the given
+    /// [`ParsingMode`] and starts watching it, checking the file
+    /// at the given interval.
+    ///
+    /// Like later reloads, the initial load rejects a database with errors
+    /// in strict mode: an [`std::io::ErrorKind::InvalidData`] error giving
+    /// the lines where errors occurred is then returned.
+    pub fn with_interval(
+        path: impl AsRef<Path>,
+        mode: ParsingMode,
+        interval: Duration,
+    ) -> Result<Self, std::io::Error> {
+        let path: PathBuf = path.as_ref().into();
+        let mut last_stamp = stamp(&path);
+        let data = fs::read_to_string(&path)?;
+        let (games, status) = match Parser::new(mode).load_from_string(&data) {
+            ParserResult::WithoutError(games) => (games, ReloadStatus::Reloaded),
+            ParserResult::WithError(_, lines) if mode == ParsingMode::Strict => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Errors in the database at lines: {:?}", lines),
+                ));
+            }
+            ParserResult::WithError(games, lines) => {
+                (games, ReloadStatus::ReloadedWithError(lines))
+            }
+        };
+        let shared = Arc::new(Shared {
+            path,
+            mode,
+            db: RwLock::new(Arc::new(GameDataBase::new(games))),
+            aliases: Mutex::new(AliasRegistry::default()),
+            hash: Mutex::new(content_hash(data.as_bytes())),
+            last_status: Mutex::new(Some(status)),
+            stop: Mutex::new(false),
+            wakeup: Condvar::new(),
+        });
+        let thread_shared = shared.clone();
+        let handle = thread::spawn(move || {
+            let shared = thread_shared;
+            let mut stop = shared.stop.lock().unwrap();
+            while !*stop {
+                stop = shared.wakeup.wait_timeout(stop, interval).unwrap().0;
+                if *stop {
+                    break;
+                }
+                drop(stop);
+                let new_stamp = stamp(&shared.path);
+                if new_stamp != last_stamp {
+                    last_stamp = new_stamp;
+                    shared.reload();
+                }
+                stop = shared.stop.lock().unwrap();
+            }
+        });
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+    /// Returns the current [`GameDataBase`]. The returned database is not
+    /// affected by later reloads.
+    pub fn get(&self) -> Arc<GameDataBase> {
+        self.shared.db.read().unwrap().clone()
+    }
+    /// Reloads the database file immediately, without waiting for the
+    /// background thread to notice the change.
+    pub fn reload(&self) -> ReloadStatus {
+        self.shared.reload()
+    }
+    /// Sets the [`AliasRegistry`] used by the current database and by the
+    /// databases built on the next reloads.
+    pub fn set_aliases(&self, aliases: AliasRegistry) {
+        // prevents a concurrent reload from swapping a database in
+        let _hash = self.shared.hash.lock().unwrap();
+        let mut db = GameDataBase::clone(&self.get());
+        db.set_aliases(aliases.clone());
+        *self.shared.aliases.lock().unwrap() = aliases;
+        *self.shared.db.write().unwrap() = Arc::new(db);
+    }
+    /// Registers a previous uid of a game in the current database, the
+    /// uid alias being kept by the next reloads.
+    pub fn add_uid_alias(&self, previous: u32, current: u32) {
+        // prevents a concurrent reload from swapping a database in
+        let _hash = self.shared.hash.lock().unwrap();
+        let mut db = GameDataBase::clone(&self.get());
+        db.add_uid_alias(previous, current);
+        *self.shared.db.write().unwrap() = Arc::new(db);
+    }
+    /// Returns the outcome of the last load or reload of the database file,
+    /// including the lines where errors occurred if any.
+    pub fn last_status(&self) -> Option<ReloadStatus> {
+        self.shared.last_status.lock().unwrap().clone()
+    }
+    /// Returns the path of the watched database file.
+    pub fn path(&self) -> &Path {
+        &self.shared.path
+    }
+}
+
+impl Drop for GameDataBaseWatcher {
+    fn drop(&mut self) {
+        *self.shared.stop.lock().unwrap() = true;
+        self.shared.wakeup.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+    use crate::SearchType;
+
+    const GAME1: &str = "Game\tGame 1\nCover\nEngine\nSetup\nRuntime\nStore\nHints\nGenre\nTags\nYear\nDev\nPub\nVersion\nStatus\nAdded\t2022-01-01\nUpdated\t2022-01-01\nIgdbId\n";
+    const GAME2: &str = "Game\tGame 2\nCover\nEngine\nSetup\nRuntime\nStore\nHints\nGenre\nTags\nYear\nDev\nPub\nVersion\nStatus\nAdded\t2022-01-01\nUpdated\t2022-01-01\nIgdbId\n";
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("libpobsd-watch-{}-{}", std::process::id(), name));
+        path
+    }
+    #[test]
+    fn test_reload() {
+        let path = tmp_path("reload.db");
+        fs::write(&path, GAME1).unwrap();
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Strict).unwrap();
+        let old_db = watcher.get();
+        assert_eq!(old_db.get_all_games().count, 1);
+        assert_eq!(watcher.reload(), ReloadStatus::Unchanged);
+        fs::write(&path, format!("{}{}", GAME1, GAME2)).unwrap();
+        assert_eq!(watcher.reload(), ReloadStatus::Reloaded);
+        assert_eq!(watcher.get().get_all_games().count, 2);
+        // previously obtained databases are not modified
+        assert_eq!(old_db.get_all_games().count, 1);
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_reload_with_aliases() {
+        let path = tmp_path("aliases.db");
+        fs::write(&path, GAME1.replace("Engine\n", "Engine\tgodot\n")).unwrap();
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Strict).unwrap();
+        let mut aliases = AliasRegistry::new();
+        aliases.add_engines_alias("Godot Engine", "godot");
+        watcher.set_aliases(aliases.clone());
+        let count = |db: &GameDataBase| {
+            db.match_games_by_engine("Godot Engine", &SearchType::CaseSensitive)
+                .count
+        };
+        assert_eq!(count(&watcher.get()), 1);
+        let game2 = GAME2.replace("Engine\n", "Engine\tGodot\n");
+        fs::write(
+            &path,
+            format!("{}{}", GAME1.replace("Engine\n", "Engine\tgodot\n"), game2),
+        )
+        .unwrap();
+        assert_eq!(watcher.reload(), ReloadStatus::Reloaded);
+        let db = watcher.get();
+        assert_eq!(db.get_aliases(), &aliases);
+        assert_eq!(count(&db), 2);
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_reload_keeps_uid_aliases() {
+        let path = tmp_path("uid-aliases.db");
+        fs::write(&path, GAME1).unwrap();
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Strict).unwrap();
+        let uid = watcher.get().get_all_games().into_inner()[0].uid;
+        watcher.add_uid_alias(42, uid);
+        // the uid of the game changes
+        let game1 = GAME1.replace("Added\t2022-01-01", "Added\t2022-01-02");
+        fs::write(&path, &game1).unwrap();
+        assert_eq!(watcher.reload(), ReloadStatus::Reloaded);
+        let new_uid = watcher.get().get_all_games().into_inner()[0].uid;
+        assert_ne!(uid, new_uid);
+        fs::write(&path, format!("{}{}", game1, GAME2)).unwrap();
+        assert_eq!(watcher.reload(), ReloadStatus::Reloaded);
+        let db = watcher.get();
+        assert_eq!(db.get_game_by_id(42).map(|game| game.uid), Some(new_uid));
+        assert_eq!(db.get_game_by_id(uid).map(|game| game.uid), Some(new_uid));
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_reload_with_error_strict_mode() {
+        let path = tmp_path("strict.db");
+        fs::write(&path, GAME1).unwrap();
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Strict).unwrap();
+        fs::write(&path, format!("{}//{}", GAME1, GAME2)).unwrap();
+        assert_eq!(watcher.reload(), ReloadStatus::Rejected(vec![18]));
+        assert_eq!(
+            watcher.last_status(),
+            Some(ReloadStatus::Rejected(vec![18]))
+        );
+        let db = watcher.get();
+        assert!(db
+            .get_game_by_name("Game 1", &SearchType::CaseSensitive)
+            .is_some());
+        assert_eq!(db.get_all_games().count, 1);
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_initial_load_with_error() {
+        let path = tmp_path("initial.db");
+        fs::write(&path, format!("{}//{}", GAME1, GAME2)).unwrap();
+        let err = GameDataBaseWatcher::new(&path, ParsingMode::Strict)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Relaxed).unwrap();
+        assert_eq!(
+            watcher.last_status(),
+            Some(ReloadStatus::ReloadedWithError(vec![18]))
+        );
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_reload_with_error_relaxed_mode() {
+        let path = tmp_path("relaxed.db");
+        fs::write(&path, GAME1).unwrap();
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Relaxed).unwrap();
+        fs::write(&path, format!("{}{}Unknown\n", GAME1, GAME2)).unwrap();
+        assert_eq!(watcher.reload(), ReloadStatus::ReloadedWithError(vec![35]));
+        assert_eq!(watcher.get().get_all_games().count, 2);
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_reload_missing_file() {
+        let path = tmp_path("missing.db");
+        fs::write(&path, GAME1).unwrap();
+        let watcher = GameDataBaseWatcher::new(&path, ParsingMode::Strict).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            watcher.reload(),
+            ReloadStatus::Failed(std::io::ErrorKind::NotFound)
+        );
+        assert_eq!(watcher.get().get_all_games().count, 1);
+    }
+    #[test]
+    fn test_background_reload() {
+        let path = tmp_path("background.db");
+        fs::write(&path, GAME1).unwrap();
+        let watcher = GameDataBaseWatcher::with_interval(
+            &path,
+            ParsingMode::Strict,
+            Duration::from_millis(10),
+        )
+        .unwrap();
+        fs::write(&path, format!("{}{}", GAME1, GAME2)).unwrap();
+        let mut count = 0;
+        for _ in 0..500 {
+            count = watcher.get().get_all_games().count;
+            if count == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(count, 2);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,453 @@
+//! Provides a [`LauncherGenerator`] generating, for the games with a known
+//! runtime, a launcher shell script and a freedesktop `.desktop` entry.
+//!
+//! Each game is expected to be installed in its own directory, by default
+//! named after the game (see [`game_slug`]) inside the games directory
+//! given to the generator, a numeric suffix telling apart the games whose
+//! names give the same slug (see [`LauncherGenerator::add_games`]). The
+//! launcher script:
+//! * checks that the packages required by the setup are installed,
+//! * runs the setup commands the first time the game is launched, only
+//!   the PlayOnBSD helpers (`fnaify` and `hlwrap`) being run, with
+//!   quoted arguments, the other setup steps being written as comments
+//!   for the user to run them by hand,
+//! * runs the runtime of the game from the game directory.
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::launcher::LauncherGenerator;
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let mut generator = LauncherGenerator::new("/home/user/games", "/home/user/.local/share/applications");
+//! generator.set_covers_dir("/home/user/games/covers");
+//! let files = generator
+//!        .generate_all(db.get_all_games())
+//!        .expect("Could not write the launchers");
+//! ```
+use crate::models::setup::KNOWN_COMMANDS;
+pub use crate::slug::game_slug;
+use crate::slug::game_slugs;
+use crate::{Game, SetupStep};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+/// Name of the file created in the game directory once the setup
+/// commands have been run.
+const SETUP_MARKER: &str = ".libpobsd-setup-done";
+
+// Quotes a value for the shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Quotes an argument of the Exec key of a desktop entry
+fn desktop_quote(value: &str) -> String {
+    let escaped: String = value
+        .chars()
+        .flat_map(|c| match c {
+            '"' | '`' | '$' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect();
+    format!("\"{}\"", escaped.replace('%', "%%"))
+}
+
+// Removes the characters breaking a desktop entry value
+fn desktop_value(value: &str) -> String {
+    value.replace(['\n', '\r'], " ")
+}
+
+/// Generates launcher scripts and `.desktop` entries for the games.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LauncherGenerator {
+    games_dir: PathBuf,
+    output_dir: PathBuf,
+    covers_dir: Option<PathBuf>,
+    game_dirs: HashMap<u32, PathBuf>,
+    slugs: HashMap<u32, String>,
+}
+
+impl LauncherGenerator {
+    /// Creates a [`LauncherGenerator`] for games installed in the given
+    /// games directory, the files being written into the output directory.
+    pub fn new(games_dir: impl AsRef<Path>, output_dir: impl AsRef<Path>) -> Self {
+        Self {
+            games_dir: games_dir.as_ref().into(),
+            output_dir: output_dir.as_ref().into(),
+            covers_dir: None,
+            game_dirs: HashMap::new(),
+            slugs: HashMap::new(),
+        }
+    }
+    /// Sets the directory holding the covers of the games, used for
+    /// the icons of the `.desktop` entries. Without it, the cover is
+    /// used as given in the database.
+    pub fn set_covers_dir(&mut self, covers_dir: impl AsRef<Path>) -> &mut Self {
+        self.covers_dir = Some(covers_dir.as_ref().into());
+        self
+    }
+    /// Sets the directory in which the game with the given uid is
+    /// installed, overriding the default one.
+    pub fn set_game_dir(&mut self, game_id: u32, game_dir: impl AsRef<Path>) -> &mut Self {
+        self.game_dirs.insert(game_id, game_dir.as_ref().into());
+        self
+    }
+    /// Registers the games for which launchers are generated, so that
+    /// the games whose names give the same slug get distinct directories
+    /// and files, a numeric suffix being added to the slug (e.g.
+    /// "my-game-2"). The games already registered keep their slug.
+    pub fn add_games<'a>(&mut self, games: impl IntoIterator<Item = &'a Game>) -> &mut Self {
+        let games: Vec<&Game> = games
+            .into_iter()
+            .filter(|game| !self.slugs.contains_key(&game.uid))
+            .collect();
+        let mut used: HashSet<String> = self.slugs.values().cloned().collect();
+        let slugs = game_slugs(games, &mut used);
+        self.slugs.extend(slugs);
+        self
+    }
+    // Returns the slug of the game, unique among the registered games
+    fn slug(&self, game: &Game) -> String {
+        match self.slugs.get(&game.uid) {
+            Some(slug) => slug.clone(),
+            None => game_slug(game),
+        }
+    }
+    /// Returns the directory in which the game is installed.
+    pub fn game_dir(&self, game: &Game) -> PathBuf {
+        match self.game_dirs.get(&game.uid) {
+            Some(game_dir) => game_dir.clone(),
+            None => self.games_dir.join(self.slug(game)),
+        }
+    }
+    /// Returns the path of the launcher script of the game.
+    pub fn script_path(&self, game: &Game) -> PathBuf {
+        self.output_dir.join(format!("{}.sh", self.slug(game)))
+    }
+    /// Returns the path of the `.desktop` entry of the game.
+    pub fn desktop_path(&self, game: &Game) -> PathBuf {
+        self.output_dir.join(format!("{}.desktop", self.slug(game)))
+    }
+    /// Returns the content of the launcher script of the game,
+    /// None if its runtime is not known.
+    pub fn script(&self, game: &Game) -> Option<String> {
+        let runtime = game.runtime.as_ref()?.trim();
+        if runtime.is_empty() {
+            return None;
+        }
+        let game_dir = self.game_dir(game);
+        let game_dir = game_dir.to_string_lossy();
+        let mut script = format!(
+            "#!/bin/sh\n# Launcher for {} generated by libpobsd\nset -e\n",
+            game.name.replace('\n', " ")
+        );
+        let steps: &[SetupStep] = match &game.setup {
+            Some(setup) => {
+                script.push_str(&format!("# Setup: {}\n", setup.as_str().replace('\n', " ")));
+                setup.steps()
+            }
+            None => &[],
+        };
+        for step in steps {
+            if let SetupStep::Package(package) = step {
+                script.push_str(&format!(
+                    "if ! pkg_info -q -e {} >/dev/null; then\n\techo \"Missing package: {}, install it with pkg_add\" >&2\n\texit 1\nfi\n",
+                    shell_quote(&format!("{}-*", package)),
+                    package.replace(['"', '`', '$', '\\'], "")
+                ));
+            }
+        }
+        // the steps coming from the database are never run as shell code:
+        // only the helpers are run, their arguments being quoted
+        let mut commands: Vec<String> = Vec::new();
+        for step in steps {
+            match step.command_name() {
+                Some(name) if KNOWN_COMMANDS.contains(&name) => {
+                    let args = step
+                        .to_string()
+                        .split_whitespace()
+                        .skip_while(|word| *word != name)
+                        .skip(1)
+                        .map(shell_quote)
+                        .collect::<Vec<String>>();
+                    commands.push(
+                        std::iter::once(name.to_string())
+                            .chain(args)
+                            .collect::<Vec<String>>()
+                            .join(" "),
+                    );
+                }
+                _ => {
+                    if !matches!(step, SetupStep::Package(_)) {
+                        script.push_str(&format!(
+                            "# To run by hand: {}\n",
+                            step.to_string().replace('\n', " ")
+                        ));
+                    }
+                }
+            }
+        }
+        script.push_str(&format!("cd {}\n", shell_quote(&game_dir)));
+        if !commands.is_empty() {
+            script.push_str(&format!("if [ ! -e {} ]; then\n", SETUP_MARKER));
+            for command in commands {
+                script.push_str(&format!("\t{}\n", command));
+            }
+            script.push_str(&format!("\ttouch {}\nfi\n", SETUP_MARKER));
+        }
+        script.push_str(&format!("exec {} \"$@\"\n", shell_quote(runtime)));
+        Some(script)
+    }
+    /// Returns the content of the `.desktop` entry of the game,
+    /// None if its runtime is not known.
+    pub fn desktop_entry(&self, game: &Game) -> Option<String> {
+        game.runtime.as_ref().filter(|r| !r.trim().is_empty())?;
+        let mut entry = String::from("[Desktop Entry]\nType=Application\n");
+        entry.push_str(&format!("Name={}\n", desktop_value(&game.name)));
+        entry.push_str(&format!(
+            "Exec={}\n",
+            desktop_quote(&self.script_path(game).to_string_lossy())
+        ));
+        entry.push_str(&format!(
+            "Path={}\n",
+            desktop_value(&self.game_dir(game).to_string_lossy())
+        ));
+        if let Some(cover) = &game.cover {
+            let icon = match &self.covers_dir {
+                Some(covers_dir) => covers_dir.join(cover),
+                None => PathBuf::from(cover),
+            };
+            entry.push_str(&format!(
+                "Icon={}\n",
+                desktop_value(&icon.to_string_lossy())
+            ));
+        }
+        if let Some(genres) = &game.genres {
+            entry.push_str(&format!("Keywords={};\n", desktop_value(&genres.join(";"))));
+        }
+        entry.push_str("Categories=Game;\nTerminal=false\n");
+        Some(entry)
+    }
+    /// Writes the launcher script (made executable) and the `.desktop`
+    /// entry of the game into the output directory, returning their
+    /// paths, or None if the runtime of the game is not known.
+    pub fn generate(&self, game: &Game) -> Result<Option<(PathBuf, PathBuf)>, Error> {
+        let (script, entry) = match (self.script(game), self.desktop_entry(game)) {
+            (Some(script), Some(entry)) => (script, entry),
+            _ => return Ok(None),
+        };
+        fs::create_dir_all(&self.output_dir)?;
+        let script_path = self.script_path(game);
+        fs::write(&script_path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))?;
+        }
+        let desktop_path = self.desktop_path(game);
+        fs::write(&desktop_path, entry)?;
+        Ok(Some((script_path, desktop_path)))
+    }
+    /// Writes the launcher scripts and the `.desktop` entries of the given
+    /// games (see [`LauncherGenerator::generate`]), the games without known
+    /// runtime being skipped. The games are registered first (see
+    /// [`LauncherGenerator::add_games`]) so that they do not overwrite
+    /// each other's files.
+    pub fn generate_all<'a>(
+        &self,
+        games: impl IntoIterator<Item = &'a Game>,
+    ) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let games: Vec<&Game> = games.into_iter().collect();
+        let mut generator = self.clone();
+        generator.add_games(games.iter().copied());
+        let mut files = Vec::new();
+        for game in games {
+            if let Some(paths) = generator.generate(game)? {
+                files.push(paths);
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod launcher_tests {
+    use super::*;
+
+    fn create_game() -> Game {
+        let mut game = Game::new();
+        game.uid = 1;
+        game.name = "The Adventures of Shuggy".into();
+        game.cover = Some("shuggy.png".into());
+        game.runtime = Some("fnaify".into());
+        game.setup = Some("lwjgl; fnaify -y && Copy the Content directory".into());
+        game.genres = Some(vec!["Puzzle".into(), "Platformer".into()]);
+        game
+    }
+    #[test]
+    fn test_game_slug() {
+        let mut game = create_game();
+        assert_eq!(game_slug(&game), "the-adventures-of-shuggy");
+        game.name = "AaaaaAAaaaAAAaaAAAAaAAAAA!!! for the Awesome".into();
+        assert_eq!(
+            game_slug(&game),
+            "aaaaaaaaaaaaaaaaaaaaaaaaa-for-the-awesome"
+        );
+    }
+    #[test]
+    fn test_slugs() {
+        let mut game1 = create_game();
+        game1.name = "Game!".into();
+        let mut game2 = create_game();
+        game2.uid = 2;
+        game2.name = "game".into();
+        let mut game3 = create_game();
+        game3.uid = 3;
+        game3.name = "!!!".into();
+        assert_eq!(game_slug(&game3), "item");
+        let mut generator = LauncherGenerator::new("/games", "/out");
+        generator.add_games([&game1, &game2, &game3]);
+        assert_eq!(generator.script_path(&game2), PathBuf::from("/out/game.sh"));
+        assert_eq!(generator.game_dir(&game1), PathBuf::from("/games/game-2"));
+        assert_eq!(
+            generator.desktop_path(&game3),
+            PathBuf::from("/out/item.desktop")
+        );
+        // registered games keep their slug
+        let mut game4 = create_game();
+        game4.uid = 4;
+        game4.name = "a game".into();
+        generator.add_games([&game4, &game1]);
+        assert_eq!(generator.game_dir(&game1), PathBuf::from("/games/game-2"));
+    }
+    #[test]
+    fn test_generate_all_same_slug() {
+        let mut output_dir = std::env::temp_dir();
+        output_dir.push(format!("libpobsd-launcher-slugs-{}", std::process::id()));
+        let game1 = create_game();
+        let mut game2 = create_game();
+        game2.uid = 2;
+        game2.name = "The adventures of Shuggy!".into();
+        let generator = LauncherGenerator::new("/games", &output_dir);
+        let files = generator.generate_all([&game1, &game2]).unwrap();
+        fs::remove_dir_all(output_dir).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_ne!(files[0].0, files[1].0);
+        assert_ne!(files[0].1, files[1].1);
+    }
+    #[test]
+    fn test_quote() {
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(desktop_quote("/a b/$x"), "\"/a b/\\$x\"");
+    }
+    #[test]
+    fn test_script() {
+        let generator = LauncherGenerator::new("/games", "/out");
+        let script = generator.script(&create_game()).unwrap();
+        assert_eq!(
+            script,
+            "#!/bin/sh
+# Launcher for The Adventures of Shuggy generated by libpobsd
+set -e
+# Setup: lwjgl; fnaify -y && Copy the Content directory
+if ! pkg_info -q -e 'lwjgl-*' >/dev/null; then
+\techo \"Missing package: lwjgl, install it with pkg_add\" >&2
+\texit 1
+fi
+# To run by hand: Copy the Content directory
+cd '/games/the-adventures-of-shuggy'
+if [ ! -e .libpobsd-setup-done ]; then
+\tfnaify '-y'
+\ttouch .libpobsd-setup-done
+fi
+exec 'fnaify' \"$@\"
+"
+        );
+    }
+    #[test]
+    fn test_script_does_not_run_setup_text() {
+        let mut game = create_game();
+        game.runtime = Some("true".into());
+        game.setup = Some("doas fnaify $(touch pwned-1); touch pwned-2 && rm -rf pwned-dir".into());
+        let generator = LauncherGenerator::new("/games", "/out");
+        let script = generator.script(&game).unwrap();
+        assert!(script.contains("\tfnaify '$(touch' 'pwned-1)'\n"));
+        assert!(script.contains("# To run by hand: touch pwned-2\n"));
+        assert!(script.contains("# To run by hand: rm -rf pwned-dir\n"));
+        #[cfg(unix)]
+        {
+            let mut game_dir = std::env::temp_dir();
+            game_dir.push(format!("libpobsd-launcher-payload-{}", std::process::id()));
+            fs::create_dir_all(&game_dir).unwrap();
+            let mut generator = LauncherGenerator::new(&game_dir, "/out");
+            generator.set_game_dir(1, &game_dir);
+            let script_path = game_dir.join("launcher.sh");
+            fs::write(&script_path, generator.script(&game).unwrap()).unwrap();
+            // fnaify is not expected to be installed, the script failing
+            let _ = std::process::Command::new("sh")
+                .arg(&script_path)
+                .env("PATH", "/bin:/usr/bin")
+                .output()
+                .unwrap();
+            assert!(!game_dir.join("pwned-1").exists());
+            assert!(!game_dir.join("pwned-2").exists());
+            fs::remove_dir_all(game_dir).unwrap();
+        }
+    }
+    #[test]
+    fn test_desktop_entry() {
+        let mut generator = LauncherGenerator::new("/games", "/out");
+        generator
+            .set_covers_dir("/covers")
+            .set_game_dir(1, "/opt/shuggy");
+        let entry = generator.desktop_entry(&create_game()).unwrap();
+        assert_eq!(
+            entry,
+            "[Desktop Entry]
+Type=Application
+Name=The Adventures of Shuggy
+Exec=\"/out/the-adventures-of-shuggy.sh\"
+Path=/opt/shuggy
+Icon=/covers/shuggy.png
+Keywords=Puzzle;Platformer;
+Categories=Game;
+Terminal=false
+"
+        );
+    }
+    #[test]
+    fn test_without_runtime() {
+        let mut game = create_game();
+        game.runtime = None;
+        let generator = LauncherGenerator::new("/games", "/out");
+        assert!(generator.script(&game).is_none());
+        assert!(generator.desktop_entry(&game).is_none());
+        assert_eq!(generator.generate(&game).unwrap(), None);
+    }
+    #[test]
+    fn test_generate() {
+        let mut output_dir = std::env::temp_dir();
+        output_dir.push(format!("libpobsd-launcher-{}", std::process::id()));
+        let generator = LauncherGenerator::new("/games", &output_dir);
+        let (script, desktop) = generator.generate(&create_game()).unwrap().unwrap();
+        assert!(fs::read_to_string(&script)
+            .unwrap()
+            .starts_with("#!/bin/sh"));
+        assert!(fs::read_to_string(&desktop)
+            .unwrap()
+            .starts_with("[Desktop Entry]"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&script).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        fs::remove_dir_all(output_dir).unwrap();
+    }
+}
@@ -115,9 +115,17 @@
 //! More examples are available in each module documentation.
 
 pub mod db;
+pub mod launcher;
+#[cfg(feature = "lsp")]
+pub mod lsp;
 #[allow(clippy::tabs_in_doc_comments)]
 pub mod models;
 pub mod parsing;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "site")]
+pub mod site;
+pub mod slug;
 
 pub use crate::db::game_filer::GameFilter;
 pub use crate::db::GameDataBase;
@@ -125,11 +133,19 @@ pub use crate::db::Item;
 pub use crate::db::QueryResult;
 pub use crate::db::SearchType;
 pub use crate::models::Game;
+pub use crate::models::GameDate;
 pub use crate::models::GameStatus;
+pub use crate::models::Setup;
+pub use crate::models::SetupStep;
 pub use crate::models::Status;
 pub use crate::models::Store;
 pub use crate::models::StoreLink;
 pub use crate::models::StoreLinks;
+pub use crate::parsing::GameSpan;
 pub use crate::parsing::Parser;
+pub use crate::parsing::ParserReport;
 pub use crate::parsing::ParserResult;
 pub use crate::parsing::ParsingMode;
+pub use crate::parsing::QuarantinedRecord;
+pub use crate::parsing::SchemaVersion;
+pub use crate::parsing::UidCollision;
@@ -0,0 +1,705 @@
+//! Provides a [`Document`] analysing the content of a PlayOnBSD database
+//! being edited: diagnostics, completions, hover information and symbols.
+//!
+//! The positions are given as in the Language Server Protocol: lines
+//! starting at 0 and characters counted in UTF-16 code units.
+use crate::models::field::Field;
+use crate::models::split_line::split_line;
+use crate::{GameDataBase, GameStatus, Parser, Status, Store, StoreLink};
+
+use chrono::NaiveDate;
+
+/// Keys of the fields of a game record, in the order they are expected.
+pub const FIELD_KEYS: [&str; 18] = [
+    "Game", "Cover", "Engine", "Setup", "Runtime", "Store", "Hints", "Genre", "Tags", "Year",
+    "Dev", "Pub", "Version", "Status", "Added", "Updated", "IgdbId", "Id",
+];
+
+// Fields that can be omitted from a game record
+const OPTIONAL_KEYS: [&str; 2] = ["IgdbId", "Id"];
+
+// Description of each field
+const FIELD_DETAILS: [&str; 18] = [
+    "Name of the game (starts a new record)",
+    "Cover of the game",
+    "Engine used by the game",
+    "Step(s) to setup the game",
+    "Executable running the game",
+    "Store urls, separated by spaces",
+    "Hints about the game",
+    "Genres, separated by commas",
+    "Tags, separated by commas",
+    "Release year",
+    "Developers, separated by commas",
+    "Publishers, separated by commas",
+    "Version of the game tested",
+    "Status level (0 to 6) followed by a comment",
+    "Date the game was added (YYYY-MM-DD)",
+    "Date the game was last updated (YYYY-MM-DD)",
+    "Id of the game in the IGDB database",
+    "Explicit uid of the game, kept when the name or the Added date change",
+];
+
+const STATUS_LEVELS: [Status; 7] = [
+    Status::DoesNotRun,
+    Status::Launches,
+    Status::MajorBugs,
+    Status::MediumImpact,
+    Status::MinorBugs,
+    Status::Completable,
+    Status::Perfect,
+];
+
+/// Returns a description of the status level.
+pub fn status_description(status: &Status) -> &'static str {
+    match status {
+        Status::Unknown => "Status not provided.",
+        Status::DoesNotRun => "Doesn't run.",
+        Status::Launches => "Game launches (not enough information to comment meaningfully on status beyond launching the game).",
+        Status::MajorBugs => "Major bugs: potentially game-breaking, making finishing the game impossible or a chore; noticeably degrading the enjoyment compared to running the game on other platforms.",
+        Status::MediumImpact => "Medium-impact bugs: noticeable, but not game-breaking.",
+        Status::MinorBugs => "Minor bugs: barely noticeable, or not relevant to core game.",
+        Status::Completable => "Completable: game can be played through until the credits roll, without major bugs; doesn't (necessarily) include optional side content, DLC, optional multiplayer, achievements etc.",
+        Status::Perfect => "100%: the complete game including optional content like DLC, side quests, multiplayer can be enjoyed.",
+    }
+}
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The line is invalid.
+    Error,
+    /// The line is likely to be wrong.
+    Warning,
+}
+
+/// Range of characters in a line of the document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Range {
+    /// Line of the range (starting at 0).
+    pub line: usize,
+    /// First character of the range (in UTF-16 code units).
+    pub start: usize,
+    /// Character after the end of the range (in UTF-16 code units).
+    pub end: usize,
+}
+
+/// Problem found in the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Part of the document concerned.
+    pub range: Range,
+    /// Severity of the problem.
+    pub severity: Severity,
+    /// Description of the problem.
+    pub message: String,
+}
+
+/// Completion proposed at a position of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Text inserted.
+    pub label: String,
+    /// Additional information about the completion.
+    pub detail: Option<String>,
+}
+
+/// Information displayed when hovering a part of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    /// Part of the document concerned.
+    pub range: Range,
+    /// Information as markdown.
+    pub contents: String,
+}
+
+/// Game record of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSymbol {
+    /// Name of the game.
+    pub name: String,
+    /// First line of the record (the `Game` line).
+    pub start_line: usize,
+    /// Last line of the record.
+    pub end_line: usize,
+    /// Range of the name of the game.
+    pub selection: Range,
+}
+
+// Number of UTF-16 code units of a string
+fn utf16_len(value: &str) -> usize {
+    value.chars().map(char::len_utf16).sum()
+}
+
+// Byte offset in the line corresponding to the UTF-16 position
+fn byte_offset(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character {
+            return offset;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+// Range of the value of a line (after the tab)
+fn value_range(n: usize, line: &str, key: &str) -> Range {
+    Range {
+        line: n,
+        start: utf16_len(key) + 1,
+        end: utf16_len(line),
+    }
+}
+
+fn key_range(n: usize, key: &str) -> Range {
+    Range {
+        line: n,
+        start: 0,
+        end: utf16_len(key),
+    }
+}
+
+// State of the game record being checked
+struct Record {
+    line: usize,
+    seen: [bool; FIELD_KEYS.len()],
+    last: usize,
+    added: Option<NaiveDate>,
+}
+
+impl Record {
+    fn new(line: usize) -> Self {
+        let mut seen = [false; FIELD_KEYS.len()];
+        seen[0] = true;
+        Self {
+            line,
+            seen,
+            last: 0,
+            added: None,
+        }
+    }
+    fn missing(&self) -> Option<Diagnostic> {
+        let mut missing: Vec<&str> = Vec::new();
+        for (key, seen) in FIELD_KEYS.iter().zip(self.seen) {
+            if !seen && !OPTIONAL_KEYS.contains(key) {
+                missing.push(key);
+            }
+        }
+        if missing.is_empty() {
+            return None;
+        }
+        Some(Diagnostic {
+            range: key_range(self.line, "Game"),
+            severity: Severity::Warning,
+            message: format!("missing fields: {}", missing.join(", ")),
+        })
+    }
+}
+
+/// Document being edited with the [`GameDataBase`] built from it.
+#[derive(Clone)]
+pub struct Document {
+    text: String,
+    db: GameDataBase,
+}
+
+impl Document {
+    /// Creates a [`Document`] from its content.
+    pub fn new(text: &str) -> Self {
+        let games = Parser::default().load_from_string(text).into();
+        Self {
+            text: text.into(),
+            db: GameDataBase::new(games),
+        }
+    }
+    /// Returns the content of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Returns the [`GameDataBase`] built from the document.
+    pub fn get_db(&self) -> &GameDataBase {
+        &self.db
+    }
+    /// Returns the problems found in the document: unknown fields, fields
+    /// out of order, duplicated or missing, invalid dates, status, ids
+    /// and store urls.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let lines: Vec<&str> = self.text.lines().collect();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let mut record: Option<Record> = None;
+        let mut push = |range: Range, severity: Severity, message: String| {
+            diagnostics.push(Diagnostic {
+                range,
+                severity,
+                message,
+            })
+        };
+        for (n, line) in lines.iter().enumerate() {
+            let (key, value) = split_line(line);
+            let key = match key {
+                Some(key) => key,
+                None => {
+                    push(
+                        Range {
+                            line: n,
+                            start: 0,
+                            end: 0,
+                        },
+                        Severity::Warning,
+                        "unexpected empty line".into(),
+                    );
+                    continue;
+                }
+            };
+            let index = match FIELD_KEYS.iter().position(|k| *k == key) {
+                Some(index) => index,
+                None => {
+                    let trimmed = key.trim_matches(|c: char| !c.is_alphanumeric());
+                    let message = match FIELD_KEYS.iter().find(|k| k.eq_ignore_ascii_case(trimmed))
+                    {
+                        Some(k) => format!("unknown field `{}`, did you mean `{}`?", key, k),
+                        None => format!("unknown field `{}`", key),
+                    };
+                    push(key_range(n, key), Severity::Error, message);
+                    continue;
+                }
+            };
+            if index == 0 {
+                if let Some(diagnostic) = record.take().and_then(|r| r.missing()) {
+                    push(diagnostic.range, diagnostic.severity, diagnostic.message);
+                }
+                record = Some(Record::new(n));
+                if value.is_none() {
+                    push(
+                        key_range(n, key),
+                        Severity::Error,
+                        "game without name".into(),
+                    );
+                }
+                continue;
+            }
+            let current = match record.as_mut() {
+                Some(current) => current,
+                None => {
+                    push(
+                        key_range(n, key),
+                        Severity::Error,
+                        format!("field `{}` outside of a game record", key),
+                    );
+                    continue;
+                }
+            };
+            if current.seen[index] {
+                push(
+                    key_range(n, key),
+                    Severity::Warning,
+                    format!("duplicated field `{}`", key),
+                );
+            } else if index < current.last {
+                push(
+                    key_range(n, key),
+                    Severity::Warning,
+                    format!(
+                        "field `{}` is out of order, it should come before `{}`",
+                        key, FIELD_KEYS[current.last]
+                    ),
+                );
+            }
+            current.seen[index] = true;
+            current.last = current.last.max(index);
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    if key == "Added" || key == "Updated" {
+                        push(
+                            key_range(n, key),
+                            Severity::Error,
+                            format!("missing date for `{}`", key),
+                        );
+                    }
+                    continue;
+                }
+            };
+            let range = value_range(n, line, key);
+            match key {
+                "Added" | "Updated" => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                    Ok(date) => {
+                        if key == "Added" {
+                            current.added = Some(date);
+                        } else if current.added.map_or(false, |added| date < added) {
+                            push(
+                                range,
+                                Severity::Warning,
+                                "the game is updated before being added".into(),
+                            );
+                        }
+                    }
+                    Err(_) => push(
+                        range,
+                        Severity::Error,
+                        format!("invalid date `{}`, expected YYYY-MM-DD", value),
+                    ),
+                },
+                "Status" if GameStatus::from_line(value).status == Status::Unknown => push(
+                    range,
+                    Severity::Warning,
+                    "the status should start with a level from 0 (doesn't run) to 6 (100%)".into(),
+                ),
+                "IgdbId" if value.parse::<usize>().is_err() => push(
+                    range,
+                    Severity::Error,
+                    format!("invalid IGDB id `{}`", value),
+                ),
+                "Id" if value.parse::<u32>().is_err() => {
+                    push(range, Severity::Error, format!("invalid id `{}`", value))
+                }
+                "Store" => {
+                    for url in value.split(' ').filter(|url| !url.is_empty()) {
+                        if !url.starts_with("http://") && !url.starts_with("https://") {
+                            push(
+                                range,
+                                Severity::Warning,
+                                format!("invalid store url `{}`", url),
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(diagnostic) = record.and_then(|r| r.missing()) {
+            diagnostics.push(diagnostic);
+        }
+        diagnostics
+    }
+    /// Returns the completions at the given position: the field keys
+    /// before the tab, the values already used in the database for the
+    /// engine, runtime, genres, tags, year, developers and publishers
+    /// and the status levels after it.
+    pub fn completions(&self, line: usize, character: usize) -> Vec<Completion> {
+        let line = match self.text.lines().nth(line) {
+            Some(line) => line,
+            None => return vec![],
+        };
+        let before = &line[..byte_offset(line, character)];
+        let (key, value) = match before.split_once('\t') {
+            Some((key, value)) => (key, value),
+            None => {
+                return FIELD_KEYS
+                    .iter()
+                    .zip(FIELD_DETAILS)
+                    .filter(|(key, _)| key.starts_with(before))
+                    .map(|(key, detail)| Completion {
+                        label: key.to_string(),
+                        detail: Some(detail.into()),
+                    })
+                    .collect()
+            }
+        };
+        let items = match key {
+            "Engine" => self.db.get_all_engines(),
+            "Runtime" => self.db.get_all_runtimes(),
+            "Genre" => self.db.get_all_genres(),
+            "Tags" => self.db.get_all_tags(),
+            "Year" => self.db.get_all_years(),
+            "Dev" => self.db.get_all_devs(),
+            "Pub" => self.db.get_all_publis(),
+            "Status" => {
+                return STATUS_LEVELS
+                    .iter()
+                    .enumerate()
+                    .map(|(level, status)| Completion {
+                        label: level.to_string(),
+                        detail: Some(status_description(status).into()),
+                    })
+                    .filter(|completion| completion.label.starts_with(value.trim()))
+                    .collect()
+            }
+            _ => return vec![],
+        };
+        let prefix = match key {
+            "Genre" | "Tags" | "Dev" | "Pub" => value.rsplit(',').next().unwrap_or(value),
+            _ => value,
+        }
+        .trim_start()
+        .to_lowercase();
+        items
+            .into_iter()
+            .filter(|item| item.to_lowercase().starts_with(&prefix))
+            .map(|item| Completion {
+                label: item.clone(),
+                detail: Some(format!(
+                    "{} (used by {} games)",
+                    key,
+                    self.count_games(key, item)
+                )),
+            })
+            .collect()
+    }
+    fn count_games(&self, key: &str, item: &str) -> usize {
+        let st = crate::SearchType::NotCaseSensitive;
+        match key {
+            "Engine" => self.db.match_games_by_engine(item, &st).count,
+            "Runtime" => self.db.match_games_by_runtime(item, &st).count,
+            "Genre" => self.db.match_games_by_genre(item, &st).count,
+            "Tags" => self.db.match_games_by_tag(item, &st).count,
+            "Year" => self.db.match_games_by_year(item, &st).count,
+            "Dev" => self.db.match_games_by_dev(item, &st).count,
+            "Pub" => self.db.match_games_by_publi(item, &st).count,
+            _ => 0,
+        }
+    }
+    /// Returns information about the field key, the store url (e.g. the
+    /// Steam app id) or the status level at the given position.
+    pub fn hover(&self, line: usize, character: usize) -> Option<Hover> {
+        let n = line;
+        let line = self.text.lines().nth(n)?;
+        let offset = byte_offset(line, character);
+        let (key, value) = match line.split_once('\t') {
+            Some((key, value)) => (key, value),
+            None => (line, ""),
+        };
+        if offset <= key.len() {
+            let index = FIELD_KEYS.iter().position(|k| *k == key)?;
+            return Some(Hover {
+                range: key_range(n, key),
+                contents: format!("**{}**: {}", key, FIELD_DETAILS[index]),
+            });
+        }
+        match Field::from(line) {
+            Field::Status(status) => Some(Hover {
+                range: value_range(n, line, key),
+                contents: match status.status {
+                    Status::Unknown => status_description(&status.status).into(),
+                    _ => format!(
+                        "**Status {}** ({})\n\n{}",
+                        STATUS_LEVELS
+                            .iter()
+                            .position(|s| *s == status.status)
+                            .unwrap_or_default(),
+                        status.status,
+                        status_description(&status.status)
+                    ),
+                },
+            }),
+            Field::Store(_) => {
+                // find the url under the cursor
+                let mut start = key.len() + 1;
+                for url in value.split(' ') {
+                    let end = start + url.len();
+                    if offset >= start && offset <= end && !url.is_empty() {
+                        let store = StoreLink::from(url);
+                        let contents = match (&store.store, store.id) {
+                            (Store::Steam, Some(id)) => format!(
+                                "**Steam** app id {}\n\n[Store page]({}) | [SteamDB](https://steamdb.info/app/{}/)",
+                                id, url, id
+                            ),
+                            (store_name, _) => format!("**{}**\n\n[Store page]({})", store_name, url),
+                        };
+                        return Some(Hover {
+                            range: Range {
+                                line: n,
+                                start: utf16_len(&line[..start]),
+                                end: utf16_len(&line[..end]),
+                            },
+                            contents,
+                        });
+                    }
+                    start = end + 1;
+                }
+                None
+            }
+            Field::IgdbId(Some(id)) => Some(Hover {
+                range: value_range(n, line, key),
+                contents: format!("**IGDB** id {}", id),
+            }),
+            _ => None,
+        }
+    }
+    /// Returns the game records of the document.
+    pub fn symbols(&self) -> Vec<GameSymbol> {
+        let mut symbols: Vec<GameSymbol> = Vec::new();
+        let mut last_line = 0;
+        for (n, line) in self.text.lines().enumerate() {
+            last_line = n;
+            if let Field::Game(name) = Field::from(line) {
+                if let Some(symbol) = symbols.last_mut() {
+                    symbol.end_line = n.saturating_sub(1);
+                }
+                symbols.push(GameSymbol {
+                    name: name.clone().unwrap_or_default(),
+                    start_line: n,
+                    end_line: n,
+                    selection: match name {
+                        Some(_) => value_range(n, line, "Game"),
+                        None => key_range(n, "Game"),
+                    },
+                });
+            }
+        }
+        if let Some(symbol) = symbols.last_mut() {
+            symbol.end_line = last_line;
+        }
+        symbols
+    }
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+
+    const DOCUMENT: &str = "Game\tFirst game
+Cover
+Engine\tgodot
+Setup
+Runtime\tgodot
+Store\thttps://store.steampowered.com/app/1869200/The_Adventures_of_Mr_Hat/ https://hukadan.itch.io/game
+Hints
+Genre\tPuzzle, RPG
+Tags\tindie
+Year\t2022
+Dev\tAX-GAME
+Pub\tFun Quarter
+Version
+Status\t5 runs well
+Added\t2022-05-13
+Updated\t2022-05-13
+Game\tSecond game
+Cover
+Engine\tGodot
+//Runtime\tgodot
+Setup
+Store
+Hints
+Genre\tRPG
+Tags
+Year
+Dev
+Pub
+Version
+Status\truns
+Added\t2022-13-13
+Updated\t2022-05-13
+IgdbId\tabc";
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostics = Document::new(DOCUMENT).diagnostics();
+        let messages: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.range.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (19, "unknown field `//Runtime`, did you mean `Runtime`?"),
+                (
+                    29,
+                    "the status should start with a level from 0 (doesn't run) to 6 (100%)"
+                ),
+                (30, "invalid date `2022-13-13`, expected YYYY-MM-DD"),
+                (32, "invalid IGDB id `abc`"),
+                (16, "missing fields: Runtime"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].range,
+            Range {
+                line: 19,
+                start: 0,
+                end: 9
+            }
+        );
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+    #[test]
+    fn test_diagnostics_order() {
+        let document = Document::new(
+            "Cover\nGame\tA\nEngine\nCover\nEngine\n\nAdded\t2022-01-02\nUpdated\t2022-01-01",
+        );
+        let messages: Vec<(usize, String)> = document
+            .diagnostics()
+            .into_iter()
+            .map(|d| (d.range.line, d.message))
+            .collect();
+        assert_eq!(
+            messages[0],
+            (0, "field `Cover` outside of a game record".into())
+        );
+        assert_eq!(
+            messages[1],
+            (
+                3,
+                "field `Cover` is out of order, it should come before `Engine`".into()
+            )
+        );
+        assert_eq!(messages[2], (4, "duplicated field `Engine`".into()));
+        assert_eq!(messages[3], (5, "unexpected empty line".into()));
+        assert_eq!(
+            messages[4],
+            (7, "the game is updated before being added".into())
+        );
+    }
+    #[test]
+    fn test_completions() {
+        let document = Document::new(DOCUMENT);
+        let labels = |line, character| -> Vec<String> {
+            document
+                .completions(line, character)
+                .into_iter()
+                .map(|c| c.label)
+                .collect()
+        };
+        // field keys
+        assert_eq!(labels(1, 1), vec!["Cover"]);
+        // engines
+        assert_eq!(labels(2, 8), vec!["godot"]);
+        // genres after a comma
+        assert_eq!(labels(7, 15), vec!["RPG"]);
+        // status levels
+        assert_eq!(labels(13, 7).len(), 7);
+        assert!(labels(5, 7).is_empty());
+        let completion = &document.completions(2, 7)[0];
+        assert_eq!(
+            completion.detail,
+            Some("Engine (used by 2 games)".to_string())
+        );
+    }
+    #[test]
+    fn test_hover() {
+        let document = Document::new(DOCUMENT);
+        let hover = document.hover(5, 10).unwrap();
+        assert!(hover.contents.starts_with("**Steam** app id 1869200"));
+        assert_eq!(hover.range.start, 6);
+        let hover = document.hover(5, 80).unwrap();
+        assert!(hover.contents.starts_with("**itch.io**"));
+        let hover = document.hover(13, 8).unwrap();
+        assert!(hover.contents.starts_with("**Status 5** (completable)"));
+        let hover = document.hover(0, 2).unwrap();
+        assert!(hover.contents.starts_with("**Game**"));
+        assert!(document.hover(2, 8).is_none());
+    }
+    #[test]
+    fn test_symbols() {
+        let symbols = Document::new(DOCUMENT).symbols();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].name, "First game");
+        assert_eq!((symbols[0].start_line, symbols[0].end_line), (0, 15));
+        assert_eq!((symbols[1].start_line, symbols[1].end_line), (16, 32));
+        assert_eq!(
+            symbols[1].selection,
+            Range {
+                line: 16,
+                start: 5,
+                end: 16
+            }
+        );
+    }
+    #[test]
+    fn test_utf16_positions() {
+        assert_eq!(utf16_len("é😀"), 3);
+        assert_eq!(byte_offset("é😀a", 3), 6);
+        assert_eq!(byte_offset("é", 5), 2);
+    }
+}
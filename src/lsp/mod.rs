@@ -0,0 +1,33 @@
+//! Provides a Language Server Protocol server helping to edit the
+//! PlayOnBSD database (requires the `lsp` feature). The server is run
+//! by the `pobsd-lsp` binary, communicating over the standard input
+//! and output.
+//!
+//! The server provides:
+//! * diagnostics: unknown field keys (e.g. `//Engine`), fields out of order,
+//!   duplicated or missing, invalid dates, status without level, invalid
+//!   IGDB ids and store urls,
+//! * completion of the field keys and of the engines, runtimes, genres,
+//!   tags, years, developers and publishers already used in the database,
+//!   as well as of the status levels,
+//! * hover information for the field keys, the store urls (e.g. Steam app
+//!   ids) and the status levels,
+//! * a document symbol per game record.
+//!
+//! ## Examples
+//! The analysis can also be used without the server.
+//! ```
+//! use libpobsd::lsp::Document;
+//!
+//! let document = Document::new("Game\tMy game\nEngine\tgodot\n//Runtime\tgodot");
+//! let diagnostics = document.diagnostics();
+//! assert_eq!(
+//!     diagnostics[0].message,
+//!     "unknown field `//Runtime`, did you mean `Runtime`?"
+//! );
+//! ```
+pub mod analysis;
+pub mod protocol;
+
+pub use analysis::{Completion, Diagnostic, Document, GameSymbol, Hover, Range, Severity};
+pub use protocol::{run, LanguageServer};
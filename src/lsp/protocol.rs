@@ -0,0 +1,350 @@
+//! Provides a [`LanguageServer`] answering the Language Server Protocol
+//! messages and [`run`], serving it over the given input and output
+//! (the standard input and output for the `pobsd-lsp` binary).
+//!
+//! The documents are fully synchronized: the whole content of the
+//! document is analysed again after each change.
+use crate::lsp::analysis::{Diagnostic, Document, Range, Severity};
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Error, ErrorKind, Write};
+
+// LSP constants
+const TEXT_DOCUMENT_SYNC_FULL: u8 = 1;
+const COMPLETION_KIND_FIELD: u8 = 5;
+const COMPLETION_KIND_VALUE: u8 = 12;
+const SYMBOL_KIND_STRUCT: u8 = 23;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INVALID_REQUEST: i32 = -32600;
+
+fn range_to_json(range: &Range) -> Value {
+    json!({
+        "start": {"line": range.line, "character": range.start},
+        "end": {"line": range.line, "character": range.end},
+    })
+}
+
+fn diagnostic_to_json(diagnostic: &Diagnostic) -> Value {
+    json!({
+        "range": range_to_json(&diagnostic.range),
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "source": "libpobsd",
+        "message": diagnostic.message,
+    })
+}
+
+// Returns the uri, line and character of a text document position request
+fn position(params: &Value) -> Option<(&str, usize, usize)> {
+    let uri = params["textDocument"]["uri"].as_str()?;
+    let line = params["position"]["line"].as_u64()? as usize;
+    let character = params["position"]["character"].as_u64()? as usize;
+    Some((uri, line, character))
+}
+
+/// Language server keeping track of the opened documents.
+#[derive(Default)]
+pub struct LanguageServer {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl LanguageServer {
+    /// Creates a [`LanguageServer`] without opened documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns true once the `exit` notification has been received.
+    pub fn has_exited(&self) -> bool {
+        self.exit
+    }
+    /// Returns the opened document with the given uri.
+    pub fn get_document(&self, uri: &str) -> Option<&Document> {
+        self.documents.get(uri)
+    }
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let diagnostics: Vec<Value> = match self.documents.get(uri) {
+            Some(document) => document
+                .diagnostics()
+                .iter()
+                .map(diagnostic_to_json)
+                .collect(),
+            None => vec![],
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": uri, "diagnostics": diagnostics},
+        })
+    }
+    // Handles a notification, returning the notifications to send back
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "exit" => {
+                self.exit = true;
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.into(), Document::new(text));
+                vec![self.publish_diagnostics(uri)]
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                match changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                {
+                    Some(text) => {
+                        self.documents.insert(uri.into(), Document::new(text));
+                        vec![self.publish_diagnostics(uri)]
+                    }
+                    None => vec![],
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![self.publish_diagnostics(uri)]
+            }
+            _ => vec![],
+        }
+    }
+    // Handles a request, returning its result or an error code and message
+    fn handle_request(&mut self, method: &str, params: &Value) -> Result<Value, (i32, String)> {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "the server is shut down".into()));
+        }
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+                    "completionProvider": {"triggerCharacters": ["\t", ","]},
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": {"name": "pobsd-lsp", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => {
+                let (uri, line, character) =
+                    position(params).ok_or((INVALID_PARAMS, "invalid position".into()))?;
+                let completions = match self.documents.get(uri) {
+                    Some(document) => document.completions(line, character),
+                    None => vec![],
+                };
+                let in_value = self
+                    .documents
+                    .get(uri)
+                    .and_then(|document| document.text().lines().nth(line))
+                    .map_or(false, |text| text.contains('\t'));
+                let items: Vec<Value> = completions
+                    .into_iter()
+                    .map(|completion| {
+                        json!({
+                            "label": completion.label,
+                            "detail": completion.detail,
+                            "kind": if in_value { COMPLETION_KIND_VALUE } else { COMPLETION_KIND_FIELD },
+                        })
+                    })
+                    .collect();
+                Ok(Value::Array(items))
+            }
+            "textDocument/hover" => {
+                let (uri, line, character) =
+                    position(params).ok_or((INVALID_PARAMS, "invalid position".into()))?;
+                let hover = self
+                    .documents
+                    .get(uri)
+                    .and_then(|document| document.hover(line, character));
+                Ok(match hover {
+                    Some(hover) => json!({
+                        "contents": {"kind": "markdown", "value": hover.contents},
+                        "range": range_to_json(&hover.range),
+                    }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/documentSymbol" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let symbols: Vec<Value> = match self.documents.get(uri) {
+                    Some(document) => document
+                        .symbols()
+                        .into_iter()
+                        .map(|symbol| {
+                            json!({
+                                "name": if symbol.name.is_empty() { "(unnamed game)".into() } else { symbol.name },
+                                "kind": SYMBOL_KIND_STRUCT,
+                                "range": {
+                                    "start": {"line": symbol.start_line, "character": 0},
+                                    "end": {"line": symbol.end_line + 1, "character": 0},
+                                },
+                                "selectionRange": range_to_json(&symbol.selection),
+                            })
+                        })
+                        .collect(),
+                    None => vec![],
+                };
+                Ok(Value::Array(symbols))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method: {}", method))),
+        }
+    }
+    /// Handles a JSON-RPC message, returning the messages (response and
+    /// notifications) to send back to the client.
+    pub fn handle_message(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match self.handle_request(method, params) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, error)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": code, "message": error},
+                    }),
+                };
+                vec![response]
+            }
+            None => self.handle_notification(method, params),
+        }
+    }
+}
+
+/// Reads a message (with its `Content-Length` header) from the input,
+/// returning None at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, Error> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let length =
+        length.ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))
+}
+
+/// Writes a message (with its `Content-Length` header) to the output.
+pub fn write_message(output: &mut impl Write, message: &Value) -> Result<(), Error> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+/// Serves a [`LanguageServer`] over the given input and output until the
+/// `exit` notification is received or the input is closed.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> Result<(), Error> {
+    let mut server = LanguageServer::new();
+    while let Some(message) = read_message(&mut input)? {
+        for response in server.handle_message(&message) {
+            write_message(&mut output, &response)?;
+        }
+        if server.has_exited() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod protocol_tests {
+    use super::*;
+
+    fn frame(message: &Value) -> String {
+        let content = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+    }
+    #[test]
+    fn test_run() {
+        let messages = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+                "textDocument": {"uri": "file:///games.db", "languageId": "pobsd", "version": 1, "text": "Game\tA\n//Engine"}
+            }}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/documentSymbol", "params": {
+                "textDocument": {"uri": "file:///games.db"}
+            }}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "unknown", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "exit"}),
+        ];
+        let input: String = messages.iter().map(frame).collect();
+        let mut output: Vec<u8> = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+        let mut output = output.as_slice();
+        let initialize = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(initialize["id"], 1);
+        assert_eq!(initialize["result"]["capabilities"]["hoverProvider"], true);
+        let diagnostics = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+        assert_eq!(
+            diagnostics["params"]["diagnostics"][0]["message"],
+            "unknown field `//Engine`, did you mean `Engine`?"
+        );
+        let symbols = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(symbols["result"][0]["name"], "A");
+        assert_eq!(symbols["result"][0]["range"]["end"]["line"], 2);
+        let unknown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        assert!(read_message(&mut output).unwrap().is_none());
+    }
+    #[test]
+    fn test_completion_and_hover() {
+        let mut server = LanguageServer::new();
+        server.handle_message(&json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": "file:///games.db", "text": "Game\tA\nEngine\tgodot\nStatus\t6 great"}
+        }}));
+        let response = server.handle_message(
+            &json!({"jsonrpc": "2.0", "id": 1, "method": "textDocument/completion", "params": {
+                "textDocument": {"uri": "file:///games.db"}, "position": {"line": 1, "character": 8}
+            }}),
+        );
+        assert_eq!(response[0]["result"][0]["label"], "godot");
+        assert_eq!(response[0]["result"][0]["kind"], COMPLETION_KIND_VALUE);
+        let response = server.handle_message(
+            &json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+                "textDocument": {"uri": "file:///games.db"}, "position": {"line": 2, "character": 8}
+            }}),
+        );
+        assert!(response[0]["result"]["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("**Status 6** (perfect)"));
+        let response = server.handle_message(
+            &json!({"jsonrpc": "2.0", "method": "textDocument/didClose", "params": {
+                "textDocument": {"uri": "file:///games.db"}
+            }}),
+        );
+        assert_eq!(response[0]["params"]["diagnostics"], json!([]));
+        assert!(server.get_document("file:///games.db").is_none());
+    }
+}
@@ -1,8 +1,9 @@
-use chrono::NaiveDate;
 use std::fmt;
 
 use crate::models::{
+    game_date::GameDate,
     game_status::{GameStatus, Status},
+    setup::Setup,
     split_line::split_line,
     store_links::{StoreLink, StoreLinks},
 };
@@ -23,7 +24,7 @@ pub enum Field {
     /// Store the result of a Engine line of the database
     Engine(Option<String>),
     /// Store the result of a Setup line of the database
-    Setup(Option<String>),
+    Setup(Option<Setup>),
     /// Store the result of a Runtime line of the database
     Runtime(Option<String>),
     /// Store the result of a Hints line of the database
@@ -48,15 +49,17 @@ pub enum Field {
     /// Store the result of a Year line of the database
     Year(Option<String>),
     /// When the game was added
-    Added(NaiveDate),
+    Added(GameDate),
     /// When the game was last updated
-    Updated(NaiveDate),
+    Updated(GameDate),
     /// The id of the game in the IGDB database
     IgdbId(Option<usize>),
+    /// Explicit uid of the game, used instead of the generated one
+    Id(Option<u32>),
     /// Store the result of a unknown line of the database
     /// The left hand side and the right hand side (if
     /// any) are stores separately.
-    Unknown(Option<String>),
+    Unknown(Option<String>, Option<String>),
 }
 
 impl fmt::Display for Field {
@@ -65,7 +68,6 @@ impl fmt::Display for Field {
             Field::Game(name)
             | Field::Cover(name)
             | Field::Engine(name)
-            | Field::Setup(name)
             | Field::Hints(name)
             | Field::Runtime(name)
             | Field::Version(name)
@@ -83,14 +85,18 @@ impl fmt::Display for Field {
                 Status::Unknown => write!(f, "Status"),
                 _ => write!(f, "Status\t{}", name),
             },
+            Field::Setup(name) => match name {
+                Some(name) => write!(f, "Setup\t{}", name),
+                None => write!(f, "Setup"),
+            },
             Field::Store(name) => match name {
                 Some(name) => write!(f, "Store\t{}", name),
                 None => write!(f, "Store"),
             },
             Field::Added(date) | Field::Updated(date) => {
-                write!(f, "{}\t{}", self.field_name(), date.format("%Y-%m-%d"))
+                write!(f, "{}\t{}", self.field_name(), date)
             }
-            Field::Unknown(field) => match field {
+            Field::Unknown(field, _) => match field {
                 Some(field) => {
                     write!(f, "Unknown field {}", field)
                 }
@@ -102,6 +108,10 @@ impl fmt::Display for Field {
                 Some(name) => write!(f, "{}\t{}", self.field_name(), name),
                 None => write!(f, "{}", self.field_name()),
             },
+            Field::Id(id) => match id {
+                Some(id) => write!(f, "{}\t{}", self.field_name(), id),
+                None => write!(f, "{}", self.field_name()),
+            },
         }
     }
 }
@@ -128,7 +138,7 @@ impl Field {
                     None => Field::Engine(None),
                 },
                 "Setup" => match right {
-                    Some(right) => Field::Setup(Some(right.into())),
+                    Some(right) => Field::Setup(Some(Setup::from_line(right))),
                     None => Field::Setup(None),
                 },
                 "Runtime" => match right {
@@ -203,18 +213,8 @@ impl Field {
                     Some(right) => Field::Year(Some(right.into())),
                     None => Field::Year(None),
                 },
-                "Added" => match right {
-                    Some(right) => Field::Added(
-                        NaiveDate::parse_from_str(right, "%Y-%m-%d").unwrap_or_default(),
-                    ),
-                    None => Field::Added(NaiveDate::default()),
-                },
-                "Updated" => match right {
-                    Some(right) => Field::Updated(
-                        NaiveDate::parse_from_str(right, "%Y-%m-%d").unwrap_or_default(),
-                    ),
-                    None => Field::Updated(NaiveDate::default()),
-                },
+                "Added" => Field::Added(GameDate::from_value(right)),
+                "Updated" => Field::Updated(GameDate::from_value(right)),
                 "IgdbId" => match right {
                     Some(right) => match right.parse::<usize>().ok() {
                         Some(right) => Field::IgdbId(Some(right.into())),
@@ -222,13 +222,70 @@ impl Field {
                     },
                     None => Field::IgdbId(None),
                 },
-                _ => Field::Unknown(Some(left.into())),
+                "Id" => Field::Id(right.and_then(|right| right.parse::<u32>().ok())),
+                _ => Field::Unknown(Some(left.into()), right.map(|right| right.into())),
             }
         } else {
-            Field::Unknown(None)
+            Field::Unknown(None, None)
         }
     }
-    pub fn field_name(&self) -> &str {
+    /// Returns true if the value of the field could not be parsed
+    /// (e.g. a malformed date).
+    pub(crate) fn is_malformed(&self) -> bool {
+        matches!(
+            self,
+            Field::Added(GameDate::Invalid(_)) | Field::Updated(GameDate::Invalid(_))
+        )
+    }
+    /// Returns true if the field has no value.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Field::Game(value)
+            | Field::Cover(value)
+            | Field::Engine(value)
+            | Field::Runtime(value)
+            | Field::Hints(value)
+            | Field::Version(value)
+            | Field::Year(value) => value.is_none(),
+            Field::Setup(value) => value.is_none(),
+            Field::Store(value) => value.is_none(),
+            Field::Genres(value) | Field::Tags(value) | Field::Dev(value) | Field::Publi(value) => {
+                value.is_none()
+            }
+            Field::Status(value) => value.status == Status::Unknown && value.message.is_none(),
+            Field::Added(date) | Field::Updated(date) => *date == GameDate::Unknown,
+            Field::IgdbId(value) => value.is_none(),
+            Field::Id(value) => value.is_none(),
+            Field::Unknown(key, _) => key.is_none(),
+        }
+    }
+    /// Returns the position of the field in a game record, None for
+    /// the Unknown variant.
+    pub fn position(&self) -> Option<usize> {
+        let position = match self {
+            Field::Game(_) => 0,
+            Field::Cover(_) => 1,
+            Field::Engine(_) => 2,
+            Field::Setup(_) => 3,
+            Field::Runtime(_) => 4,
+            Field::Store(_) => 5,
+            Field::Hints(_) => 6,
+            Field::Genres(_) => 7,
+            Field::Tags(_) => 8,
+            Field::Year(_) => 9,
+            Field::Dev(_) => 10,
+            Field::Publi(_) => 11,
+            Field::Version(_) => 12,
+            Field::Status(_) => 13,
+            Field::Added(_) => 14,
+            Field::Updated(_) => 15,
+            Field::IgdbId(_) => 16,
+            Field::Id(_) => 17,
+            Field::Unknown(..) => return None,
+        };
+        Some(position)
+    }
+    pub fn field_name(&self) -> &'static str {
         match self {
             Field::Game(_) => "Game",
             Field::Cover(_) => "Cover",
@@ -247,7 +304,8 @@ impl Field {
             Field::Added(_) => "Added",
             Field::Updated(_) => "Updated",
             Field::IgdbId(_) => "IgdbId",
-            Field::Unknown(_) => "Unknown field",
+            Field::Id(_) => "Id",
+            Field::Unknown(..) => "Unknown field",
         }
     }
 }
@@ -443,21 +501,18 @@ mod field_tests {
         let input = "Added\t1980-11-01";
         let field = Field::from(&input);
         assert_eq!(
-            Field::Added(NaiveDate::parse_from_str("1980-11-01", "%Y-%m-%d").unwrap()),
+            Field::Added(GameDate::from_value(Some("1980-11-01"))),
             field
         );
         assert_eq!(format!("{}", field), input);
         let input = "Added";
         let field = Field::from(&input);
-        assert_eq!(
-            Field::Added(NaiveDate::parse_from_str("1970-01-01", "%Y-%m-%d").unwrap()),
-            field
-        );
+        assert_eq!(Field::Added(GameDate::Unknown), field);
         assert_eq!(format!("{}", field), format!("{}\t1970-01-01", input));
         // should not happen normally but check if
         // it works as expected just in case.
         assert_eq!(
-            format!("{}", Field::Added(NaiveDate::default())),
+            format!("{}", Field::Added(GameDate::default())),
             format!("Added\t1970-01-01")
         );
     }
@@ -466,21 +521,35 @@ mod field_tests {
         let input = "Updated\t1980-12-01";
         let field = Field::from(&input);
         assert_eq!(
-            Field::Updated(NaiveDate::parse_from_str("1980-12-01", "%Y-%m-%d").unwrap()),
+            Field::Updated(GameDate::from_value(Some("1980-12-01"))),
             field
         );
         assert_eq!(format!("{}", field), input);
         let input = "Updated";
         let field = Field::from(&input);
         let input = "Updated\t1970-01-01";
-        assert_eq!(Field::Updated(NaiveDate::default()), field);
+        assert_eq!(Field::Updated(GameDate::Unknown), field);
+        assert_eq!(format!("{}", field), input);
+    }
+    #[test]
+    fn test_from_malformed_date_line() {
+        let input = "Updated\t2022-13-05";
+        let field = Field::from(input);
+        assert_eq!(
+            Field::Updated(GameDate::Invalid("2022-13-05".into())),
+            field
+        );
+        assert!(!field.is_empty());
         assert_eq!(format!("{}", field), input);
     }
     #[test]
     fn test_from_unknown_field() {
         let input = "Let's not\tpanic";
         let field = Field::from(&input);
-        assert_eq!(Field::Unknown(Some("Let's not".into())), field);
+        assert_eq!(
+            Field::Unknown(Some("Let's not".into()), Some("panic".into())),
+            field
+        );
         assert_eq!(
             format!("{}", field),
             format!("Unknown field {}", "Let's not")
@@ -490,14 +559,14 @@ mod field_tests {
     fn test_from_unknown_field_with_notab() {
         let input = "Let's not";
         let field = Field::from(&input);
-        assert_eq!(Field::Unknown(Some("Let's not".into())), field);
+        assert_eq!(Field::Unknown(Some("Let's not".into()), None), field);
         assert_eq!(format!("{}", field), format!("Unknown field {}", input));
     }
     #[test]
     fn test_from_unknown_field_with_empty_line() {
         let input = "";
         let field = Field::from(&input);
-        assert_eq!(Field::Unknown(None), field);
+        assert_eq!(Field::Unknown(None, None), field);
         assert_eq!(format!("{}", field), "Unexpected pattern");
     }
     #[test]
@@ -511,4 +580,12 @@ mod field_tests {
         assert_eq!(Field::IgdbId(None), field);
         assert_eq!(format!("{}", field), input);
     }
+    #[test]
+    fn test_from_id_line() {
+        let input = "Id\t4242";
+        let field = Field::from(input);
+        assert_eq!(Field::Id(Some(4242)), field);
+        assert_eq!(format!("{}", field), input);
+        assert_eq!(Field::from("Id\tabc"), Field::Id(None));
+    }
 }
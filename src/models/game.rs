@@ -1,14 +1,16 @@
 //! Provides a representations of the game in the PlayOnBSD database.
 use crate::{
+    db::matcher::Matcher,
     models::{
         field::Field,
+        game_date::GameDate,
         game_status::{GameStatus, Status},
+        setup::Setup,
         store_links::StoreLinks,
     },
     SearchType, Store,
 };
 
-use chrono::NaiveDate;
 use paste::paste;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -19,62 +21,62 @@ use std::{
 
 macro_rules! game_contains {
     (name) => {
-        /// Returns true if the name field of a [`Game`] contains the given pattern, false otherwise.
-        /// The search can be case sensitive or not depending on the [`SearchType`] variant.
+        /// Returns true if the name field of a [`Game`] matches the given pattern, false otherwise.
+        /// The pattern is searched within the name, or compared to it, depending on the
+        /// [`SearchType`] variant.
         pub fn name_contains(&self, pattern: &str, search_type: &SearchType) -> bool {
-            match search_type {
-                SearchType::CaseSensitive => self.name.contains(pattern),
-                SearchType::NotCaseSensitive => {
-                    self.name.to_lowercase().contains(&pattern.to_lowercase())
-                }
-            }
+            self.name_matches(&Matcher::new(search_type, pattern))
+        }
+        pub(crate) fn name_matches(&self, matcher: &Matcher) -> bool {
+            matcher.is_match(&self.name)
         }
     };
     ($field:ident) => {
         paste! {
-            /// Returns true if the chosen field of a [`Game`] contains the given pattern, false otherwise.
-            /// The search can be case sensitive or not depending on the [`SearchType`] variant.
+            /// Returns true if the chosen field of a [`Game`] matches the given pattern, false otherwise.
+            /// The pattern is searched within the field, or compared to it, depending on the
+            /// [`SearchType`] variant.
             pub fn [<$field _contains>](&self, pattern: &str, search_type: &SearchType) -> bool {
-            match search_type {
-                SearchType::CaseSensitive => self.[<$field>].as_ref().is_some_and(|v| v.contains(pattern)),
-                SearchType::NotCaseSensitive => self
-                    .[< $field>]
-                    .as_ref()
-                    .is_some_and(|v| v.to_lowercase().contains(&pattern.to_lowercase())),
-                }
+                self.[<$field _matches>](&Matcher::new(search_type, pattern))
+            }
+            pub(crate) fn [<$field _matches>](&self, matcher: &Matcher) -> bool {
+                self.[<$field>].as_ref().map_or(false, |v| matcher.is_match(v))
             }
         }
     };
     (array $field:ident) => {
         paste! {
-            /// Returns true if the chosen field of a [`Game`] contains the given pattern, false otherwise.
-            /// The search can be case sensitive or not depending on the [`SearchType`] variant.
+            /// Returns true if one of the items of the chosen field of a [`Game`] matches the given
+            /// pattern, false otherwise. The pattern is searched within each item, or compared to
+            /// it, depending on the [`SearchType`] variant.
             pub fn [<$field _contains>](&self, value: &str, search_type: &SearchType) -> bool {
-                match search_type {
-                    SearchType::CaseSensitive => match self.[<$field>].as_ref() {
-                        Some(items) => {
-                            !items
-                            .iter().filter(|x| x.contains(value))
-                            .collect::<Vec<&String>>()
-                            .is_empty()
-                            },
-                        None => false,
-                    },
-                    SearchType::NotCaseSensitive => match self.[<$field>].as_ref() {
-                        Some(items) => {
-                            !items
-                            .iter().filter(|x| x.to_lowercase().contains(&value.to_lowercase()))
-                            .collect::<Vec<&String>>()
-                            .is_empty()
-                            },
-                        None => false,
-                    },
-                }
+                self.[<$field _matches>](&Matcher::new(search_type, value))
+            }
+            pub(crate) fn [<$field _matches>](&self, matcher: &Matcher) -> bool {
+                self.[<$field>]
+                    .as_ref()
+                    .map_or(false, |items| items.iter().any(|item| matcher.is_match(item)))
             }
         }
     };
 }
 
+/// Normalizes a game name so that slightly different spellings of the
+/// same name are equal: the name is lowercased, punctuation is replaced
+/// by spaces, whitespaces are collapsed and leading articles are removed.
+pub(crate) fn normalize_name(name: &str) -> String {
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = name.split_whitespace().collect();
+    if words.len() > 1 && matches!(words[0], "the" | "a" | "an") {
+        words.remove(0);
+    }
+    words.join(" ")
+}
+
 /// Representation of a game of the PlayOnBSD database.
 ///
 /// It also includes an additional [`Game::uid`] field
@@ -82,7 +84,9 @@ macro_rules! game_contains {
 /// which the game was added to the database. It therefore
 /// provides an unique identifier under the assumption that no
 /// game with the same name will be added the same date into
-/// the database.
+/// the database. A record can instead give its uid explicitly
+/// with an `Id` field, so that it does not change when the name
+/// or the date are corrected.
 ///
 /// The name of some fields differs from the one used
 /// in the database itself: Genre and Store are plural
@@ -95,6 +99,11 @@ macro_rules! game_contains {
 /// and will be displayed as it would appear in the
 /// PlayOnBSD database.
 ///
+/// ### Extra fields
+/// The lines whose key is not one of the database (e.g. `Controller` in a
+/// fork experimenting with new keys) are kept in [`Game::extra`], in the
+/// order they appear, and displayed after the `IgdbId` line.
+///
 /// ### PartialOrd
 /// The [`Game`] struct implements the [`core::cmp::PartialOrd`] trait
 /// and [`Game`] objects are ordered according to their name (without The or A).
@@ -111,7 +120,7 @@ pub struct Game {
     /// Engine used by the game.
     pub engine: Option<String>,
     /// Step(s) to setup the game.
-    pub setup: Option<String>,
+    pub setup: Option<Setup>,
     /// Executable in the package.
     pub runtime: Option<String>,
     /// Vector with store urls.
@@ -134,12 +143,26 @@ pub struct Game {
     pub version: Option<String>,
     /// When tested on -current.
     pub status: GameStatus,
-    /// When added
-    pub added: NaiveDate,
-    /// When updated
-    pub updated: NaiveDate,
+    /// When added, [`GameDate::Unknown`] if not available
+    pub added: GameDate,
+    /// When updated, [`GameDate::Unknown`] if not available
+    pub updated: GameDate,
     /// IGDB Id of the game
     pub igdb_id: Option<usize>,
+    /// Explicit uid given by the `Id` field, used as [`Game::uid`]
+    /// instead of the generated one.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub id: Option<u32>,
+    /// Unknown and extension fields (key and value if any), in the
+    /// order they appear in the database.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub extra: Vec<(String, Option<String>)>,
 }
 
 impl<'a> Game {
@@ -161,6 +184,12 @@ impl<'a> Game {
         }
     }
 
+    /// Returns the name of the game normalized for comparison: lowercased,
+    /// without punctuation nor leading article ("The", "A" or "An").
+    pub fn get_normalized_name(&self) -> String {
+        normalize_name(&self.name)
+    }
+
     game_contains!(name);
     game_contains!(engine);
     game_contains!(runtime);
@@ -177,6 +206,53 @@ impl<'a> Game {
     pub fn status_is(&self, status: &impl AsRef<Status>) -> bool {
         self.status.status.eq(status.as_ref())
     }
+    /// Returns the value of the given extra field (e.g. `Controller`),
+    /// None if the field is missing or has no value.
+    pub fn get_extra(&self, key: &str) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(extra_key, _)| extra_key == key)
+            .and_then(|(_, value)| value.as_deref())
+    }
+    /// Returns true if the [`Game`] has the given extra field.
+    pub fn has_extra(&self, key: &str) -> bool {
+        self.extra.iter().any(|(extra_key, _)| extra_key == key)
+    }
+    /// Sets the value of the given extra field. An existing field keeps
+    /// its position, a new one is added after the others.
+    pub fn set_extra(&mut self, key: impl Into<String>, value: Option<String>) {
+        let key = key.into();
+        match self
+            .extra
+            .iter_mut()
+            .find(|(extra_key, _)| *extra_key == key)
+        {
+            Some(extra) => extra.1 = value,
+            None => self.extra.push((key, value)),
+        }
+    }
+    /// Returns the fields of the game in the order of the database.
+    pub(crate) fn fields(&self) -> Vec<Field> {
+        vec![
+            Field::Game(Some(self.name.to_string())),
+            Field::Cover(self.cover.to_owned()),
+            Field::Engine(self.engine.to_owned()),
+            Field::Setup(self.setup.to_owned()),
+            Field::Runtime(self.runtime.to_owned()),
+            Field::Store(self.stores.to_owned()),
+            Field::Hints(self.hints.to_owned()),
+            Field::Genres(self.genres.to_owned()),
+            Field::Tags(self.tags.to_owned()),
+            Field::Year(self.year.to_owned()),
+            Field::Dev(self.devs.to_owned()),
+            Field::Publi(self.publis.to_owned()),
+            Field::Version(self.version.to_owned()),
+            Field::Status(self.status.to_owned()),
+            Field::Added(self.added.to_owned()),
+            Field::Updated(self.updated.to_owned()),
+            Field::IgdbId(self.igdb_id.to_owned()),
+        ]
+    }
     /// Returns the Steam id of a [`Game`] if it has any.
     pub fn get_steam_id(&self) -> Option<usize> {
         if let Some(ref stores) = self.stores {
@@ -228,27 +304,18 @@ impl AsRef<Game> for Game {
 /// for details.
 impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
-            Field::Game(Some(self.name.to_string())),
-            Field::Cover(self.cover.to_owned()),
-            Field::Engine(self.engine.to_owned()),
-            Field::Setup(self.setup.to_owned()),
-            Field::Runtime(self.runtime.to_owned()),
-            Field::Store(self.stores.to_owned()),
-            Field::Hints(self.hints.to_owned()),
-            Field::Genres(self.genres.to_owned()),
-            Field::Tags(self.tags.to_owned()),
-            Field::Year(self.year.to_owned()),
-            Field::Dev(self.devs.to_owned()),
-            Field::Publi(self.publis.to_owned()),
-            Field::Version(self.version.to_owned()),
-            Field::Status(self.status.to_owned()),
-            Field::Added(self.added.to_owned()),
-            Field::Updated(self.updated.to_owned()),
-            Field::IgdbId(self.igdb_id.to_owned()),
-        )
+        let fields: Vec<String> = self.fields().iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", fields.join("\n"))?;
+        if self.id.is_some() {
+            write!(f, "\n{}", Field::Id(self.id))?;
+        }
+        for (key, value) in &self.extra {
+            match value {
+                Some(value) => write!(f, "\n{}\t{}", key, value)?,
+                None => write!(f, "\n{}", key)?,
+            }
+        }
+        Ok(())
     }
 }
 
@@ -270,7 +337,7 @@ mod game_tests {
         game.name = "game name".to_string();
         game.cover = Some("cover.jpg".to_string());
         game.engine = Some("game engine".to_string());
-        game.setup = Some("game setup".to_string());
+        game.setup = Some("game setup".into());
         game.runtime = Some("game runtime".to_string());
         game.stores = Some(stores);
         game.hints = Some("game hints".to_string());
@@ -281,8 +348,8 @@ mod game_tests {
         game.publis = Some(vec!["game publi".to_string()]);
         game.version = Some("game version".to_string());
         game.status = GameStatus::new(Status::DoesNotRun, Some("game status".to_string()));
-        game.added = NaiveDate::parse_from_str("2012-12-03", "%Y-%m-%d").unwrap();
-        game.updated = NaiveDate::parse_from_str("2014-12-03", "%Y-%m-%d").unwrap();
+        game.added = GameDate::from_value(Some("2012-12-03"));
+        game.updated = GameDate::from_value(Some("2014-12-03"));
         game
     }
     #[test]
@@ -324,6 +391,20 @@ mod game_tests {
         assert_eq!(game.get_ordering_name(), "thechampion");
     }
     #[test]
+    fn test_get_normalized_name() {
+        let mut game = create_game();
+        game.name = "The Adventures of Shuggy".into();
+        assert_eq!(game.get_normalized_name(), "adventures of shuggy");
+        game.name = "Adventures of  Shuggy!".into();
+        assert_eq!(game.get_normalized_name(), "adventures of shuggy");
+        game.name = "Airships: Conquer the Skies".into();
+        assert_eq!(game.get_normalized_name(), "airships conquer the skies");
+        game.name = "An Untitled Story".into();
+        assert_eq!(game.get_normalized_name(), "untitled story");
+        game.name = "The".into();
+        assert_eq!(game.get_normalized_name(), "the");
+    }
+    #[test]
     fn test_ordering() {
         use std::cmp::Ordering;
         let mut game1 = create_game();
@@ -390,9 +471,11 @@ IgdbId";
             publis: None,
             version: None,
             status: GameStatus::default(),
-            added: NaiveDate::default(),
-            updated: NaiveDate::default(),
+            added: GameDate::default(),
+            updated: GameDate::default(),
             igdb_id: None,
+            id: None,
+            extra: vec![],
         };
         assert_eq!(format!("{}", game), game_str);
     }
@@ -420,7 +503,7 @@ IgdbId\t1234";
             name: "AaaaaAAaaaAAAaaAAAAaAAAAA!!! for the Awesome".to_string(),
             cover: None,
             engine: Some("Engine1".to_string()),
-            setup: Some("Setup1".to_string()),
+            setup: Some("Setup1".into()),
             runtime: None,
             stores: None,
             hints: None,
@@ -431,13 +514,27 @@ IgdbId\t1234";
             publis: Some(vec!["pub1".to_string()]),
             version: Some("ver1".to_string()),
             status: GameStatus::new(Status::DoesNotRun, Some("fine".to_string())),
-            added: NaiveDate::parse_from_str("1970-01-01", "%Y-%m-%d").unwrap(),
-            updated: NaiveDate::parse_from_str("1970-01-02", "%Y-%m-%d").unwrap(),
+            added: GameDate::Unknown,
+            updated: GameDate::from_value(Some("1970-01-02")),
             igdb_id: Some(1234),
+            id: None,
+            extra: vec![],
         };
         assert_eq!(format!("{}", game), game_str);
     }
     #[test]
+    fn test_display_with_extra() {
+        let mut game = create_game();
+        game.set_extra("Controller", Some("full".into()));
+        game.set_extra("Multiplayer", None);
+        game.set_extra("Controller", Some("partial".into()));
+        assert_eq!(game.get_extra("Controller"), Some("partial"));
+        assert_eq!(game.get_extra("Multiplayer"), None);
+        assert!(game.has_extra("Multiplayer"));
+        assert!(!game.has_extra("License"));
+        assert!(format!("{}", game).ends_with("\nController\tpartial\nMultiplayer"));
+    }
+    #[test]
     fn test_name_contains() {
         let game = create_game();
         let st = SearchType::CaseSensitive;
@@ -0,0 +1,141 @@
+//! Provides a [`GameDate`] enum representing the value of the `Added`
+//! and `Updated` fields of the PlayOnBSD database, distinguishing an
+//! unknown date from a malformed one.
+use chrono::NaiveDate;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Represents a date of the PlayOnBSD database. The database uses
+/// 1970-01-01 (EPOCH) when the date is not available: such a date, as
+/// well as a missing one, is [`GameDate::Unknown`] and is displayed as
+/// 1970-01-01.
+///
+/// The variants are ordered: an unknown date comes before a malformed
+/// one, itself before any known date.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", from = "String"))]
+pub enum GameDate {
+    /// The date is not available.
+    #[default]
+    Unknown,
+    /// The date could not be parsed (e.g. `2022-13-05`), the value
+    /// being kept as written in the database.
+    Invalid(String),
+    /// A valid date.
+    Known(NaiveDate),
+}
+
+impl GameDate {
+    /// Creates a [`GameDate`] from the value of a date field of the
+    /// PlayOnBSD database (YYYY-MM-DD).
+    pub fn from_value(value: Option<&str>) -> Self {
+        match value.map(|value| value.trim()) {
+            None | Some("") => GameDate::Unknown,
+            Some(value) => match NaiveDate::parse_from_str(value, DATE_FORMAT) {
+                Ok(date) => date.into(),
+                Err(_) => GameDate::Invalid(value.into()),
+            },
+        }
+    }
+    /// Returns the date if it is known.
+    pub fn get_date(&self) -> Option<NaiveDate> {
+        match self {
+            GameDate::Known(date) => Some(*date),
+            _ => None,
+        }
+    }
+    /// Returns the date if it is known, 1970-01-01 otherwise.
+    pub fn get_date_or_epoch(&self) -> NaiveDate {
+        self.get_date().unwrap_or_default()
+    }
+    /// Returns true if the date is known.
+    pub fn is_known(&self) -> bool {
+        matches!(self, GameDate::Known(_))
+    }
+    /// Returns true if the date could not be parsed.
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, GameDate::Invalid(_))
+    }
+}
+
+impl From<NaiveDate> for GameDate {
+    /// 1970-01-01 being used when the date is not available, it
+    /// gives [`GameDate::Unknown`].
+    fn from(date: NaiveDate) -> Self {
+        if date == NaiveDate::default() {
+            GameDate::Unknown
+        } else {
+            GameDate::Known(date)
+        }
+    }
+}
+
+impl From<String> for GameDate {
+    fn from(value: String) -> Self {
+        GameDate::from_value(Some(&value))
+    }
+}
+
+impl From<GameDate> for String {
+    fn from(date: GameDate) -> Self {
+        date.to_string()
+    }
+}
+
+impl PartialEq<NaiveDate> for GameDate {
+    fn eq(&self, other: &NaiveDate) -> bool {
+        self.get_date_or_epoch().eq(other) && !self.is_invalid()
+    }
+}
+
+/// Displays the date as written in the database: 1970-01-01 for an
+/// unknown date and the original value for a malformed one.
+impl fmt::Display for GameDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameDate::Unknown => write!(f, "{}", NaiveDate::default().format(DATE_FORMAT)),
+            GameDate::Invalid(value) => write!(f, "{}", value),
+            GameDate::Known(date) => write!(f, "{}", date.format(DATE_FORMAT)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod game_date_tests {
+    use super::*;
+    #[test]
+    fn test_from_value() {
+        let date = NaiveDate::from_ymd_opt(2022, 5, 13).unwrap();
+        assert_eq!(GameDate::from_value(Some("2022-05-13")), date);
+        assert_eq!(GameDate::from_value(Some("1970-01-01")), GameDate::Unknown);
+        assert_eq!(GameDate::from_value(None), GameDate::Unknown);
+        let invalid = GameDate::from_value(Some("2022-13-05"));
+        assert_eq!(invalid, GameDate::Invalid("2022-13-05".into()));
+        assert!(invalid.is_invalid());
+        assert_ne!(invalid, NaiveDate::default());
+        assert_eq!(invalid.get_date_or_epoch(), NaiveDate::default());
+    }
+    #[test]
+    fn test_display() {
+        assert_eq!(GameDate::Unknown.to_string(), "1970-01-01");
+        assert_eq!(
+            GameDate::Invalid("2022-13-05".into()).to_string(),
+            "2022-13-05"
+        );
+        let date = GameDate::from_value(Some("2022-05-13"));
+        assert_eq!(date.to_string(), "2022-05-13");
+        assert_eq!(GameDate::from(date.to_string()), date);
+    }
+    #[test]
+    fn test_ordering() {
+        let date = GameDate::from_value(Some("2022-05-13"));
+        assert!(GameDate::Unknown < GameDate::Invalid("x".into()));
+        assert!(GameDate::Invalid("x".into()) < date);
+        assert!(date < GameDate::from_value(Some("2022-05-14")));
+    }
+}
@@ -1,6 +1,7 @@
 //! Provides a [`Status`] enum and a [`GameStatus`] struct to
 //! represent the status of a Game according to the value of
 //! the status field in the PlayOnBSD database.
+use chrono::NaiveDate;
 use std::fmt::Display;
 
 #[cfg(feature = "serde")]
@@ -78,6 +79,14 @@ pub struct GameStatus {
 }
 
 impl GameStatus {
+    /// Returns the date at which the game was tested, given in
+    /// parentheses at the end of the comment (e.g. `(2022-05-13)`).
+    pub fn get_tested_date(&self) -> Option<NaiveDate> {
+        let message = self.message.as_ref()?;
+        let start = message.rfind('(')?;
+        let end = start + message[start..].find(')')?;
+        NaiveDate::parse_from_str(&message[start + 1..end], "%Y-%m-%d").ok()
+    }
     /// Creates a new [`GameStatus`] provided a [`Status`] and an optional
     /// comment.
     pub fn new(status: Status, message: Option<String>) -> Self {
@@ -179,6 +188,14 @@ mod game_status_test {
         assert_eq!(st, Status::Unknown);
     }
     #[test]
+    fn test_get_tested_date() {
+        let gst = GameStatus::from_line("3 graphics artefacts (2023-01-04)");
+        assert_eq!(gst.get_tested_date(), NaiveDate::from_ymd_opt(2023, 1, 4));
+        let gst = GameStatus::from_line("3 (not dated)");
+        assert_eq!(gst.get_tested_date(), None);
+        assert_eq!(GameStatus::default().get_tested_date(), None);
+    }
+    #[test]
     fn test_game_status_from_line_parfect() {
         let line = "6 comment";
         let gst = GameStatus::from_line(line);
@@ -7,16 +7,25 @@
 //! collection.
 //! * A [`GameStatus`] struct representing the status of a Gamei. The status
 //! itself as a [`Status`] enum and the optional associated comment.
+//! * A [`GameDate`] enum representing the added and updated dates of a
+//!   game, distinguishing unknown and malformed dates.
+//! * A [`Setup`] struct representing the setup field of a game, split
+//!   into [`SetupStep`]s (packages, commands or text).
 //!
 pub(crate) mod field;
 pub mod game;
+pub mod game_date;
 pub mod game_status;
+pub mod setup;
 pub(crate) mod split_line;
 pub mod store_links;
 
 pub use self::game::Game;
+pub use self::game_date::GameDate;
 pub use self::game_status::GameStatus;
 pub use self::game_status::Status;
+pub use self::setup::Setup;
+pub use self::setup::SetupStep;
 pub use self::store_links::Store;
 pub use self::store_links::StoreLink;
 pub use self::store_links::StoreLinks;
@@ -0,0 +1,256 @@
+//! Provides a representation of the setup field associated to each game.
+//!
+//! The setup field of the PlayOnBSD database is free-form but usually
+//! holds OpenBSD packages to install, shell commands to run (e.g. `fnaify`
+//! or `hlwrap`) and/or some text. The [`Setup`] struct splits the field
+//! into [`SetupStep`]s while keeping the original line so that it is
+//! displayed exactly as it appears in the database.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// PlayOnBSD helpers recognized as commands when they start a setup step.
+pub(crate) const KNOWN_COMMANDS: [&str; 2] = ["fnaify", "hlwrap"];
+
+/// Packages recognized as such when given without `pkg_add` nor version.
+const KNOWN_PACKAGES: [&str; 6] = ["lwjgl", "lwjgl3", "jdk", "mono", "godot", "love"];
+
+/// Represents a single step of the setup of a game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetupStep {
+    /// OpenBSD package to install (e.g. "lwjgl").
+    Package(String),
+    /// Shell command to run (e.g. "fnaify -y"), the name of the command
+    /// being its first word.
+    Command(String),
+    /// Free text instructions.
+    Text(String),
+}
+
+impl SetupStep {
+    // Converts a part of the setup line into steps
+    fn parse(part: &str, steps: &mut Vec<SetupStep>) {
+        let part = part.trim();
+        if part.is_empty() {
+            return;
+        }
+        let mut words = part.split_whitespace();
+        let first = words.next().unwrap_or_default();
+        let first = match first {
+            "doas" => words.next().unwrap_or(first),
+            _ => first,
+        };
+        if first.eq("pkg_add") {
+            for package in words.filter(|word| !word.starts_with('-')) {
+                steps.push(SetupStep::Package(package.into()));
+            }
+        } else if KNOWN_COMMANDS.contains(&first) {
+            steps.push(SetupStep::Command(part.into()));
+        } else if is_package_name(part)
+            && (KNOWN_PACKAGES.contains(&part) || is_versioned_package(part))
+        {
+            steps.push(SetupStep::Package(part.into()));
+        } else {
+            steps.push(SetupStep::Text(part.into()));
+        }
+    }
+    /// Returns the name of the command (its first word) if the step is a
+    /// [`SetupStep::Command`].
+    pub fn command_name(&self) -> Option<&str> {
+        match self {
+            SetupStep::Command(command) => {
+                let mut words = command.split_whitespace();
+                match words.next() {
+                    Some("doas") => words.next(),
+                    first => first,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for SetupStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetupStep::Package(step) | SetupStep::Command(step) | SetupStep::Text(step) => {
+                write!(f, "{}", step)
+            }
+        }
+    }
+}
+
+// Returns true if the given str looks like an OpenBSD package name
+fn is_package_name(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && chars.all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '+' | '.' | '-')
+        })
+}
+
+// Returns true if the given package name carries a version (e.g.
+// "godot-3.5.2") or a flavor (e.g. "vim--no_x11")
+fn is_versioned_package(value: &str) -> bool {
+    value
+        .split('-')
+        .skip(1)
+        .any(|part| part.is_empty() || part.chars().next().map_or(false, |c| c.is_ascii_digit()))
+}
+
+/// Represents the setup field of a game.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "String", into = "String"))]
+pub struct Setup {
+    line: String,
+    steps: Vec<SetupStep>,
+}
+
+impl Setup {
+    /// Creates a [`Setup`] from the value of the setup field in the PlayOnBSD
+    /// database. Steps are separated by `;` or `&&`.
+    pub fn from_line(line: &str) -> Self {
+        let mut steps: Vec<SetupStep> = Vec::new();
+        for part in line.split(';').flat_map(|part| part.split("&&")) {
+            SetupStep::parse(part, &mut steps);
+        }
+        Self {
+            line: line.into(),
+            steps,
+        }
+    }
+    /// Returns the original line of the setup field.
+    pub fn as_str(&self) -> &str {
+        &self.line
+    }
+    /// Returns the steps of the setup.
+    pub fn steps(&self) -> &[SetupStep] {
+        &self.steps
+    }
+    /// Returns the OpenBSD packages required by the setup.
+    pub fn packages(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step {
+                SetupStep::Package(package) => Some(package.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Returns the names of the commands run by the setup.
+    pub fn commands(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter_map(|step| step.command_name())
+            .collect()
+    }
+    /// Returns true if the setup requires the given OpenBSD package, false otherwise.
+    pub fn needs_package(&self, package: &str) -> bool {
+        self.packages().contains(&package)
+    }
+    /// Returns true if the setup runs the given command, false otherwise.
+    pub fn needs_command(&self, command: &str) -> bool {
+        self.commands().contains(&command)
+    }
+}
+
+impl From<&str> for Setup {
+    fn from(line: &str) -> Self {
+        Setup::from_line(line)
+    }
+}
+
+impl From<String> for Setup {
+    fn from(line: String) -> Self {
+        Setup::from_line(&line)
+    }
+}
+
+impl From<Setup> for String {
+    fn from(setup: Setup) -> Self {
+        setup.line
+    }
+}
+
+/// Displays the setup as it appears in the database.
+impl Display for Setup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.line)
+    }
+}
+
+#[cfg(test)]
+mod setup_tests {
+    use super::*;
+    #[test]
+    fn test_is_package_name() {
+        assert!(is_package_name("lwjgl"));
+        assert!(is_package_name("godot-3.5.2"));
+        assert!(is_package_name("gtk+3"));
+        assert!(is_package_name("vim--no_x11"));
+        assert!(!is_package_name("Copy the data files"));
+        assert!(!is_package_name("Steam"));
+        assert!(!is_package_name("-v"));
+        assert!(is_versioned_package("godot-3.5.2"));
+        assert!(is_versioned_package("vim--no_x11"));
+        assert!(!is_versioned_package("none"));
+        assert!(!is_versioned_package("sdl2-mixer"));
+    }
+    #[test]
+    fn test_setup_command() {
+        let setup = Setup::from_line("fnaify");
+        assert_eq!(setup.steps(), &[SetupStep::Command("fnaify".into())]);
+        assert!(setup.needs_command("fnaify"));
+        assert!(setup.packages().is_empty());
+        let setup = Setup::from_line("hlwrap -f game.hl");
+        assert_eq!(setup.commands(), vec!["hlwrap"]);
+        let setup = Setup::from_line("doas fnaify -y");
+        assert_eq!(setup.commands(), vec!["fnaify"]);
+    }
+    #[test]
+    fn test_setup_package() {
+        let setup = Setup::from_line("lwjgl");
+        assert_eq!(setup.steps(), &[SetupStep::Package("lwjgl".into())]);
+        assert!(setup.needs_package("lwjgl"));
+        let setup = Setup::from_line("pkg_add -v jdk godot");
+        assert_eq!(setup.packages(), vec!["jdk", "godot"]);
+        assert!(setup.commands().is_empty());
+    }
+    #[test]
+    fn test_setup_mixed() {
+        let line = "lwjgl; fnaify && Copy the Content directory";
+        let setup = Setup::from_line(line);
+        assert_eq!(
+            setup.steps(),
+            &[
+                SetupStep::Package("lwjgl".into()),
+                SetupStep::Command("fnaify".into()),
+                SetupStep::Text("Copy the Content directory".into()),
+            ]
+        );
+        assert_eq!(format!("{}", setup), line);
+        assert_eq!(String::from(setup), line);
+    }
+    #[test]
+    fn test_setup_text() {
+        let setup = Setup::from_line("See the hints");
+        assert_eq!(setup.steps(), &[SetupStep::Text("See the hints".into())]);
+        assert_eq!(setup.steps()[0].command_name(), None);
+        for line in ["none", "steam", "see"] {
+            let setup = Setup::from_line(line);
+            assert_eq!(setup.steps(), &[SetupStep::Text(line.into())]);
+        }
+        let setup = Setup::from_line("rm -rf data; cp a b");
+        assert!(setup.commands().is_empty());
+        assert_eq!(
+            setup.steps(),
+            &[
+                SetupStep::Text("rm -rf data".into()),
+                SetupStep::Text("cp a b".into()),
+            ]
+        );
+    }
+}
@@ -81,13 +81,43 @@
 //! assert_eq!(Some(String::from("godot")), game1.engine);
 //!
 //! ```
+//! The parser can also record where each game and each of its fields
+//! are located in the database (see [`ParserReport`]).
+//! ```no_run
+//! use libpobsd::Parser;
+//!
+//! let report = Parser::default()
+//!        .load_report_from_file("/path/to/games.db")
+//!        .expect("Problem trying to open the file");
+//! for (game, span) in report.get_games().iter().zip(&report.spans) {
+//!     println!("{} (lines {} to {})", game.name, span.start_line, span.end_line);
+//! }
+//! ```
+//! In recovery mode, a record whose `Game` line is broken is set aside
+//! instead of overwriting the fields of the previous game.
+//! ```
+//! use libpobsd::{Parser, ParsingMode};
+//!
+//! let data = "Game\tGame 1\nEngine\tgodot\nIgdbId\t1\n//Game\tGame 2\nEngine\tfna";
+//! let report = Parser::new(ParsingMode::Recovery).load_report_from_string(data);
+//! assert_eq!(report.get_games()[0].engine, Some("godot".into()));
+//! assert_eq!(report.quarantined[0].game.name, "Game 2");
+//! assert_eq!(report.quarantined[0].start_line, 4);
+//! ```
 #[macro_use]
 pub(crate) mod parser_macros;
+pub mod schema;
+pub mod span;
+
+pub use schema::SchemaVersion;
+pub use span::{FieldSpan, GameSpan, ParserReport, QuarantinedRecord, UidCollision};
 
 use crate::models::field::Field;
+use crate::models::split_line::split_line;
 use crate::Game;
 
 use hash32::{FnvHasher, Hasher};
+use std::collections::HashMap;
 use std::fs;
 use std::hash::Hash;
 use std::path::Path;
@@ -97,7 +127,8 @@ enum ParserState {
     Error,
 }
 
-/// Represents the two parsing modes supported by [`Parser`].
+/// Represents the parsing modes supported by [`Parser`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParsingMode {
     /// In **strict mode**, the parsing will stop if a parsing error occurs
     /// returning the games processed before the error as well as the line
@@ -107,6 +138,15 @@ pub enum ParsingMode {
     /// an error, and returning all the games that have been parsed as well as
     /// the line numbers that were ignored due to parsing errors.
     Relaxed,
+    /// In **recovery mode**, the parsing continues as in relaxed mode but
+    /// the parser also detects the start of a record whose `Game` line is
+    /// broken: a `Cover`, or a field following unknown lines, coming back
+    /// in the record order (e.g. after the `IgdbId` of the previous game)
+    /// starts a new record. Such a record is quarantined until the next
+    /// `Game` line and returned separately (see [`ParserReport::quarantined`])
+    /// instead of overwriting the fields of the previous game. Other fields
+    /// out of order are kept on the current game and reported as errors.
+    Recovery,
 }
 
 /// Represents the result of the parsing. When in strict mode,
@@ -137,9 +177,12 @@ impl From<ParserResult> for Vec<Game> {
 pub struct Parser {
     state: ParserState,
     games: Vec<Game>,
+    spans: Vec<GameSpan>,
     current_line: usize,
     error_lines: Vec<usize>,
     mode: ParsingMode,
+    extension_keys: Vec<String>,
+    schema: Option<SchemaVersion>,
 }
 
 impl Default for Parser {
@@ -147,9 +190,12 @@ impl Default for Parser {
         Self {
             state: ParserState::Parsing,
             games: Vec::new(),
+            spans: Vec::new(),
             current_line: 0,
             error_lines: Vec::new(),
             mode: ParsingMode::Relaxed,
+            extension_keys: Vec::new(),
+            schema: None,
         }
     }
 }
@@ -159,11 +205,38 @@ impl Parser {
         Self {
             state: ParserState::Parsing,
             games: Vec::new(),
+            spans: Vec::new(),
             current_line: 0,
             error_lines: Vec::new(),
             mode,
+            extension_keys: Vec::new(),
+            schema: None,
         }
     }
+    /// Sets the keys of the extension fields (e.g. `Controller`). Their
+    /// lines are kept in [`Game::extra`] like the ones of the other
+    /// unknown keys, but are not reported as parsing errors.
+    ///
+    /// Outside of the strict mode, the lines of the other unknown keys are
+    /// only kept if their key looks like a field key (a capitalized word
+    /// such as `License`) or if they are found before the last field of
+    /// the record; the other ones (e.g. a commented out `//Game` line) are
+    /// only reported as errors.
+    pub fn with_extension_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extension_keys = keys.into_iter().map(|key| key.into()).collect();
+        self
+    }
+    /// Declares the layout of the database (see [`SchemaVersion`]) instead
+    /// of detecting it: the lines of the fields that are not part of the
+    /// layout are handled as unknown fields.
+    pub fn with_schema(mut self, schema: SchemaVersion) -> Self {
+        self.schema = Some(schema);
+        self
+    }
     /// Load the PlayOnBSD database from a file.
     pub fn load_from_file(self, file: impl AsRef<Path>) -> Result<ParserResult, std::io::Error> {
         let file: &Path = file.as_ref();
@@ -177,11 +250,39 @@ impl Parser {
             ))
         }
     }
-    /// Load the database from a [`&str`].
-    pub fn load_from_string(mut self, data: &str) -> ParserResult {
+    /// Load the database from a [`&str`]. The `Game` lines of the games
+    /// sharing the uid of a previous game are reported as errors (see
+    /// [`ParserReport::collisions`]).
+    pub fn load_from_string(self, data: &str) -> ParserResult {
+        self.load_report_from_string(data).result
+    }
+    /// Load the PlayOnBSD database from a file, recording the lines
+    /// of the games and of their fields (see [`ParserReport`]).
+    pub fn load_report_from_file(
+        self,
+        file: impl AsRef<Path>,
+    ) -> Result<ParserReport, std::io::Error> {
+        let file: &Path = file.as_ref();
+        if file.is_file() {
+            let data = fs::read_to_string(file)?;
+            Ok(self.load_report_from_string(&data))
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "This is not a file",
+            ))
+        }
+    }
+    /// Load the database from a [`&str`], recording the lines of the
+    /// games and of their fields (see [`ParserReport`]).
+    pub fn load_report_from_string(mut self, data: &str) -> ParserReport {
+        if let ParsingMode::Recovery = self.mode {
+            return self.load_report_with_recovery(data);
+        }
+        let mut last_line = 0;
         for line in data.lines() {
             self.current_line += 1;
-            self.parse(line);
+            self.parse(self.read_field(line));
             if let ParserState::Error = self.state {
                 self.error_lines.push(self.current_line);
                 if let ParsingMode::Strict = self.mode {
@@ -189,22 +290,210 @@ impl Parser {
                 }
                 self.state = ParserState::Parsing;
             };
+            last_line = self.current_line;
         }
-        for game in &mut self.games {
-            let mut fnv = FnvHasher::default();
-            // This is ugly but for compatibility
-            // uid should not change while updating
-            // libpobsd
-            let added = game.added.format("%Y-%m-%d").to_string();
-            Some(added).hash(&mut fnv);
-            game.name.hash(&mut fnv);
-            game.uid = fnv.finish32();
+        if let Some(span) = self.spans.last_mut() {
+            span.end_line = last_line;
         }
-        match self.error_lines.is_empty() {
+        self.into_report(Vec::new())
+    }
+    fn load_report_with_recovery(mut self, data: &str) -> ParserReport {
+        let mut quarantined: Vec<QuarantinedRecord> = Vec::new();
+        let mut quarantine: Option<QuarantinedRecord> = None;
+        // unknown lines not yet attributed to a record
+        let mut pending: Vec<(usize, &str)> = Vec::new();
+        // position of the last field of the current game
+        let mut last_position: Option<usize> = None;
+        for line in data.lines() {
+            self.current_line += 1;
+            let field = self.read_field(line);
+            let extension = self.is_extension(&field);
+            match (field.position(), &mut quarantine) {
+                (Some(0), _) => {
+                    quarantined.extend(quarantine.take());
+                    self.flush_pending(&mut pending);
+                    last_position = Some(0);
+                    self.parse(field);
+                }
+                (None, Some(record)) => {
+                    if !extension {
+                        self.error_lines.push(self.current_line);
+                    }
+                    Self::quarantine_line(record, self.current_line, line);
+                }
+                (None, None) if extension => {
+                    self.flush_pending(&mut pending);
+                    self.parse(field);
+                }
+                (None, None) => {
+                    self.error_lines.push(self.current_line);
+                    pending.push((self.current_line, line));
+                    if let Some(span) = self.spans.last_mut() {
+                        span.end_line = self.current_line;
+                    }
+                }
+                (Some(_), Some(record)) => {
+                    Self::quarantine_line(record, self.current_line, line);
+                }
+                (Some(position), None)
+                    if last_position.map_or(true, |last| {
+                        position < last && (position == 1 || !pending.is_empty())
+                    }) =>
+                {
+                    // a Cover or a field following unknown lines coming
+                    // back in the record order starts a new record whose
+                    // Game line is broken
+                    let mut record = QuarantinedRecord::default();
+                    for (number, pending_line) in pending.drain(..) {
+                        Self::quarantine_line(&mut record, number, pending_line);
+                    }
+                    Self::quarantine_line(&mut record, self.current_line, line);
+                    if let Some(span) = self.spans.last_mut() {
+                        span.end_line = record.start_line - 1;
+                    }
+                    quarantine = Some(record);
+                }
+                (Some(position), None) if last_position.map_or(false, |last| position < last) => {
+                    // other fields out of order are kept on the current game
+                    self.error_lines.push(self.current_line);
+                    last_position = Some(position);
+                    self.parse(field);
+                }
+                (position, None) => {
+                    self.flush_pending(&mut pending);
+                    last_position = position;
+                    self.parse(field);
+                }
+            }
+            if let ParserState::Error = self.state {
+                self.error_lines.push(self.current_line);
+                self.state = ParserState::Parsing;
+            }
+        }
+        self.flush_pending(&mut pending);
+        quarantined.extend(quarantine);
+        for record in quarantined.iter_mut() {
+            record.game.uid = game_uid(&record.game);
+        }
+        self.into_report(quarantined)
+    }
+    // attributes the unknown lines kept aside to the current game
+    fn flush_pending(&mut self, pending: &mut Vec<(usize, &str)>) {
+        for (_, line) in pending.drain(..) {
+            let field = self.read_field(line);
+            if !self.keeps_unknown(&field) {
+                continue;
+            }
+            if let Some(game) = self.games.last_mut() {
+                Self::set_field(game, field);
+            }
+        }
+    }
+    fn quarantine_line(record: &mut QuarantinedRecord, number: usize, line: &str) {
+        if record.lines.is_empty() {
+            record.start_line = number;
+        }
+        record.end_line = number;
+        record.lines.push(line.into());
+        // recover the name from a corrupted Game key (e.g. //Game)
+        if let (Some(key), Some(name)) = split_line(line) {
+            if key.ends_with("Game") && record.game.name.is_empty() {
+                record.game.name = name.into();
+            }
+        }
+        Self::set_field(&mut record.game, Field::from(line));
+    }
+    fn read_field(&self, line: &str) -> Field {
+        let field = Field::from(line);
+        match self.schema {
+            // the Id field can be used with any layout
+            Some(schema)
+                if field.position().is_some()
+                    && !matches!(field, Field::Id(_))
+                    && !schema.has_field(field.field_name()) =>
+            {
+                let (key, value) = split_line(line);
+                Field::Unknown(key.map(|key| key.into()), value.map(|value| value.into()))
+            }
+            _ => field,
+        }
+    }
+    // Returns true if the unknown field belongs to the current game: its
+    // key looks like a field key or the record is not over yet
+    fn keeps_unknown(&self, field: &Field) -> bool {
+        let last_key = self
+            .schema
+            .unwrap_or(SchemaVersion::LATEST)
+            .fields()
+            .last()
+            .copied();
+        let record_open = self
+            .spans
+            .last()
+            .and_then(|span| span.fields.last())
+            .map_or(true, |field| {
+                Some(field.key) != last_key && field.key != Field::Id(None).field_name()
+            });
+        match field {
+            Field::Unknown(Some(key), _) => record_open || is_field_key(key),
+            _ => false,
+        }
+    }
+    fn is_extension(&self, field: &Field) -> bool {
+        matches!(field, Field::Unknown(Some(key), _) if self.extension_keys.contains(key))
+    }
+    fn into_report(mut self, quarantined: Vec<QuarantinedRecord>) -> ParserReport {
+        for (game, span) in self.games.iter_mut().zip(self.spans.iter_mut()) {
+            game.uid = game_uid(game);
+            span.uid = game.uid;
+        }
+        let collisions = self.find_collisions();
+        // the Game lines of the games colliding with a previous one are
+        // reported as errors
+        for collision in &collisions {
+            self.error_lines.extend(&collision.lines[1..]);
+        }
+        self.error_lines.sort_unstable();
+        self.error_lines.dedup();
+        let result = match self.error_lines.is_empty() {
             false => ParserResult::WithError(self.games, self.error_lines),
             true => ParserResult::WithoutError(self.games),
+        };
+        let schema = self.schema.unwrap_or_else(|| {
+            SchemaVersion::detect(
+                self.spans
+                    .iter()
+                    .flat_map(|span| span.fields.iter().map(|field| field.key)),
+            )
+        });
+        ParserReport {
+            result,
+            spans: self.spans,
+            quarantined,
+            schema,
+            collisions,
         }
     }
+    fn find_collisions(&self) -> Vec<UidCollision> {
+        let mut games: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (index, game) in self.games.iter().enumerate() {
+            games.entry(game.uid).or_default().push(index);
+        }
+        let mut collisions: Vec<UidCollision> = games
+            .into_iter()
+            .filter(|(_, games)| games.len() > 1)
+            .map(|(uid, games)| UidCollision {
+                uid,
+                lines: games
+                    .iter()
+                    .map(|index| self.spans[*index].start_line)
+                    .collect(),
+                games,
+            })
+            .collect();
+        collisions.sort_by_key(|collision| collision.games[0]);
+        collisions
+    }
     impl_parse![Field::Game, name;
          (Field::Cover, cover);
          (Field::Engine, engine);
@@ -221,9 +510,36 @@ impl Parser {
          (Field::Status, status);
          (Field::Added, added);
          (Field::Updated, updated);
-         (Field::IgdbId, igdb_id)
+         (Field::IgdbId, igdb_id);
+         (Field::Id, id)
     ];
 }
+pub(crate) fn game_uid(game: &Game) -> u32 {
+    if let Some(id) = game.id {
+        return id;
+    }
+    let mut fnv = FnvHasher::default();
+    // This is ugly but for compatibility
+    // uid should not change while updating
+    // libpobsd
+    let added = game
+        .added
+        .get_date_or_epoch()
+        .format("%Y-%m-%d")
+        .to_string();
+    Some(added).hash(&mut fnv);
+    game.name.hash(&mut fnv);
+    fnv.finish32()
+}
+
+// Returns true if the key is shaped like the key of a field, i.e. a
+// capitalized word (e.g. "Controller")
+fn is_field_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().map_or(false, |c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 #[cfg(test)]
 mod game_tests {
     use super::*;
@@ -1,26 +1,76 @@
 macro_rules! impl_parse {
     ($firstfield:path, $firstsetter:ident;
         $(($field:path, $setter:ident));+) => {
-        fn parse(&mut self, line: &str) {
-            let field = Field::from(line);
-            // If the parser is in Error state, it tries to
-            // recover on new games
+        pub(crate) fn set_field(game: &mut Game, field: Field) {
             match field {
                 $firstfield(name) => {
-                    let mut game = Game::default();
                     if let Some(name) = name {
-                        game.$firstsetter= name.into();
+                        game.$firstsetter = name.into();
                     };
-                    self.games.push(game);
                 },
             $(
-                $field(name) => {
-                    if let Some(game)  = self.games.last_mut() {
-                        game.$setter = name;
+                $field(name) => game.$setter = name,
+            )*
+                Field::Unknown(Some(key), value) => game.set_extra(key, value),
+                Field::Unknown(None, _) => {},
+            }
+        }
+        fn parse(&mut self, field: Field) {
+            let key = field.field_name();
+            // If the parser is in Error state, it tries to
+            // recover on new games
+            match field {
+                $firstfield(_) => {
+                    let mut game = Game::default();
+                    Self::set_field(&mut game, field);
+                    self.games.push(game);
+                    self.spans.push(GameSpan {
+                        start_line: self.current_line,
+                        end_line: self.current_line,
+                        fields: vec![FieldSpan {
+                            key,
+                            line: self.current_line,
+                        }],
+                        ..Default::default()
+                    });
+                },
+                Field::Unknown(..) if self.is_extension(&field) => {
+                    if let Some(game) = self.games.last_mut() {
+                        Self::set_field(game, field);
+                    }
+                    if let Some(span) = self.spans.last_mut() {
+                        span.end_line = self.current_line;
+                    }
+                },
+                Field::Unknown(..) => {
+                    self.state = ParserState::Error;
+                    // unknown fields are kept unless the parsing stops or
+                    // they look like corrupted lines
+                    if self.mode != ParsingMode::Strict && self.keeps_unknown(&field) {
+                        if let Some(game) = self.games.last_mut() {
+                            Self::set_field(game, field);
+                        }
+                        if let Some(span) = self.spans.last_mut() {
+                            span.end_line = self.current_line;
+                        }
+                    }
+                },
+                field => {
+                    // malformed values are kept but reported
+                    if field.is_malformed() {
+                        self.state = ParserState::Error;
+                    }
+                    if let Some(game) = self.games.last_mut() {
+                        Self::set_field(game, field);
+                    }
+                    if let Some(span) = self.spans.last_mut() {
+                        span.end_line = self.current_line;
+                        span.fields.push(FieldSpan {
+                            key,
+                            line: self.current_line,
+                        });
                     }
                 },
-            )*
-                Field::Unknown(_) => self.state = ParserState::Error,
             }
         }
     }
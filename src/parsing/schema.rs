@@ -0,0 +1,203 @@
+//! Provides a [`SchemaVersion`] representing the successive layouts of the
+//! PlayOnBSD database and a [`migrate_games`] function upgrading the games
+//! parsed from an older layout to the latest one.
+//!
+//! ## Examples
+//! ```
+//! use chrono::NaiveDate;
+//! use libpobsd::parsing::schema::{migrate_games, SchemaVersion};
+//! use libpobsd::Parser;
+//!
+//! // a record predating the Added, Updated and IgdbId fields
+//! let report = Parser::default().load_report_from_string("Game\tMy game\nEngine\tgodot");
+//! assert_eq!(report.schema, SchemaVersion::V1);
+//! let date = NaiveDate::from_ymd_opt(2020, 1, 1);
+//! let migration = migrate_games(report.get_games().to_vec(), report.schema, date);
+//! assert_eq!(migration.games[0].added, date.unwrap());
+//! assert_eq!(migration.synthesized.len(), 3);
+//! ```
+use crate::parsing::game_uid;
+use crate::Game;
+
+use chrono::NaiveDate;
+
+const FIELD_KEYS: [&str; 17] = [
+    "Game", "Cover", "Engine", "Setup", "Runtime", "Store", "Hints", "Genre", "Tags", "Year",
+    "Dev", "Pub", "Version", "Status", "Added", "Updated", "IgdbId",
+];
+
+/// Represents a layout of the PlayOnBSD database, each version adding
+/// fields at the end of the records of the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaVersion {
+    /// Records of 14 fields, from `Game` to `Status`.
+    V1,
+    /// Records of 16 fields, adding `Added` and `Updated`.
+    V2,
+    /// Records of 17 fields, adding `IgdbId`.
+    V3,
+}
+
+impl SchemaVersion {
+    /// Latest layout of the database.
+    pub const LATEST: SchemaVersion = SchemaVersion::V3;
+
+    /// Returns the keys of the fields of a record, in order.
+    pub fn fields(&self) -> &'static [&'static str] {
+        match self {
+            SchemaVersion::V1 => &FIELD_KEYS[..14],
+            SchemaVersion::V2 => &FIELD_KEYS[..16],
+            SchemaVersion::V3 => &FIELD_KEYS,
+        }
+    }
+    /// Returns true if the given key (e.g. `Added`) is a field of the layout.
+    pub fn has_field(&self, key: &str) -> bool {
+        self.fields().contains(&key)
+    }
+    /// Returns the first layout having the given field key, None if the
+    /// key is not a field of the latest layout.
+    pub fn introducing(key: &str) -> Option<SchemaVersion> {
+        [SchemaVersion::V1, SchemaVersion::V2, SchemaVersion::V3]
+            .into_iter()
+            .find(|version| version.has_field(key))
+    }
+    /// Returns the oldest layout having all the given field keys, the
+    /// keys not being fields of the latest layout being ignored.
+    pub fn detect<'a>(keys: impl IntoIterator<Item = &'a str>) -> SchemaVersion {
+        keys.into_iter()
+            .filter_map(SchemaVersion::introducing)
+            .max()
+            .unwrap_or(SchemaVersion::V1)
+    }
+}
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        SchemaVersion::LATEST
+    }
+}
+
+/// Field of a game filled in by [`migrate_games`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynthesizedField {
+    /// Uid of the game after the migration.
+    pub uid: u32,
+    /// Key of the field (e.g. `Added`).
+    pub key: &'static str,
+    /// Value given to the field, None if the field was left empty.
+    pub value: Option<String>,
+}
+
+/// Result of [`migrate_games`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// Layout the games were migrated from.
+    pub from: SchemaVersion,
+    /// Layout the games were migrated to.
+    pub to: SchemaVersion,
+    /// Migrated games.
+    pub games: Vec<Game>,
+    /// Fields filled in during the migration.
+    pub synthesized: Vec<SynthesizedField>,
+}
+
+impl Migration {
+    /// Returns the fields filled in for the game with the given uid.
+    pub fn get_synthesized(&self, game_id: u32) -> Vec<&SynthesizedField> {
+        self.synthesized
+            .iter()
+            .filter(|field| field.uid == game_id)
+            .collect()
+    }
+}
+
+/// Upgrades games parsed from the given layout to the latest one.
+///
+/// The `Added` and `Updated` fields missing from [`SchemaVersion::V1`]
+/// records are set to the given date (e.g. the date of the database
+/// release the games come from) or to the default date (1970-01-01),
+/// the uids being computed again since they depend on `Added`. The
+/// `IgdbId` field missing from older records is left empty, the IGDB
+/// ids already set being kept. Every filled in field is recorded in
+/// [`Migration::synthesized`].
+pub fn migrate_games(
+    mut games: Vec<Game>,
+    from: SchemaVersion,
+    date: Option<NaiveDate>,
+) -> Migration {
+    let mut synthesized = Vec::new();
+    let date = date.unwrap_or_default();
+    for game in games.iter_mut() {
+        let mut fields: Vec<(&'static str, Option<String>)> = Vec::new();
+        if !from.has_field("Added") {
+            game.added = date.into();
+            game.updated = date.into();
+            let value = date.format("%Y-%m-%d").to_string();
+            fields.push(("Added", Some(value.clone())));
+            fields.push(("Updated", Some(value)));
+            game.uid = game_uid(game);
+        }
+        if !from.has_field("IgdbId") && game.igdb_id.is_none() {
+            fields.push(("IgdbId", None));
+        }
+        synthesized.extend(fields.into_iter().map(|(key, value)| SynthesizedField {
+            uid: game.uid,
+            key,
+            value,
+        }));
+    }
+    Migration {
+        from,
+        to: SchemaVersion::LATEST,
+        games,
+        synthesized,
+    }
+}
+
+#[cfg(test)]
+mod schema_tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(SchemaVersion::V1.fields().len(), 14);
+        assert_eq!(SchemaVersion::V1.fields().last(), Some(&"Status"));
+        assert_eq!(SchemaVersion::V2.fields().last(), Some(&"Updated"));
+        assert_eq!(SchemaVersion::LATEST.fields().len(), 17);
+    }
+    #[test]
+    fn test_detect() {
+        assert_eq!(SchemaVersion::detect(["Game", "Cover"]), SchemaVersion::V1);
+        assert_eq!(
+            SchemaVersion::detect(["Game", "Updated", "Controller"]),
+            SchemaVersion::V2
+        );
+        assert_eq!(SchemaVersion::detect(["IgdbId"]), SchemaVersion::V3);
+        assert_eq!(SchemaVersion::introducing("Controller"), None);
+    }
+    #[test]
+    fn test_migrate_games_from_v2() {
+        let mut game = Game::new();
+        game.name = "Game 1".into();
+        game.uid = 12;
+        let mut game2 = game.clone();
+        game2.uid = 13;
+        game2.igdb_id = Some(1);
+        let migration = migrate_games(vec![game, game2], SchemaVersion::V2, None);
+        assert_eq!(migration.games[0].uid, 12);
+        assert_eq!(migration.games[0].igdb_id, None);
+        // the IGDB ids already set are kept
+        assert_eq!(migration.games[1].igdb_id, Some(1));
+        assert!(migration.get_synthesized(13).is_empty());
+        assert_eq!(
+            migration.get_synthesized(12),
+            vec![&SynthesizedField {
+                uid: 12,
+                key: "IgdbId",
+                value: None
+            }]
+        );
+        let migration = migrate_games(migration.games, SchemaVersion::V3, None);
+        assert!(migration.synthesized.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+//! Provides a [`GameSpan`] recording where a [`crate::Game`] comes from
+//! in the parsed database and a [`ParserReport`] holding the
+//! [`ParserResult`] along with the spans of the games and, in recovery
+//! mode, the [`QuarantinedRecord`]s.
+use crate::parsing::{ParserResult, SchemaVersion};
+use crate::Game;
+
+/// Line of a field of a game in the parsed database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpan {
+    /// Key of the field as written in the database (e.g. `Pub`).
+    pub key: &'static str,
+    /// Line of the field (starting at 1).
+    pub line: usize,
+}
+
+/// Lines of a game and of its fields in the parsed database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameSpan {
+    /// Uid of the game.
+    pub uid: u32,
+    /// Line of the `Game` field (starting at 1).
+    pub start_line: usize,
+    /// Last line of the game, before the next `Game` field.
+    pub end_line: usize,
+    /// Lines of the fields of the game, in the order they appear.
+    pub fields: Vec<FieldSpan>,
+}
+
+impl GameSpan {
+    /// Returns the line of the given field (e.g. `Pub`). When the field
+    /// is repeated, the line of the last one (whose value is kept by
+    /// the parser) is returned.
+    pub fn get_field_line(&self, key: &str) -> Option<usize> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|field| field.key == key)
+            .map(|field| field.line)
+    }
+    /// Returns true if the given line belongs to the game.
+    pub fn contains_line(&self, line: usize) -> bool {
+        self.start_line <= line && line <= self.end_line
+    }
+    /// Returns the lines of the game in the given text, the text being
+    /// the one that was parsed.
+    pub fn get_text<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.lines()
+            .skip(self.start_line.saturating_sub(1))
+            .take(self.end_line + 1 - self.start_line)
+            .collect()
+    }
+}
+
+/// Broken record set aside by the parser in recovery mode (see
+/// [`crate::ParsingMode::Recovery`]) instead of being merged into
+/// the previous game.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuarantinedRecord {
+    /// First line of the record (starting at 1).
+    pub start_line: usize,
+    /// Last line of the record, before the next `Game` field.
+    pub end_line: usize,
+    /// Lines of the record as written in the database.
+    pub lines: Vec<String>,
+    /// Game partially parsed from the record. Its name is recovered
+    /// from a corrupted `Game` key (e.g. `//Game`) when possible.
+    pub game: Game,
+}
+
+/// Games sharing the same uid, either because they have the same name
+/// and `Added` date, the same explicit `Id` or because of a collision
+/// of the hash generating the uid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UidCollision {
+    /// The shared uid.
+    pub uid: u32,
+    /// Indices of the games in the parsed games.
+    pub games: Vec<usize>,
+    /// Lines of the `Game` fields of the games.
+    pub lines: Vec<usize>,
+}
+
+/// Result of the parsing along with the spans of the parsed games, the
+/// span at a given index being the one of the game at the same index.
+pub struct ParserReport {
+    /// Result of the parsing.
+    pub result: ParserResult,
+    /// Spans of the parsed games.
+    pub spans: Vec<GameSpan>,
+    /// Broken records set aside in recovery mode, empty otherwise.
+    pub quarantined: Vec<QuarantinedRecord>,
+    /// Layout of the database, either declared (see
+    /// [`crate::Parser::with_schema`]) or detected from the fields.
+    pub schema: SchemaVersion,
+    /// Games sharing the same uid, the lines of the `Game` fields of the
+    /// games after the first one being reported as errors.
+    pub collisions: Vec<UidCollision>,
+}
+
+impl ParserReport {
+    /// Returns the parsed games.
+    pub fn get_games(&self) -> &[Game] {
+        match &self.result {
+            ParserResult::WithError(games, _) | ParserResult::WithoutError(games) => games,
+        }
+    }
+    /// Returns the span of the game with the given uid.
+    pub fn get_span(&self, game_id: u32) -> Option<&GameSpan> {
+        self.spans.iter().find(|span| span.uid == game_id)
+    }
+    /// Returns the span of the game the given line belongs to.
+    pub fn get_span_at_line(&self, line: usize) -> Option<&GameSpan> {
+        self.spans.iter().find(|span| span.contains_line(line))
+    }
+    /// Returns the quarantined record the given line belongs to.
+    pub fn get_quarantined_at_line(&self, line: usize) -> Option<&QuarantinedRecord> {
+        self.quarantined
+            .iter()
+            .find(|record| record.start_line <= line && line <= record.end_line)
+    }
+    /// Returns the games along with their spans.
+    pub fn into_games_with_spans(self) -> Vec<(Game, GameSpan)> {
+        let games: Vec<Game> = self.result.into();
+        games.into_iter().zip(self.spans).collect()
+    }
+}
+
+impl From<ParserReport> for ParserResult {
+    fn from(report: ParserReport) -> Self {
+        report.result
+    }
+}
+
+#[cfg(test)]
+mod span_tests {
+    use super::*;
+
+    fn create_span() -> GameSpan {
+        GameSpan {
+            uid: 1,
+            start_line: 2,
+            end_line: 4,
+            fields: vec![
+                FieldSpan {
+                    key: "Engine",
+                    line: 3,
+                },
+                FieldSpan {
+                    key: "Engine",
+                    line: 4,
+                },
+            ],
+        }
+    }
+    #[test]
+    fn test_get_field_line() {
+        let span = create_span();
+        assert_eq!(span.get_field_line("Engine"), Some(4));
+        assert_eq!(span.get_field_line("Pub"), None);
+    }
+    #[test]
+    fn test_get_text() {
+        let span = create_span();
+        let text = "Game\tA\nGame\tB\nEngine\tgodot\nEngine\tfna\nGame\tC";
+        assert_eq!(
+            span.get_text(text),
+            vec!["Game\tB", "Engine\tgodot", "Engine\tfna"]
+        );
+        assert!(span.contains_line(4));
+        assert!(!span.contains_line(5));
+    }
+}
@@ -0,0 +1,466 @@
+//! Provides a JSON API over HTTP exposing the [`GameDataBase`] (requires
+//! the `server` feature). The API is served by the `pobsd-server` binary.
+//!
+//! All the endpoints answer `GET` requests with JSON. The lists of
+//! games or items are paginated using the `page` (starting at 1) and
+//! `per_page` (50 by default, at most 500) parameters, and are returned
+//! as `{"page": 1, "per_page": 50, "total": 120, "pages": 3, "items": [...]}`.
+//! The searches are not case sensitive unless `case=sensitive` is given.
+//! The patterns are searched within the values unless `match` is given,
+//! `exact`, `prefix`, `word` or `regex` comparing them to the whole values,
+//! their beginning, their words or using them as regular expressions
+//! (see [`SearchType`]). An unknown `match` value or an invalid regular
+//! expression is answered with a 400 error.
+//! Errors are returned as `{"error": "..."}` with the matching status code.
+//!
+//! | Endpoint | Description |
+//! |----------|-------------|
+//! | `/games` | all the games |
+//! | `/games/<uid>` | game with the given uid |
+//! | `/games/name/<name>` | game with the given name |
+//! | `/games/steam/<steam id>` | game with the given Steam app id |
+//! | `/search/<field>?q=<pattern>` | games which field contains the pattern, the field being one of `name`, `engine`, `runtime`, `genre`, `tag`, `year`, `dev` or `publi` |
+//! | `/filter?<field>=<pattern>&...` | games matching at least one of the given fields (see [`crate::GameFilter`]), `status` included |
+//! | `/items/<field>` | values of the field with the uids of the associated games, the field being one of `engines`, `runtimes`, `genres`, `tags`, `years`, `devs` or `publis` |
+//! | `/stats` | number of games, of values of each field and of games per status |
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::server::{handle_request, serve};
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let response = handle_request(&db, "/search/engine?q=godot&per_page=10");
+//! assert_eq!(response.status, 200);
+//! serve(&db, "127.0.0.1:8080").expect("Could not start the server");
+//! ```
+use crate::{Game, GameDataBase, GameFilter, SearchType, Status};
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error, ErrorKind};
+
+/// Address the server is bound to unless set otherwise.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+/// Number of items per page unless set otherwise.
+pub const DEFAULT_PER_PAGE: usize = 50;
+/// Maximal number of items per page.
+pub const MAX_PER_PAGE: usize = 500;
+
+/// Response to a request to the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// JSON body.
+    pub body: String,
+}
+
+impl ApiResponse {
+    fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Self { status: 200, body },
+            Err(e) => Self::error(500, &e.to_string()),
+        }
+    }
+    fn error(status: u16, message: &str) -> Self {
+        let mut error = BTreeMap::new();
+        error.insert("error", message);
+        Self {
+            status,
+            body: serde_json::to_string(&error).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Page<T> {
+    page: usize,
+    per_page: usize,
+    total: usize,
+    pages: usize,
+    items: Vec<T>,
+}
+
+#[derive(Serialize)]
+struct ItemWithIds {
+    name: String,
+    ids: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    games: usize,
+    engines: usize,
+    runtimes: usize,
+    genres: usize,
+    tags: usize,
+    years: usize,
+    devs: usize,
+    publis: usize,
+    status: BTreeMap<String, usize>,
+}
+
+// Decodes a percent-encoded component of the query string, `+`
+// standing for a space
+fn percent_decode(value: &str) -> String {
+    decode(value, true)
+}
+
+// Decodes a percent-encoded segment of the path, `+` being kept
+fn path_decode(value: &str) -> String {
+    decode(value, false)
+}
+
+fn decode(value: &str, plus_as_space: bool) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let byte = std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match byte {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Parses the query string of an url
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+// Returns the status matching its name (see the Display of Status)
+fn parse_status(name: &str) -> Option<Status> {
+    let status = match name.to_lowercase().as_str() {
+        "unknown" => Status::Unknown,
+        "doesnotrun" => Status::DoesNotRun,
+        "launches" => Status::Launches,
+        "majorbugs" => Status::MajorBugs,
+        "mediumimpact" => Status::MediumImpact,
+        "minorbugs" => Status::MinorBugs,
+        "completable" => Status::Completable,
+        "perfect" => Status::Perfect,
+        _ => return None,
+    };
+    Some(status)
+}
+
+// Returns the requested page of the items
+fn paginate<T: Serialize>(items: Vec<T>, params: &HashMap<String, String>) -> ApiResponse {
+    let page = match params.get("page").map(|p| p.parse::<usize>()) {
+        None => 1,
+        Some(Ok(page)) if page > 0 => page,
+        Some(_) => return ApiResponse::error(400, "invalid page"),
+    };
+    let per_page = match params.get("per_page").map(|p| p.parse::<usize>()) {
+        None => DEFAULT_PER_PAGE,
+        Some(Ok(per_page)) if per_page > 0 => per_page.min(MAX_PER_PAGE),
+        Some(_) => return ApiResponse::error(400, "invalid per_page"),
+    };
+    let total = items.len();
+    let items: Vec<T> = items
+        .into_iter()
+        .skip((page - 1).saturating_mul(per_page))
+        .take(per_page)
+        .collect();
+    ApiResponse::json(&Page {
+        page,
+        per_page,
+        total,
+        pages: (total + per_page - 1) / per_page,
+        items,
+    })
+}
+
+fn game_response(game: Option<&Game>) -> ApiResponse {
+    match game {
+        Some(game) => ApiResponse::json(game),
+        None => ApiResponse::error(404, "game not found"),
+    }
+}
+
+fn search<'a>(
+    db: &'a GameDataBase,
+    field: &str,
+    pattern: &str,
+    search_type: &SearchType,
+) -> Option<Vec<&'a Game>> {
+    let games = match field {
+        "name" => db.search_games_by_name(pattern, search_type),
+        "engine" => db.search_games_by_engine(pattern, search_type),
+        "runtime" => db.search_games_by_runtime(pattern, search_type),
+        "genre" => db.search_games_by_genre(pattern, search_type),
+        "tag" => db.search_games_by_tag(pattern, search_type),
+        "year" => db.search_games_by_year(pattern, search_type),
+        "dev" => db.search_games_by_dev(pattern, search_type),
+        "publi" => db.search_games_by_publi(pattern, search_type),
+        _ => return None,
+    };
+    Some(games.into_inner())
+}
+
+fn items_with_ids(db: &GameDataBase, field: &str) -> Option<Vec<(String, Vec<u32>)>> {
+    let items = match field {
+        "engines" => db.get_all_engines_with_ids(),
+        "runtimes" => db.get_all_runtimes_with_ids(),
+        "genres" => db.get_all_genres_with_ids(),
+        "tags" => db.get_all_tags_with_ids(),
+        "years" => db.get_all_years_with_ids(),
+        "devs" => db.get_all_devs_with_ids(),
+        "publis" => db.get_all_publis_with_ids(),
+        _ => return None,
+    };
+    Some(items)
+}
+
+fn filter(
+    db: &GameDataBase,
+    params: &HashMap<String, String>,
+    search_type: &SearchType,
+) -> ApiResponse {
+    let mut filter = GameFilter::default();
+    for (key, value) in params {
+        let is_pattern = matches!(
+            key.as_str(),
+            "name" | "engine" | "runtime" | "genre" | "tag" | "year" | "dev" | "publi"
+        );
+        if is_pattern && search_type.validate(value).is_err() {
+            return ApiResponse::error(400, "invalid regex");
+        }
+        match key.as_str() {
+            "name" => filter.set_name(value),
+            "engine" => filter.set_engine(value),
+            "runtime" => filter.set_runtime(value),
+            "genre" => filter.set_genre(value),
+            "tag" => filter.set_tag(value),
+            "year" => filter.set_year(value),
+            "dev" => filter.set_dev(value),
+            "publi" => filter.set_publi(value),
+            "status" => match parse_status(value) {
+                Some(status) => filter.set_status(status),
+                None => return ApiResponse::error(400, "invalid status"),
+            },
+            _ => &mut filter,
+        };
+    }
+    if filter.is_empty() {
+        return ApiResponse::error(400, "no filter given");
+    }
+    paginate(
+        db.search_game_by_filter(search_type, &filter).into_inner(),
+        params,
+    )
+}
+
+fn stats(db: &GameDataBase) -> ApiResponse {
+    let games = db.get_all_games().into_inner();
+    let mut status: BTreeMap<String, usize> = BTreeMap::new();
+    for game in &games {
+        *status.entry(game.status.status.to_string()).or_default() += 1;
+    }
+    ApiResponse::json(&Stats {
+        games: games.len(),
+        engines: db.get_all_engines().count,
+        runtimes: db.get_all_runtimes().count,
+        genres: db.get_all_genres().count,
+        tags: db.get_all_tags().count,
+        years: db.get_all_years().count,
+        devs: db.get_all_devs().count,
+        publis: db.get_all_publis().count,
+        status,
+    })
+}
+
+/// Returns the response of the API to a `GET` request of the given url
+/// (path and query string, e.g. `/games?page=2`).
+pub fn handle_request(db: &GameDataBase, url: &str) -> ApiResponse {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = parse_query(query);
+    let case_sensitive = params.get("case").map_or(false, |case| case == "sensitive");
+    let search_type = match params.get("match").map(|mode| mode.as_str()) {
+        Some("exact") => SearchType::Exact { case_sensitive },
+        Some("prefix") => SearchType::Prefix { case_sensitive },
+        Some("word") => SearchType::WholeWord { case_sensitive },
+        Some("regex") => SearchType::Regex { case_sensitive },
+        Some(_) => return ApiResponse::error(400, "invalid match"),
+        None if case_sensitive => SearchType::CaseSensitive,
+        None => SearchType::NotCaseSensitive,
+    };
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(path_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(|segment| segment.as_str()).collect();
+    match segments.as_slice() {
+        ["games"] => paginate(db.get_all_games().into_inner(), &params),
+        ["games", "name", name] => match search_type.validate(name) {
+            Ok(()) => game_response(db.get_game_by_name(name, &search_type)),
+            Err(_) => ApiResponse::error(400, "invalid regex"),
+        },
+        ["games", "steam", id] => match id.parse::<usize>() {
+            Ok(id) => game_response(db.get_game_by_steam_id(id)),
+            Err(_) => ApiResponse::error(400, "invalid steam id"),
+        },
+        ["games", uid] => match uid.parse::<u32>() {
+            Ok(uid) => game_response(db.get_game_by_id(uid)),
+            Err(_) => ApiResponse::error(400, "invalid uid"),
+        },
+        ["search", field] => match params.get("q") {
+            Some(pattern) if search_type.validate(pattern).is_err() => {
+                ApiResponse::error(400, "invalid regex")
+            }
+            Some(pattern) => match search(db, field, pattern, &search_type) {
+                Some(games) => paginate(games, &params),
+                None => ApiResponse::error(404, "unknown field"),
+            },
+            None => ApiResponse::error(400, "missing q parameter"),
+        },
+        ["filter"] => filter(db, &params, &search_type),
+        ["items", field] => match items_with_ids(db, field) {
+            Some(items) => paginate(
+                items
+                    .into_iter()
+                    .map(|(name, ids)| ItemWithIds { name, ids })
+                    .collect(),
+                &params,
+            ),
+            None => ApiResponse::error(404, "unknown field"),
+        },
+        ["stats"] => stats(db),
+        _ => ApiResponse::error(404, "not found"),
+    }
+}
+
+/// Serves the API on the given address (e.g. `127.0.0.1:8080`) until
+/// the process is stopped. The errors occurring while responding to a
+/// client (e.g. when it hangs up) are written to the standard error.
+pub fn serve(db: &GameDataBase, address: &str) -> Result<(), Error> {
+    let server = tiny_http::Server::http(address)
+        .map_err(|e| Error::new(ErrorKind::AddrNotAvailable, e.to_string()))?;
+    let content_type =
+        tiny_http::Header::from_bytes("Content-Type", "application/json").expect("Invalid header");
+    for request in server.incoming_requests() {
+        let response = match request.method() {
+            tiny_http::Method::Get => handle_request(db, request.url()),
+            _ => ApiResponse::error(405, "method not allowed"),
+        };
+        let response = tiny_http::Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            eprintln!("Could not respond to the request: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("Game%20name+2"), "Game name 2");
+        assert_eq!(percent_decode("%C3%A9t%C3%A9"), "été");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(path_decode("C++%20Game"), "C++ Game");
+    }
+    #[test]
+    fn test_parse_query() {
+        let params = parse_query("q=the%20game&page=2&flag");
+        assert_eq!(params.get("q"), Some(&"the game".to_string()));
+        assert_eq!(params.get("page"), Some(&"2".to_string()));
+        assert_eq!(params.get("flag"), Some(&String::new()));
+    }
+    #[test]
+    fn test_paginate() {
+        let mut params = HashMap::new();
+        params.insert("page".to_string(), "2".to_string());
+        params.insert("per_page".to_string(), "2".to_string());
+        let response = paginate(vec![1, 2, 3, 4, 5], &params);
+        assert_eq!(
+            response.body,
+            "{\"page\":2,\"per_page\":2,\"total\":5,\"pages\":3,\"items\":[3,4]}"
+        );
+        params.insert("page".to_string(), "0".to_string());
+        assert_eq!(paginate(vec![1], &params).status, 400);
+    }
+    #[test]
+    fn test_not_found() {
+        let db = GameDataBase::default();
+        let response = handle_request(&db, "/unknown");
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body, "{\"error\":\"not found\"}");
+        assert_eq!(handle_request(&db, "/games/1").status, 404);
+        assert_eq!(handle_request(&db, "/games/abc").status, 400);
+        assert_eq!(handle_request(&db, "/search/unknown?q=a").status, 404);
+    }
+    #[test]
+    fn test_search_match_modes() {
+        let mut game1 = Game::new();
+        game1.uid = 1;
+        game1.name = "Super Game".into();
+        let mut game2 = Game::new();
+        game2.uid = 2;
+        game2.name = "Game Super".into();
+        let db = GameDataBase::new(vec![game1, game2]);
+        let total = |url: &str| handle_request(&db, url).body.contains("\"total\":1,");
+        assert!(!total("/search/name?q=super"));
+        assert!(total("/search/name?q=super&match=prefix"));
+        assert!(total("/search/name?q=super%24&match=regex"));
+        assert!(!total("/search/name?q=super&match=prefix&case=sensitive"));
+    }
+    #[test]
+    fn test_search_invalid_match() {
+        let db = GameDataBase::default();
+        let response = handle_request(&db, "/search/name?q=%28&match=regex");
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body, "{\"error\":\"invalid regex\"}");
+        assert_eq!(
+            handle_request(&db, "/filter?tag=%28&match=regex").status,
+            400
+        );
+        assert_eq!(
+            handle_request(&db, "/games/name/%28?match=regex").status,
+            400
+        );
+        assert_eq!(handle_request(&db, "/search/name?q=%28").status, 200);
+        let response = handle_request(&db, "/search/name?q=a&match=fuzzy");
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body, "{\"error\":\"invalid match\"}");
+    }
+    #[test]
+    fn test_game_name_with_plus() {
+        let mut game = Game::new();
+        game.uid = 1;
+        game.name = "C++".into();
+        let db = GameDataBase::new(vec![game]);
+        assert_eq!(handle_request(&db, "/games/name/C++").status, 200);
+        assert_eq!(handle_request(&db, "/games/name/C%2B%2B").status, 200);
+    }
+}
@@ -0,0 +1,588 @@
+//! Provides a [`SiteGenerator`] rendering the [`GameDataBase`] into a
+//! static website (requires the `site` feature).
+//!
+//! The website is made of:
+//! * an `index.html` page listing all the games,
+//! * one page per game in the `games` directory,
+//! * for each engine, genre, tag, developer, publisher, year and status,
+//!   an index page (e.g. `engines/index.html`) and one page listing the
+//!   games associated to each value (e.g. `engines/godot.html`),
+//! * a `search.json` file used by the client-side search of the
+//!   `index.html` page,
+//! * an `atom.xml` feed of the recently added or updated games when the
+//!   base url of the site is set (see [`crate::db::feed`]).
+//!
+//! The pages are rendered using [TinyTemplate](https://docs.rs/tinytemplate)
+//! templates. The default templates (`header`, `footer`, `home`, `game`,
+//! `index` and `category`) can be overridden one by one, either using
+//! [`SiteGenerator::set_template`] or by putting a `<name>.html` file in a
+//! directory given to [`SiteGenerator::load_templates_from_dir`].
+//!
+//! ## Examples
+//! ```no_run
+//! # use libpobsd::{Parser, GameDataBase, Game};
+//! use libpobsd::site::SiteGenerator;
+//! # let games: Vec<Game> = Parser::default()
+//! #            .load_from_file("openbsd-games.db")
+//! #            .expect("Failed to load database").into();
+//! let db = GameDataBase::new(games);
+//! let mut generator = SiteGenerator::new("PlayOnBSD", "public");
+//! generator
+//!        .set_base_url("https://playonbsd.com")
+//!        .load_templates_from_dir("templates")
+//!        .expect("Could not read the templates");
+//! let files = generator.generate(&db).expect("Could not write the site");
+//! ```
+use crate::db::feed::FeedConfig;
+use crate::slug::unique_slugs;
+use crate::{Game, GameDataBase};
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use tinytemplate::TinyTemplate;
+
+/// Names of the templates used to render the website.
+pub const TEMPLATE_NAMES: [&str; 6] = ["header", "footer", "home", "game", "index", "category"];
+
+const DEFAULT_TEMPLATES: [(&str, &str); 6] = [
+    ("header", include_str!("templates/header.html")),
+    ("footer", include_str!("templates/footer.html")),
+    ("home", include_str!("templates/home.html")),
+    ("game", include_str!("templates/game.html")),
+    ("index", include_str!("templates/index.html")),
+    ("category", include_str!("templates/category.html")),
+];
+
+// Directory and title of the sections of the website
+const SECTIONS: [(&str, &str); 7] = [
+    ("engines", "Engines"),
+    ("genres", "Genres"),
+    ("tags", "Tags"),
+    ("devs", "Developers"),
+    ("publis", "Publishers"),
+    ("years", "Years"),
+    ("status", "Status"),
+];
+
+#[derive(Serialize, Clone)]
+struct Link {
+    name: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct PageContext {
+    site_title: String,
+    title: String,
+    root: String,
+    canonical: Option<String>,
+    feed: Option<String>,
+    sections: Vec<Link>,
+}
+
+#[derive(Serialize)]
+struct GameItem {
+    name: String,
+    url: String,
+    year: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HomeContext {
+    page: PageContext,
+    count: usize,
+    games: Vec<GameItem>,
+}
+
+#[derive(Serialize)]
+struct GameContext<'a> {
+    page: PageContext,
+    name: &'a str,
+    cover: Option<&'a str>,
+    runtime: Option<&'a str>,
+    setup: Option<&'a str>,
+    hints: Option<&'a str>,
+    version: Option<&'a str>,
+    engine: Vec<Link>,
+    genres: Vec<Link>,
+    tags: Vec<Link>,
+    year: Vec<Link>,
+    devs: Vec<Link>,
+    publis: Vec<Link>,
+    status: Link,
+    status_message: Option<&'a str>,
+    stores: Vec<Link>,
+    added: String,
+    updated: String,
+}
+
+#[derive(Serialize)]
+struct IndexItem {
+    name: String,
+    url: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct IndexContext {
+    page: PageContext,
+    items: Vec<IndexItem>,
+}
+
+#[derive(Serialize)]
+struct CategoryContext {
+    page: PageContext,
+    section: String,
+    name: String,
+    games: Vec<GameItem>,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    name: &'a str,
+    url: String,
+    engine: Option<&'a str>,
+    year: Option<&'a str>,
+    genres: &'a [String],
+    tags: &'a [String],
+    devs: &'a [String],
+    publis: &'a [String],
+    status: String,
+}
+
+// Section of the website: its values, the page of each value and the
+// values associated to each game
+struct Section {
+    dir: &'static str,
+    title: &'static str,
+    items: Vec<(String, String, Vec<u32>)>,
+    by_game: HashMap<u32, Vec<(String, String)>>,
+}
+
+impl Section {
+    fn new(dir: &'static str, title: &'static str, items: Vec<(String, Vec<u32>)>) -> Self {
+        // the index page of the section is not overwritten
+        let slugs = unique_slugs(
+            items.iter().map(|(name, _)| name.as_str()),
+            &mut HashSet::from(["index".to_string()]),
+        );
+        let mut by_game: HashMap<u32, Vec<(String, String)>> = HashMap::new();
+        let items: Vec<(String, String, Vec<u32>)> = items
+            .into_iter()
+            .zip(slugs)
+            .map(|((name, ids), slug)| {
+                for id in &ids {
+                    by_game
+                        .entry(*id)
+                        .or_default()
+                        .push((name.clone(), slug.clone()));
+                }
+                (name, slug, ids)
+            })
+            .collect();
+        Self {
+            dir,
+            title,
+            items,
+            by_game,
+        }
+    }
+    fn links(&self, game_id: u32, root: &str) -> Vec<Link> {
+        match self.by_game.get(&game_id) {
+            Some(items) => items
+                .iter()
+                .map(|(name, slug)| Link {
+                    name: name.clone(),
+                    url: format!("{}{}/{}.html", root, self.dir, slug),
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+fn render_error(error: tinytemplate::error::Error) -> Error {
+    Error::new(ErrorKind::InvalidData, error.to_string())
+}
+
+/// Generates a static website from the [`GameDataBase`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteGenerator {
+    title: String,
+    output_dir: PathBuf,
+    base_url: Option<String>,
+    templates: BTreeMap<String, String>,
+}
+
+impl SiteGenerator {
+    /// Creates a [`SiteGenerator`] for a website with the given title,
+    /// the files being written into the output directory.
+    pub fn new(title: &str, output_dir: impl AsRef<Path>) -> Self {
+        Self {
+            title: title.into(),
+            output_dir: output_dir.as_ref().into(),
+            base_url: None,
+            templates: DEFAULT_TEMPLATES
+                .iter()
+                .map(|(name, content)| (name.to_string(), content.to_string()))
+                .collect(),
+        }
+    }
+    /// Sets the base url of the website (e.g. `https://playonbsd.com`),
+    /// used for the canonical urls of the games and the Atom feed.
+    pub fn set_base_url(&mut self, base_url: &str) -> &mut Self {
+        self.base_url = Some(base_url.trim_end_matches('/').into());
+        self
+    }
+    /// Overrides the template with the given name (see [`TEMPLATE_NAMES`]).
+    pub fn set_template(&mut self, name: &str, content: &str) -> Result<&mut Self, Error> {
+        if !TEMPLATE_NAMES.contains(&name) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown template: {}", name),
+            ));
+        }
+        TinyTemplate::new()
+            .add_template(name, content)
+            .map_err(render_error)?;
+        self.templates.insert(name.into(), content.into());
+        Ok(self)
+    }
+    /// Overrides the templates for which a `<name>.html` file exists in
+    /// the given directory.
+    pub fn load_templates_from_dir(&mut self, dir: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        for name in TEMPLATE_NAMES {
+            let path = dir.as_ref().join(format!("{}.html", name));
+            if path.is_file() {
+                let content = fs::read_to_string(&path)?;
+                self.set_template(name, &content)?;
+            }
+        }
+        Ok(self)
+    }
+    /// Returns the content of the template with the given name.
+    pub fn get_template(&self, name: &str) -> Option<&str> {
+        self.templates.get(name).map(|content| content.as_str())
+    }
+    fn page(&self, title: &str, root: &str, canonical: Option<String>) -> PageContext {
+        PageContext {
+            site_title: self.title.clone(),
+            title: title.into(),
+            root: root.into(),
+            canonical,
+            feed: self.base_url.as_ref().map(|_| format!("{}atom.xml", root)),
+            sections: SECTIONS
+                .iter()
+                .map(|(dir, title)| Link {
+                    name: title.to_string(),
+                    url: format!("{}{}/index.html", root, dir),
+                })
+                .collect(),
+        }
+    }
+    fn sections(&self, db: &GameDataBase) -> Vec<Section> {
+        let mut status: BTreeMap<_, Vec<u32>> = BTreeMap::new();
+        for game in db.get_all_games() {
+            status
+                .entry(&game.status.status)
+                .or_default()
+                .push(game.uid);
+        }
+        let status: Vec<(String, Vec<u32>)> = status
+            .into_iter()
+            .map(|(status, ids)| (status.to_string(), ids))
+            .collect();
+        let items = [
+            db.get_all_engines_with_ids(),
+            db.get_all_genres_with_ids(),
+            db.get_all_tags_with_ids(),
+            db.get_all_devs_with_ids(),
+            db.get_all_publis_with_ids(),
+            db.get_all_years_with_ids(),
+            status,
+        ];
+        SECTIONS
+            .iter()
+            .zip(items)
+            .map(|((dir, title), items)| Section::new(dir, title, items))
+            .collect()
+    }
+    /// Returns the content of the `search.json` file: the list of the
+    /// games with the url of their page and the values searched.
+    pub fn search_index(&self, db: &GameDataBase) -> String {
+        let games = db.get_all_games().into_inner();
+        let slugs = db.page_slugs();
+        let entries: Vec<SearchEntry> = games
+            .iter()
+            .map(|game| SearchEntry {
+                name: &game.name,
+                url: format!("games/{}.html", slugs[&game.uid]),
+                engine: game.engine.as_deref(),
+                year: game.year.as_deref(),
+                genres: game.genres.as_deref().unwrap_or_default(),
+                tags: game.tags.as_deref().unwrap_or_default(),
+                devs: game.devs.as_deref().unwrap_or_default(),
+                publis: game.publis.as_deref().unwrap_or_default(),
+                status: game.status.status.to_string(),
+            })
+            .collect();
+        serde_json::to_string(&entries).unwrap_or_default()
+    }
+    // Renders the page of a game
+    fn game_page(
+        &self,
+        tt: &TinyTemplate,
+        game: &Game,
+        slug: &str,
+        sections: &[Section],
+    ) -> Result<String, Error> {
+        let root = "../";
+        let canonical = self
+            .base_url
+            .as_ref()
+            .map(|base_url| format!("{}/games/{}.html", base_url, slug));
+        let links = |dir: &str| -> Vec<Link> {
+            sections
+                .iter()
+                .find(|section| section.dir == dir)
+                .map(|section| section.links(game.uid, root))
+                .unwrap_or_default()
+        };
+        let context = GameContext {
+            page: self.page(&game.name, root, canonical),
+            name: &game.name,
+            cover: game.cover.as_deref(),
+            runtime: game.runtime.as_deref(),
+            setup: game.setup.as_ref().map(|setup| setup.as_str()),
+            hints: game.hints.as_deref(),
+            version: game.version.as_deref(),
+            engine: links("engines"),
+            genres: links("genres"),
+            tags: links("tags"),
+            year: links("years"),
+            devs: links("devs"),
+            publis: links("publis"),
+            status: links("status").into_iter().next().unwrap_or(Link {
+                name: game.status.status.to_string(),
+                url: String::new(),
+            }),
+            status_message: game.status.message.as_deref(),
+            stores: match &game.stores {
+                Some(stores) => stores
+                    .inner_ref()
+                    .iter()
+                    .map(|store| Link {
+                        name: store.store.to_string(),
+                        url: store.url.clone(),
+                    })
+                    .collect(),
+                None => vec![],
+            },
+            added: game.added.to_string(),
+            updated: game.updated.to_string(),
+        };
+        tt.render("game", &context).map_err(render_error)
+    }
+    /// Renders the website into the output directory and returns the
+    /// paths of the written files.
+    pub fn generate(&self, db: &GameDataBase) -> Result<Vec<PathBuf>, Error> {
+        let mut tt = TinyTemplate::new();
+        for (name, content) in &self.templates {
+            tt.add_template(name, content).map_err(render_error)?;
+        }
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut write = |path: PathBuf, content: String| -> Result<(), Error> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, content)?;
+            files.push(path);
+            Ok(())
+        };
+        let games = db.get_all_games().into_inner();
+        let slugs = db.page_slugs();
+        let game_items: HashMap<u32, (String, Option<String>)> = games
+            .iter()
+            .map(|game| (game.uid, (slugs[&game.uid].clone(), game.year.clone())))
+            .collect();
+        let game_list = |ids: &[u32], root: &str| -> Vec<GameItem> {
+            let mut items: Vec<&Game> =
+                ids.iter().filter_map(|id| db.get_game_by_id(*id)).collect();
+            items.sort();
+            items
+                .into_iter()
+                .filter_map(|game| {
+                    game_items.get(&game.uid).map(|(slug, year)| GameItem {
+                        name: game.name.clone(),
+                        url: format!("{}games/{}.html", root, slug),
+                        year: year.clone(),
+                    })
+                })
+                .collect()
+        };
+        // home page
+        let ids: Vec<u32> = games.iter().map(|game| game.uid).collect();
+        let context = HomeContext {
+            page: self.page(
+                "Games",
+                "",
+                self.base_url.as_ref().map(|url| format!("{}/", url)),
+            ),
+            count: games.len(),
+            games: game_list(&ids, ""),
+        };
+        let content = tt.render("home", &context).map_err(render_error)?;
+        write(self.output_dir.join("index.html"), content)?;
+        // game pages
+        let sections = self.sections(db);
+        for game in &games {
+            let slug = &slugs[&game.uid];
+            let content = self.game_page(&tt, game, slug, &sections)?;
+            write(
+                self.output_dir.join("games").join(format!("{}.html", slug)),
+                content,
+            )?;
+        }
+        // section pages
+        for section in &sections {
+            let dir = self.output_dir.join(section.dir);
+            let context = IndexContext {
+                page: self.page(section.title, "../", None),
+                items: section
+                    .items
+                    .iter()
+                    .map(|(name, slug, ids)| IndexItem {
+                        name: name.clone(),
+                        url: format!("{}.html", slug),
+                        count: ids.len(),
+                    })
+                    .collect(),
+            };
+            let content = tt.render("index", &context).map_err(render_error)?;
+            write(dir.join("index.html"), content)?;
+            for (name, slug, ids) in &section.items {
+                let context = CategoryContext {
+                    page: self.page(name, "../", None),
+                    section: section.title.into(),
+                    name: name.clone(),
+                    games: game_list(ids, "../"),
+                };
+                let content = tt.render("category", &context).map_err(render_error)?;
+                write(dir.join(format!("{}.html", slug)), content)?;
+            }
+        }
+        write(self.output_dir.join("search.json"), self.search_index(db))?;
+        if let Some(base_url) = &self.base_url {
+            let config = FeedConfig::new(&self.title, base_url);
+            write(
+                self.output_dir.join("atom.xml"),
+                db.atom_feed(&config, None),
+            )?;
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod site_tests {
+    use super::*;
+
+    fn create_db() -> GameDataBase {
+        let mut game1 = Game::new();
+        game1.uid = 1;
+        game1.name = "Game <One>".into();
+        game1.engine = Some("FNA".into());
+        game1.genres = Some(vec!["RPG".into(), "Puzzle".into()]);
+        game1.year = Some("2020".into());
+        let mut game2 = Game::new();
+        game2.uid = 2;
+        game2.name = "Game two".into();
+        game2.engine = Some("fna".into());
+        GameDataBase::new(vec![game1, game2])
+    }
+    #[test]
+    fn test_section_slugs() {
+        let section = Section::new(
+            "tags",
+            "Tags",
+            vec![("Index".into(), vec![1]), ("Indie".into(), vec![2])],
+        );
+        let slugs: Vec<&str> = section
+            .items
+            .iter()
+            .map(|(_, slug, _)| slug.as_str())
+            .collect();
+        assert_eq!(slugs, vec!["index-2", "indie"]);
+    }
+    #[test]
+    fn test_set_template() {
+        let mut generator = SiteGenerator::new("Site", "public");
+        assert!(generator.set_template("game", "{name}").is_ok());
+        assert_eq!(generator.get_template("game"), Some("{name}"));
+        assert!(generator.set_template("unknown", "{name}").is_err());
+        assert!(generator.set_template("game", "{{ if name }}").is_err());
+    }
+    #[test]
+    fn test_default_templates_render() {
+        let db = create_db();
+        let generator = SiteGenerator::new("Site", "public");
+        let mut tt = TinyTemplate::new();
+        for (name, content) in &generator.templates {
+            tt.add_template(name, content).unwrap();
+        }
+        let sections = generator.sections(&db);
+        let game = db.get_game_by_id(1).unwrap();
+        let page = generator
+            .game_page(&tt, game, "game-one", &sections)
+            .unwrap();
+        assert!(page.contains("<h2>Game &lt;One&gt;</h2>"));
+        assert!(page.contains("<a href=\"../engines/fna.html\">FNA</a>"));
+        assert!(page.contains("<a href=\"../genres/puzzle.html\">Puzzle</a>"));
+        assert!(page.contains("<a href=\"../status/unknown.html\">unknown</a>"));
+    }
+    #[test]
+    fn test_search_index() {
+        let db = create_db();
+        let generator = SiteGenerator::new("Site", "public");
+        let index: serde_json::Value = serde_json::from_str(&generator.search_index(&db)).unwrap();
+        assert_eq!(index[0]["name"], "Game <One>");
+        assert_eq!(index[0]["url"], "games/game-one.html");
+        assert_eq!(index[0]["genres"][1], "Puzzle");
+        assert_eq!(index[1]["genres"], serde_json::json!([]));
+    }
+    #[test]
+    fn test_feed_links_to_game_pages() {
+        let mut games = Vec::new();
+        for (uid, name) in [(1, "Game two"), (2, "Game-Two"), (3, "Game <One>")] {
+            let mut game = Game::new();
+            game.uid = uid;
+            game.name = name.into();
+            game.added = crate::models::GameDate::from_value(Some("2023-01-01"));
+            games.push(game);
+        }
+        let db = GameDataBase::new(games);
+        let dir = std::env::temp_dir().join(format!("libpobsd-site-{}", std::process::id()));
+        let mut generator = SiteGenerator::new("Site", &dir);
+        generator.set_base_url("https://playonbsd.com");
+        let files = generator.generate(&db).unwrap();
+        let feed = fs::read_to_string(dir.join("atom.xml")).unwrap();
+        let prefix = "https://playonbsd.com/games/";
+        let mut urls: Vec<&str> = feed
+            .split(prefix)
+            .skip(1)
+            .map(|rest| &rest[..rest.find(['"', '<']).unwrap()])
+            .collect();
+        // a link for each game
+        assert_eq!(urls.len(), 3);
+        for url in &urls {
+            assert!(files.contains(&dir.join("games").join(url)), "{}", url);
+        }
+        urls.sort();
+        urls.dedup();
+        assert_eq!(urls.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+//! Provides the slugs naming the files generated for the games (the
+//! launchers and the pages of the website) and for the items of the
+//! database.
+use crate::Game;
+
+use std::collections::{HashMap, HashSet};
+
+/// Returns the slug of a game, used for its launcher files and its page
+/// on the website: its name lowercased, with non alphanumeric characters
+/// replaced by dashes (e.g. "the-adventures-of-shuggy"), or "item" if
+/// the name has no alphanumeric character.
+pub fn game_slug(game: &Game) -> String {
+    non_empty_slug(&game.name)
+}
+
+// Slugifies a value, "item" being used when the slug is empty
+fn non_empty_slug(value: &str) -> String {
+    match slugify(value) {
+        slug if slug.is_empty() => "item".into(),
+        slug => slug,
+    }
+}
+
+// Returns a slug for each value, a numeric suffix being added to the
+// slugs already used
+pub(crate) fn unique_slugs<'a>(
+    values: impl Iterator<Item = &'a str>,
+    used: &mut HashSet<String>,
+) -> Vec<String> {
+    values
+        .map(|value| {
+            let base = non_empty_slug(value);
+            let mut slug = base.clone();
+            let mut n = 2;
+            while used.contains(&slug) {
+                slug = format!("{}-{}", base, n);
+                n += 1;
+            }
+            used.insert(slug.clone());
+            slug
+        })
+        .collect()
+}
+
+// Returns the unique slug of each game, the games being sorted as in
+// GameDataBase::get_all_games so that the suffixes do not depend on
+// the order in which they are given
+pub(crate) fn game_slugs<'a>(
+    games: impl IntoIterator<Item = &'a Game>,
+    used: &mut HashSet<String>,
+) -> HashMap<u32, String> {
+    let mut games: Vec<&Game> = games.into_iter().collect();
+    games.sort();
+    let slugs = unique_slugs(games.iter().map(|game| game.name.as_str()), used);
+    games.iter().map(|game| game.uid).zip(slugs).collect()
+}
+
+// Lowercases a value, replacing non alphanumeric characters by dashes
+fn slugify(value: &str) -> String {
+    let slug: String = value
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    slug.split_whitespace().collect::<Vec<&str>>().join("-")
+}
+
+#[cfg(test)]
+mod slug_tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_slugs() {
+        let slugs = unique_slugs(["C++", "C", "c", "!"].into_iter(), &mut HashSet::new());
+        assert_eq!(slugs, vec!["c", "c-2", "c-3", "item"]);
+        let mut used = HashSet::from(["index".to_string()]);
+        let slugs = unique_slugs(["Index", "index!"].into_iter(), &mut used);
+        assert_eq!(slugs, vec!["index-2", "index-3"]);
+    }
+}
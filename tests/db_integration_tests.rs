@@ -1,4 +1,6 @@
-use libpobsd::{Game, GameDataBase, Parser, ParserResult, ParsingMode, SearchType};
+use libpobsd::db::library::{Library, MatchMethod};
+use libpobsd::db::{AliasRegistry, FeedConfig, FeedEntryKind, InstalledPackages, RuntimePackages};
+use libpobsd::{Game, GameDataBase, GameFilter, Parser, ParserResult, ParsingMode, SearchType};
 
 // HELPER FUNCTIONS
 
@@ -123,7 +125,7 @@ fn test_get_by_steam_id_game_does_not_exist() {
 #[test]
 fn test_get_game_by_tag_tag_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_tag("indie");
+    let game_query = db.match_games_by_tag("indie", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 3);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(game.name, "The Adventures of Mr. Hat".to_string());
@@ -135,7 +137,27 @@ fn test_get_game_by_tag_tag_exists() {
 #[test]
 fn test_get_game_by_tag_tag_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_tag("I do not exist");
+    let game_query = db.match_games_by_tag("I do not exist", &SearchType::CaseSensitive);
+    assert_eq!(game_query.items.len(), 0);
+}
+
+#[test]
+fn test_get_game_by_genre_not_case_sensitive() {
+    let db = get_db_strict();
+    let st = SearchType::NotCaseSensitive;
+    let game_query = db.match_games_by_genre("PLATFORMER ", &st);
+    assert_eq!(game_query.items.len(), 2);
+    assert_eq!(game_query.items[0].name, "Akane the Kunoichi");
+    assert_eq!(game_query.items[1].name, "Alien Shepherd");
+}
+#[test]
+fn test_get_game_by_genre_case_sensitive() {
+    let db = get_db_strict();
+    let st = SearchType::CaseSensitive;
+    let game_query = db.match_games_by_genre("platformer", &st);
+    assert_eq!(game_query.items.len(), 1);
+    assert_eq!(game_query.items[0].name, "Alien Shepherd");
+    let game_query = db.match_games_by_genre("PLATFORMER", &st);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -143,7 +165,7 @@ fn test_get_game_by_tag_tag_does_not_exist() {
 #[test]
 fn test_get_by_year_year_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_year("2011");
+    let game_query = db.match_games_by_year("2011", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 1);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(
@@ -154,7 +176,7 @@ fn test_get_by_year_year_exists() {
 #[test]
 fn test_get_by_year_year_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_year("2811");
+    let game_query = db.match_games_by_year("2811", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -162,7 +184,7 @@ fn test_get_by_year_year_does_not_exist() {
 #[test]
 fn test_get_game_by_engine_engine_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_engine("godot");
+    let game_query = db.match_games_by_engine("godot", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 1);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(game.name, "The Adventures of Mr. Hat".to_string());
@@ -170,7 +192,7 @@ fn test_get_game_by_engine_engine_exists() {
 #[test]
 fn test_get_game_by_engine_engine_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_engine("I do not exist");
+    let game_query = db.match_games_by_engine("I do not exist", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -178,7 +200,7 @@ fn test_get_game_by_engine_engine_does_not_exist() {
 #[test]
 fn test_get_game_by_runtime_runtime_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_runtime("lwjgl");
+    let game_query = db.match_games_by_runtime("lwjgl", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 1);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(game.name, "Airships: Conquer the Skies".to_string());
@@ -186,7 +208,7 @@ fn test_get_game_by_runtime_runtime_exists() {
 #[test]
 fn test_get_game_by_runtime_runtime_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_runtime("I do not exist");
+    let game_query = db.match_games_by_runtime("I do not exist", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -194,7 +216,7 @@ fn test_get_game_by_runtime_runtime_does_not_exist() {
 #[test]
 fn test_get_game_by_genre_genre_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_genre("shmup");
+    let game_query = db.match_games_by_genre("shmup", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 1);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(game.name, "Aeternum".to_string());
@@ -202,7 +224,7 @@ fn test_get_game_by_genre_genre_exists() {
 #[test]
 fn test_get_game_by_genre_genre_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_genre("I do not exist");
+    let game_query = db.match_games_by_genre("I do not exist", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -210,7 +232,7 @@ fn test_get_game_by_genre_genre_does_not_exist() {
 #[test]
 fn test_get_game_by_dev_dev_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_dev("Creaky Lantern Games");
+    let game_query = db.match_games_by_dev("Creaky Lantern Games", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 1);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(game.name, "Aeternum".to_string());
@@ -218,7 +240,7 @@ fn test_get_game_by_dev_dev_exists() {
 #[test]
 fn test_get_game_by_dev_dev_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_dev("I do not exist");
+    let game_query = db.match_games_by_dev("I do not exist", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -226,7 +248,7 @@ fn test_get_game_by_dev_dev_does_not_exist() {
 #[test]
 fn test_get_game_by_publi_publi_exists() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_publi("Florent Espanet");
+    let game_query = db.match_games_by_publi("Florent Espanet", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 1);
     let game = game_query.items.get(0).unwrap();
     assert_eq!(game.name, "Alien Shepherd".to_string());
@@ -234,7 +256,7 @@ fn test_get_game_by_publi_publi_exists() {
 #[test]
 fn test_get_game_by_publi_publi_does_not_exist() {
     let db = get_db_strict();
-    let game_query = db.match_games_by_publi("I do not exist");
+    let game_query = db.match_games_by_publi("I do not exist", &SearchType::CaseSensitive);
     assert_eq!(game_query.items.len(), 0);
 }
 
@@ -301,15 +323,9 @@ fn test_get_all_years() {
 fn test_get_all_genres() {
     let db = get_db_strict();
     let tag_query = db.get_all_genres();
-    assert_eq!(tag_query.items.len(), 6);
-    for genre in vec![
-        "Puzzle Platformer",
-        "RPG",
-        "shmup",
-        "RTS",
-        "Platformer",
-        "platformer",
-    ] {
+    // "Platformer" and "platformer" are collapsed
+    assert_eq!(tag_query.items.len(), 5);
+    for genre in vec!["Puzzle Platformer", "RPG", "shmup", "RTS", "Platformer"] {
         assert!(tag_query.items.contains(&&genre.to_string()));
     }
 }
@@ -650,3 +666,540 @@ fn test_search_game_by_engine_engine_does_not_exist() {
     let games = games.into_inner();
     assert!(games.is_empty());
 }
+
+// Test search modes
+#[test]
+fn test_search_game_by_genre_exact() {
+    let db = get_db_strict();
+    let st = SearchType::Exact {
+        case_sensitive: false,
+    };
+    let games = db.search_games_by_genre("platformer", &st).into_inner();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].name, "Akane the Kunoichi");
+    assert_eq!(games[1].name, "Alien Shepherd");
+    let st = SearchType::Exact {
+        case_sensitive: true,
+    };
+    let games = db.search_games_by_genre("platformer", &st).into_inner();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].name, "Alien Shepherd");
+}
+#[test]
+fn test_search_game_by_name_prefix() {
+    let db = get_db_strict();
+    let st = SearchType::Prefix {
+        case_sensitive: false,
+    };
+    let games = db.search_games_by_name("the adventures", &st).into_inner();
+    assert_eq!(games.len(), 2);
+    let games = db.search_games_by_name("adventures", &st).into_inner();
+    assert!(games.is_empty());
+    let st = SearchType::Prefix {
+        case_sensitive: true,
+    };
+    let games = db.search_games_by_name("the adventures", &st).into_inner();
+    assert!(games.is_empty());
+}
+#[test]
+fn test_search_game_by_tag_whole_word() {
+    let db = get_db_strict();
+    let st = SearchType::NotCaseSensitive;
+    let games = db.search_games_by_tag("hell", &st).into_inner();
+    assert_eq!(games.len(), 1);
+    let st = SearchType::WholeWord {
+        case_sensitive: false,
+    };
+    let games = db.search_games_by_tag("hell", &st).into_inner();
+    assert!(games.is_empty());
+    let games = db.search_games_by_tag("Manga", &st).into_inner();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].name, "Aeternum");
+}
+#[test]
+fn test_search_game_by_name_regex() {
+    let db = get_db_strict();
+    let st = SearchType::Regex {
+        case_sensitive: true,
+    };
+    let games = db.search_games_by_name("^Al", &st).into_inner();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].name, "Alien Shepherd");
+    assert_eq!(games[1].name, "Always Sometimes Monsters");
+    let games = db.search_games_by_name("^al", &st).into_inner();
+    assert!(games.is_empty());
+    // an invalid regular expression matches nothing
+    let games = db.search_games_by_name("(", &st).into_inner();
+    assert!(games.is_empty());
+    let mut filter = GameFilter::default();
+    filter.set_name("shuggy$").set_genre("^rpg$");
+    let st = SearchType::Regex {
+        case_sensitive: false,
+    };
+    let games = db.search_game_by_filter(&st, &filter).into_inner();
+    assert_eq!(games.len(), 3);
+}
+
+// Test get_collapsed_spellings
+#[test]
+fn test_get_collapsed_spellings() {
+    let db = get_db_strict();
+    let collapsed = db.get_collapsed_spellings();
+    assert_eq!(collapsed.len(), 1);
+    assert_eq!(collapsed[0].field, "genres");
+    assert_eq!(collapsed[0].display, "Platformer");
+    assert_eq!(
+        collapsed[0].spellings,
+        vec![("Platformer".to_string(), 1), ("platformer".to_string(), 1)]
+    );
+}
+#[test]
+fn test_get_all_genres_with_ids_collapsed() {
+    let db = get_db_strict();
+    let genres = db.get_all_genres_with_ids();
+    let (genre, ids) = genres
+        .iter()
+        .find(|(genre, _)| genre.eq("Platformer"))
+        .expect("Platformer exists");
+    assert_eq!(genre, "Platformer");
+    assert_eq!(ids.len(), 2);
+}
+
+//-------------------
+// SIMILAR GAMES
+//-------------------
+
+#[test]
+fn test_similar_games() {
+    let db = get_db_strict();
+    let st = SearchType::CaseSensitive;
+    let game = db
+        .get_game_by_name("The Adventures of Shuggy", &st)
+        .expect("Game exists");
+    let similar = db.similar_games(game.uid, 5);
+    assert_eq!(similar.len(), 2);
+    assert_eq!(similar[0].game.name, "The Adventures of Mr. Hat");
+    assert_eq!(similar[0].similarity.tags, 1.0);
+    assert_eq!(similar[0].similarity.genres, 1.0);
+    assert_eq!(similar[1].game.name, "Aeternum");
+    assert_eq!(similar[1].similarity.tags, 0.25);
+    assert_eq!(similar[1].similarity.engine, 1.0);
+    assert!(similar[0].score > similar[1].score);
+}
+#[test]
+fn test_similar_games_game_does_not_exist() {
+    let db = get_db_strict();
+    assert!(db.similar_games(1, 5).is_empty());
+}
+
+//-------------------
+// DUPLICATES
+//-------------------
+
+#[test]
+fn test_find_duplicates_without_duplicates() {
+    let db = get_db_strict();
+    assert!(db.find_duplicates().is_empty());
+}
+#[test]
+fn test_find_duplicates_with_renamed_game() {
+    let mut games = get_games_strict("tests/data/test-games.db");
+    let mut game = games[2].clone();
+    assert_eq!(game.name, "The Adventures of Shuggy");
+    game.name = "Adventures of Shuggy".into();
+    game.uid = 1;
+    games.push(game);
+    let db = GameDataBase::new(games);
+    let groups = db.find_duplicates();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].games[0].name, "Adventures of Shuggy");
+    assert_eq!(groups[0].games[1].name, "The Adventures of Shuggy");
+    // same name, two store links and IGDB id
+    assert_eq!(groups[0].reasons.len(), 4);
+}
+
+//-------------------
+// ALIASES
+//-------------------
+
+fn get_db_with_aliases() -> GameDataBase {
+    let games = get_games_strict("tests/data/test-games.db");
+    let aliases =
+        AliasRegistry::load_from_file("tests/data/test-aliases.txt").expect("Valid alias file");
+    GameDataBase::with_aliases(games, aliases)
+}
+
+#[test]
+fn test_match_games_by_engine_with_aliases() {
+    let db = get_db_with_aliases();
+    for engine in ["FNA", "fna/xna", "XNA"] {
+        let games = db.match_games_by_engine(engine, &SearchType::CaseSensitive);
+        assert_eq!(games.count, 3);
+    }
+    assert!(db
+        .get_all_engines()
+        .into_inner()
+        .contains(&&"FNA".to_string()));
+    assert!(!db
+        .get_all_engines()
+        .into_inner()
+        .contains(&&"XNA".to_string()));
+}
+#[test]
+fn test_match_games_by_dev_with_aliases() {
+    let db = get_db_with_aliases();
+    let games = db.match_games_by_dev("creaky lantern studio", &SearchType::CaseSensitive);
+    assert_eq!(games.count, 1);
+    assert_eq!(games.get(0).unwrap().name, "Aeternum");
+    let devs = db.get_all_devs_with_ids();
+    assert!(devs.iter().any(|(dev, _)| dev.eq("Creaky Lantern")));
+    // publishers are not aliased
+    let games = db.match_games_by_publi("Creaky Lantern", &SearchType::NotCaseSensitive);
+    assert!(games.into_inner().is_empty());
+}
+#[test]
+fn test_set_aliases() {
+    let mut db = get_db_strict();
+    assert_eq!(
+        db.match_games_by_engine("XNA", &SearchType::CaseSensitive)
+            .count,
+        1
+    );
+    db.set_aliases(AliasRegistry::load_from_file("tests/data/test-aliases.txt").unwrap());
+    assert_eq!(
+        db.match_games_by_engine("XNA", &SearchType::CaseSensitive)
+            .count,
+        3
+    );
+    db.set_aliases(AliasRegistry::new());
+    assert!(db.get_aliases().is_empty());
+    assert_eq!(
+        db.match_games_by_engine("XNA", &SearchType::CaseSensitive)
+            .count,
+        1
+    );
+}
+
+//-------------------
+// SETUP
+//-------------------
+
+#[test]
+fn test_match_games_needing_command() {
+    let db = get_db_strict();
+    let games = db.match_games_needing_command("fnaify");
+    assert_eq!(games.count, 2);
+    assert_eq!(games.get(0).unwrap().name, "The Adventures of Shuggy");
+    assert!(db
+        .match_games_needing_command("hlwrap")
+        .into_inner()
+        .is_empty());
+}
+#[test]
+fn test_match_games_needing_package() {
+    let db = get_db_strict();
+    let games = db.match_games_needing_package("lwjgl");
+    assert_eq!(games.count, 1);
+    let game = games.get(0).unwrap();
+    assert_eq!(game.setup.as_ref().unwrap().to_string(), "lwjgl");
+    assert!(db
+        .match_games_needing_package("fnaify")
+        .into_inner()
+        .is_empty());
+}
+
+//-------------------
+// PACKAGES
+//-------------------
+
+#[test]
+fn test_check_installed_packages() {
+    let db = get_db_strict();
+    let installed = InstalledPackages::from_pkg_info("godot-3.5.2p0\nfnaify-2.2\nsdl2-2.26.5\n");
+    let report = db.check_installed_packages(&installed, &RuntimePackages::builtin());
+    assert_eq!(report.installed.len(), 2);
+    assert_eq!(report.missing.len(), 3);
+    assert_eq!(report.unmapped.len(), 2);
+    assert_eq!(
+        report.to_install(),
+        vec!["easyrpg-player", "hashlink", "lwjgl"]
+    );
+}
+
+//-------------------
+// LIBRARY
+//-------------------
+
+#[test]
+fn test_match_library_steam() {
+    let db = get_db_strict();
+    let library = Library::from_steam_app_ids("211440\n454750 Aeternum\n1 Not a game\n");
+    let report = db.match_library(&library);
+    assert_eq!(report.matched.len(), 2);
+    assert_eq!(report.matched[0].game.name, "The Adventures of Shuggy");
+    assert_eq!(report.matched[0].method, MatchMethod::StoreId);
+    assert_eq!(report.unmatched_titles(), vec!["Not a game"]);
+}
+#[test]
+fn test_match_library_gog() {
+    let db = get_db_strict();
+    let library =
+        Library::from_gog_list("airships_conquer_the_skies\nAlways Sometimes Monster\nAedemphia\n");
+    let report = db.match_library(&library);
+    let methods: Vec<MatchMethod> = report.matched.iter().map(|m| m.method).collect();
+    assert_eq!(
+        methods,
+        vec![
+            MatchMethod::Name,
+            MatchMethod::StoreUrl,
+            MatchMethod::FuzzyName(1)
+        ]
+    );
+    assert!(report.unmatched.is_empty());
+}
+#[test]
+fn test_match_library_short_titles_are_not_fuzzy() {
+    let db = get_db_strict();
+    let library = Library::from_gog_list("Aeternam\nAedemphio\n");
+    let report = db.match_library(&library);
+    assert!(report.matched.is_empty());
+    assert_eq!(report.unmatched_titles(), vec!["Aeternam", "Aedemphio"]);
+}
+#[test]
+fn test_get_game_by_store_url() {
+    let db = get_db_strict();
+    let game = db
+        .get_game_by_store_url("https://zarkonnen.itch.io/airships/")
+        .expect("Game exists");
+    assert_eq!(game.name, "Airships: Conquer the Skies");
+    assert!(db
+        .get_game_by_store_url("https://zarkonnen.itch.io")
+        .is_none());
+}
+
+//-------------------
+// FEED
+//-------------------
+
+#[test]
+fn test_feed_entries() {
+    let db = get_db_strict();
+    let mut config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+    config.set_max_entries(3);
+    let entries = db.feed_entries(&config, None);
+    let names: Vec<&str> = entries.iter().map(|e| e.game.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["Alien Shepherd", "The Adventures of Mr. Hat", "Aeternum"]
+    );
+    assert_eq!(entries[0].kind, FeedEntryKind::Updated);
+    assert_eq!(entries[1].kind, FeedEntryKind::Added);
+}
+#[test]
+fn test_atom_feed_links_stores() {
+    let db = get_db_strict();
+    let mut config = FeedConfig::new("PlayOnBSD", "https://playonbsd.com");
+    config.set_max_entries(1);
+    let feed = db.atom_feed(&config, None);
+    assert!(feed.contains("<id>tag:playonbsd.com,2023-01-01:games/"));
+    assert!(feed.contains(
+        "<link rel=\"alternate\" href=\"https://playonbsd.com/games/alien-shepherd.html\"/>"
+    ));
+    assert!(feed.contains("href=\"https://store.steampowered.com/app/1808820/Alien_Shepherd/\""));
+}
+
+//-------------------
+// SITE
+//-------------------
+
+#[cfg(feature = "site")]
+#[test]
+fn test_generate_site() {
+    use libpobsd::site::SiteGenerator;
+    let db = get_db_strict();
+    let mut output_dir = std::env::temp_dir();
+    output_dir.push(format!("libpobsd-site-{}", std::process::id()));
+    let mut generator = SiteGenerator::new("PlayOnBSD", &output_dir);
+    generator
+        .set_base_url("https://playonbsd.com/")
+        .set_template(
+            "category",
+            "{section}/{name}: {{ for game in games }}{game.name};{{ endfor }}",
+        )
+        .unwrap();
+    let files = generator.generate(&db).unwrap();
+    assert!(files.contains(&output_dir.join("games/aeternum.html")));
+    assert!(files.contains(&output_dir.join("atom.xml")));
+    let game =
+        std::fs::read_to_string(output_dir.join("games/the-adventures-of-shuggy.html")).unwrap();
+    assert!(game.contains("<link rel=\"canonical\" href=\"https://playonbsd.com/games/the-adventures-of-shuggy.html\">"));
+    assert!(game.contains("<a href=\"../engines/fna.html\">FNA</a>"));
+    let engine = std::fs::read_to_string(output_dir.join("engines/fna.html")).unwrap();
+    assert_eq!(engine, "Engines/FNA: The Adventures of Shuggy;Aeternum;");
+    let engines = std::fs::read_to_string(output_dir.join("engines/index.html")).unwrap();
+    assert!(engines.contains("<a href=\"fna.html\">FNA</a> (2)"));
+    let search = std::fs::read_to_string(output_dir.join("search.json")).unwrap();
+    assert!(search.contains("\"url\":\"games/aeternum.html\""));
+    std::fs::remove_dir_all(output_dir).unwrap();
+}
+
+//-------------------
+// SERVER
+//-------------------
+
+#[cfg(feature = "server")]
+#[test]
+fn test_server_games() {
+    use libpobsd::server::handle_request;
+    let db = get_db_strict();
+    let response = handle_request(&db, "/games?per_page=4&page=3");
+    assert_eq!(response.status, 200);
+    let page: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(page["total"], 9);
+    assert_eq!(page["pages"], 3);
+    assert_eq!(page["items"][0]["name"], "Always Sometimes Monsters");
+    let response = handle_request(&db, "/games/steam/454750");
+    let game: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(game["name"], "Aeternum");
+    let uid = game["uid"].as_u64().unwrap();
+    let response = handle_request(&db, &format!("/games/{}", uid));
+    assert_eq!(response.status, 200);
+    let response = handle_request(&db, "/games/name/the%20adventures%20of%20shuggy");
+    assert_eq!(response.status, 200);
+    let response = handle_request(
+        &db,
+        "/games/name/the%20adventures%20of%20shuggy?case=sensitive",
+    );
+    assert_eq!(response.status, 404);
+}
+#[cfg(feature = "server")]
+#[test]
+fn test_server_queries() {
+    use libpobsd::server::handle_request;
+    let db = get_db_strict();
+    let response = handle_request(&db, "/search/engine?q=fna");
+    let page: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(page["total"], 2);
+    let response = handle_request(&db, "/filter?engine=xna&dev=creaky");
+    let page: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(page["total"], 2);
+    assert_eq!(handle_request(&db, "/filter?status=broken").status, 400);
+    let response = handle_request(&db, "/items/engines?per_page=1");
+    let page: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(page["items"][0]["name"], "FNA");
+    assert_eq!(page["items"][0]["ids"].as_array().unwrap().len(), 2);
+    let response = handle_request(&db, "/stats");
+    let stats: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(stats["games"], 9);
+}
+#[test]
+fn test_timeline_from_files() {
+    use chrono::NaiveDate;
+    use libpobsd::db::{Timeline, TimelineEventKind};
+    use libpobsd::Status;
+    let data = std::fs::read_to_string("tests/data/test-games.db").unwrap();
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("libpobsd-timeline-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let old_data = data.replace("Status\truns (2022-05-13)", "Status\t5 (2022-05-13)");
+    let new_data = data
+        .replace(
+            "Status\truns (2022-05-13)",
+            "Status\t2 crashes (2023-02-01)",
+        )
+        .replace("Game\tThe Adventures of Mr. Hat", "Game\tMr. Hat");
+    std::fs::write(dir.join("old.db"), old_data).unwrap();
+    std::fs::write(dir.join("new.db"), new_data).unwrap();
+    let mut timeline = Timeline::new();
+    timeline
+        .add_snapshot_from_file(
+            "v1",
+            NaiveDate::from_ymd_opt(2022, 6, 1),
+            dir.join("old.db"),
+        )
+        .unwrap()
+        .add_snapshot_from_file(
+            "v2",
+            NaiveDate::from_ymd_opt(2023, 3, 1),
+            dir.join("new.db"),
+        )
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(timeline.games.len(), 9);
+    let regressions = timeline.regressions();
+    assert_eq!(regressions.len(), 1);
+    let (game, event) = regressions[0];
+    assert_eq!(game.game.name, "Mr. Hat");
+    assert_eq!(timeline.snapshots[event.snapshot].label, "v2");
+    assert_eq!(
+        event.kind,
+        TimelineEventKind::StatusChanged(
+            Status::Completable,
+            Status::MajorBugs,
+            NaiveDate::from_ymd_opt(2023, 2, 1)
+        )
+    );
+    assert!(game.events.iter().any(|event| event.kind
+        == TimelineEventKind::Renamed("The Adventures of Mr. Hat".into(), "Mr. Hat".into())));
+    assert_eq!(timeline.get_game(game.game.uid), Some(game));
+}
+#[test]
+fn test_merge_databases() {
+    use libpobsd::db::{DatabaseMerger, MergeRule};
+    let upstream: Vec<Game> = Parser::default()
+        .load_from_file("tests/data/test-games.db")
+        .unwrap()
+        .into();
+    let local = "Game\tThe Adventures of Mr. Hat (local)\nTags\tpuzzle\nStatus\t5 (2023-01-01)\nIgdbId\t13\nGame\tUnreleased\nAdded\t2023-01-01";
+    let local: Vec<Game> = Parser::default().load_from_string(local).into();
+    let mut merger = DatabaseMerger::new();
+    merger
+        .add_source("upstream", upstream)
+        .add_source("local", local)
+        .set_rule("Status", MergeRule::Prefer("local".into()))
+        .unwrap()
+        .set_rule("Tags", MergeRule::Union)
+        .unwrap();
+    let (db, conflicts) = merger.merge();
+    assert_eq!(db.get_all_games().count, 10);
+    let game = db
+        .get_game_by_name("The Adventures of Mr. Hat", &SearchType::CaseSensitive)
+        .unwrap();
+    assert_eq!(game.status.to_string(), "5 (2023-01-01)");
+    assert_eq!(game.tags, Some(vec!["indie".into(), "puzzle".into()]));
+    assert_eq!(game.engine, Some("godot".into()));
+    let keys: Vec<&str> = conflicts.iter().map(|conflict| conflict.key).collect();
+    // the upstream status has no level, only the local one is set
+    assert_eq!(keys, vec!["Game", "Tags"]);
+    assert!(conflicts.iter().all(|conflict| conflict.uid == game.uid));
+}
+#[test]
+fn test_link_previous_uids() {
+    let data = std::fs::read_to_string("tests/data/test-games.db").unwrap();
+    let previous = GameDataBase::new(Parser::default().load_from_string(&data).into());
+    let old_uid = previous
+        .get_game_by_name("The Adventures of Mr. Hat", &SearchType::CaseSensitive)
+        .unwrap()
+        .uid;
+    let data = data.replace(
+        "Game\tThe Adventures of Mr. Hat",
+        "Game\tThe Adventures of Mister Hat",
+    );
+    let mut db = GameDataBase::new(Parser::default().load_from_string(&data).into());
+    assert!(db.get_game_by_id(old_uid).is_none());
+    let links = db.link_previous_uids(&previous);
+    assert_eq!(links.len(), 1);
+    let game = db.get_game_by_id(old_uid).unwrap();
+    assert_eq!(game.name, "The Adventures of Mister Hat");
+    assert_eq!(links[0], (old_uid, game.uid));
+    // the aliases are kept across versions
+    let data = data.replace("Added\t2022-05-13", "Added\t2022-05-14");
+    let mut next = GameDataBase::new(Parser::default().load_from_string(&data).into());
+    next.link_previous_uids(&db);
+    assert_eq!(
+        next.get_game_by_id(old_uid)
+            .map(|game| game.added.to_string()),
+        Some("2022-05-14".into())
+    );
+    assert_eq!(next.get_uid_aliases().len(), 2);
+}
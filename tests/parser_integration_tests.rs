@@ -1,4 +1,6 @@
-use libpobsd::{Game, Parser, ParserResult, ParsingMode};
+use chrono::NaiveDate;
+use libpobsd::parsing::schema::migrate_games;
+use libpobsd::{Game, GameDate, Parser, ParserResult, ParsingMode, SchemaVersion};
 
 // HELPER FUNCTIONS
 // helper function to return the games with both
@@ -195,3 +197,237 @@ fn test_parser_right_games_with_faulty_database_strict_mode() {
     );
     assert_eq!(games.get(1).unwrap().name, "The Adventures of Mr. Hat");
 }
+
+#[test]
+fn test_parser_report_spans() {
+    let report = Parser::default()
+        .load_report_from_file("tests/data/test-games.db")
+        .unwrap();
+    assert_eq!(report.spans.len(), report.get_games().len());
+    let span = &report.spans[1];
+    assert_eq!(span.uid, report.get_games()[1].uid);
+    assert_eq!((span.start_line, span.end_line), (18, 34));
+    assert_eq!(span.get_field_line("Engine"), Some(20));
+    assert_eq!(span.get_field_line("Pub"), Some(29));
+    assert_eq!(report.get_span_at_line(25), Some(span));
+    assert_eq!(report.get_span(span.uid), Some(span));
+}
+#[test]
+fn test_parser_report_spans_with_faulty_database() {
+    let report = Parser::default()
+        .load_report_from_file("tests/data/test-games-faulty.db")
+        .unwrap();
+    let span = &report.spans[1];
+    assert_eq!((span.start_line, span.end_line), (18, 34));
+    assert_eq!(span.get_field_line("Engine"), None);
+    let report = Parser::new(ParsingMode::Strict)
+        .load_report_from_file("tests/data/test-games-faulty.db")
+        .unwrap();
+    assert_eq!(report.spans.len(), 2);
+    assert_eq!(report.spans[1].end_line, 19);
+    let games: Vec<Game> = ParserResult::from(report).into();
+    assert_eq!(games.len(), 2);
+}
+#[test]
+fn test_parser_recovery_mode_quarantines_broken_record() {
+    let report = Parser::new(ParsingMode::Recovery)
+        .load_report_from_file("tests/data/test-games-faulty.db")
+        .unwrap();
+    assert_eq!(report.quarantined.len(), 1);
+    let record = &report.quarantined[0];
+    assert_eq!((record.start_line, record.end_line), (120, 136));
+    assert_eq!(record.lines[0], "//Game\tAlien Shepherd");
+    assert_eq!(record.game.name, "Alien Shepherd");
+    assert_eq!(record.game.engine, Some("HashLink".into()));
+    assert_eq!(report.get_quarantined_at_line(125), Some(record));
+    // the previous game is not overwritten by the broken record
+    let games = report.get_games();
+    let akane = games
+        .iter()
+        .find(|game| game.name == "Akane the Kunoichi")
+        .unwrap();
+    assert_eq!(akane.added.to_string(), "1970-01-01");
+    assert_eq!(akane.engine, Some("XNA".into()));
+    assert!(games.iter().all(|game| game.name != "Alien Shepherd"));
+    let span = report.get_span(akane.uid).unwrap();
+    assert_eq!(span.end_line, 119);
+    assert_eq!(report.get_span_at_line(120), None);
+    match report.result {
+        ParserResult::WithError(_, lines) => assert_eq!(lines, vec![20, 51, 97, 120, 138]),
+        ParserResult::WithoutError(_) => panic!(),
+    }
+}
+#[test]
+fn test_parser_recovery_mode_with_faulty_first_record() {
+    let report = Parser::new(ParsingMode::Recovery)
+        .load_report_from_file("tests/data/test-games-faulty-at-start.db")
+        .unwrap();
+    assert_eq!(report.quarantined.len(), 1);
+    assert_eq!(report.quarantined[0].start_line, 1);
+    assert_eq!(
+        report.quarantined[0].game.name,
+        "AaaaaAAaaaAAAaaAAAAaAAAAA!!! for the Awesome"
+    );
+    assert_eq!(report.get_games().len(), 8);
+}
+#[test]
+fn test_parser_recovery_mode_keeps_out_of_order_fields() {
+    let data = "Game\tA\nStatus\t3\nEngine\tgodot\nRuntime\thashlink\nGame\tB";
+    let report = Parser::new(ParsingMode::Recovery).load_report_from_string(data);
+    assert!(report.quarantined.is_empty());
+    let games = report.get_games();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].engine, Some("godot".into()));
+    assert_eq!(games[0].runtime, Some("hashlink".into()));
+    assert_eq!(report.spans[0].end_line, 4);
+    match report.result {
+        ParserResult::WithError(_, lines) => assert_eq!(lines, vec![3]),
+        ParserResult::WithoutError(_) => panic!(),
+    }
+}
+#[test]
+fn test_parser_keeps_unknown_fields() {
+    let games = get_games("tests/data/test-games-faulty.db");
+    let game = &games[1];
+    assert_eq!(game.extra, vec![("//Engine".into(), Some("godot".into()))]);
+    assert!(format!("{}", game).ends_with("IgdbId\t13\n//Engine\tgodot"));
+}
+#[test]
+fn test_parser_with_extension_keys() {
+    let data =
+        "Game\tGame 1\nEngine\tgodot\nController\tfull\nMultiplayer\nLicense\tMIT\nGame\tGame 2";
+    let parser = Parser::default().with_extension_keys(["Controller", "Multiplayer"]);
+    let games = match parser.load_from_string(data) {
+        ParserResult::WithError(games, lines) => {
+            assert_eq!(lines, vec![5]);
+            games
+        }
+        ParserResult::WithoutError(_) => panic!(),
+    };
+    assert_eq!(games[0].get_extra("Controller"), Some("full"));
+    assert!(games[0].has_extra("Multiplayer"));
+    assert_eq!(games[0].get_extra("License"), Some("MIT"));
+    let parser = Parser::new(ParsingMode::Strict).with_extension_keys(["Controller"]);
+    let games: Vec<Game> = parser.load_from_string(data).into();
+    assert_eq!(games.len(), 1);
+    assert_eq!(
+        games[0].extra,
+        vec![("Controller".into(), Some("full".into()))]
+    );
+}
+#[test]
+fn test_parser_schema_version() {
+    let report = Parser::default()
+        .load_report_from_file("tests/data/test-games.db")
+        .unwrap();
+    assert_eq!(report.schema, SchemaVersion::LATEST);
+    let data = "Game\tGame 1\nEngine\tgodot\nStatus\truns\nGame\tGame 2\nAdded\t2020-01-01";
+    let report = Parser::default().load_report_from_string(data);
+    assert_eq!(report.schema, SchemaVersion::V2);
+    let report = Parser::default()
+        .with_schema(SchemaVersion::V1)
+        .load_report_from_string(data);
+    assert_eq!(report.schema, SchemaVersion::V1);
+    assert_eq!(report.get_games()[1].added, NaiveDate::default());
+    assert_eq!(
+        report.get_games()[1].extra,
+        vec![("Added".into(), Some("2020-01-01".into()))]
+    );
+    match report.result {
+        ParserResult::WithError(_, lines) => assert_eq!(lines, vec![5]),
+        ParserResult::WithoutError(_) => panic!(),
+    }
+}
+#[test]
+fn test_migrate_games_from_v1() {
+    let data = "Game\tGame 1\nEngine\tgodot\nStatus\truns\nGame\tGame 2";
+    let report = Parser::default().load_report_from_string(data);
+    assert_eq!(report.schema, SchemaVersion::V1);
+    let date = NaiveDate::from_ymd_opt(2021, 3, 1);
+    let migration = migrate_games(report.get_games().to_vec(), report.schema, date);
+    assert_eq!(migration.to, SchemaVersion::LATEST);
+    let game = &migration.games[0];
+    assert_eq!(game.added, date.unwrap());
+    assert_eq!(game.updated, date.unwrap());
+    // the uid is the one the migrated database would give
+    let games: Vec<Game> = Parser::default()
+        .load_from_string(&format!("{}", game))
+        .into();
+    assert_eq!(games[0].uid, game.uid);
+    let keys: Vec<&str> = migration
+        .get_synthesized(game.uid)
+        .iter()
+        .map(|field| field.key)
+        .collect();
+    assert_eq!(keys, vec!["Added", "Updated", "IgdbId"]);
+    assert_eq!(migration.synthesized.len(), 6);
+}
+#[test]
+fn test_parser_uid_collisions_and_explicit_id() {
+    let data = "Game\tGame 1\nAdded\t2020-01-01\nGame\tGame 2\nId\t42\nGame\tGame 1\nAdded\t2020-01-01\nGame\tGame 3\nId\t42";
+    let report = Parser::default().load_report_from_string(data);
+    let games = report.get_games();
+    assert_eq!(games[1].uid, 42);
+    assert_eq!(games[1].id, Some(42));
+    assert!(format!("{}", games[1]).ends_with("IgdbId\nId\t42"));
+    assert_eq!(report.collisions.len(), 2);
+    assert_eq!(report.collisions[0].games, vec![0, 2]);
+    assert_eq!(report.collisions[0].lines, vec![1, 5]);
+    assert_eq!(report.collisions[1].uid, 42);
+    match report.result {
+        ParserResult::WithError(_, lines) => assert_eq!(lines, vec![5, 7]),
+        ParserResult::WithoutError(_) => panic!(),
+    }
+    let report = Parser::default().load_report_from_file("tests/data/test-games.db");
+    assert!(report.unwrap().collisions.is_empty());
+}
+#[test]
+fn test_parser_malformed_dates() {
+    let data = "Game\tGame 1\nAdded\t2022-13-05\nUpdated\nGame\tGame 2\nAdded\t2022-05-13";
+    let games = match Parser::default().load_from_string(data) {
+        ParserResult::WithError(games, lines) => {
+            assert_eq!(lines, vec![2]);
+            games
+        }
+        ParserResult::WithoutError(_) => panic!(),
+    };
+    assert_eq!(games[0].added, GameDate::Invalid("2022-13-05".into()));
+    assert_eq!(games[0].updated, GameDate::Unknown);
+    assert_eq!(games[0].added.get_date(), None);
+    assert_eq!(
+        games[1].added,
+        NaiveDate::from_ymd_opt(2022, 5, 13).unwrap()
+    );
+    // the dates are written back as read, 1970-01-01 when unknown
+    let written = format!("{}", games[0]);
+    assert!(written.contains("Added\t2022-13-05\nUpdated\t1970-01-01\n"));
+    match Parser::new(ParsingMode::Recovery).load_from_string(&written) {
+        ParserResult::WithError(games_again, lines) => {
+            assert_eq!(lines, vec![15]);
+            assert_eq!(games_again[0], games[0]);
+        }
+        ParserResult::WithoutError(_) => panic!(),
+    }
+    match Parser::new(ParsingMode::Strict).load_from_string(data) {
+        ParserResult::WithError(games, lines) => {
+            assert_eq!(lines, vec![2]);
+            assert_eq!(games.len(), 1);
+        }
+        ParserResult::WithoutError(_) => panic!(),
+    }
+}
+#[test]
+fn test_parser_does_not_keep_commented_out_record() {
+    let data = "Game\tA\nEngine\tgodot\nIgdbId\t1\n//Game\tB\n//Engine\tfna\nLicense\tMIT\nGame\tC";
+    for mode in [ParsingMode::Relaxed, ParsingMode::Recovery] {
+        let report = Parser::new(mode).load_report_from_string(data);
+        let games = report.get_games();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].extra, vec![("License".into(), Some("MIT".into()))]);
+        assert!(!format!("{}", games[0]).contains("//"));
+        match report.result {
+            ParserResult::WithError(_, lines) => assert_eq!(lines, vec![4, 5, 6]),
+            ParserResult::WithoutError(_) => panic!(),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use libpobsd::{Game, GameDataBase, Parser, ParserResult, ParsingMode, SearchType};
 
+use std::sync::Arc;
+
 // HELPER FUNCTIONS
 
 // helper function to return the games with both
@@ -449,6 +451,24 @@ fn test_search_game_by_name_name_does_not_exist() {
     assert!(games.is_empty());
 }
 
+// Test filter_games_by with search modes
+#[test]
+fn test_filter_games_by_genre_whole_word() {
+    let db = get_db_strict();
+    let qr = db.get_all_games();
+    let st = SearchType::WholeWord {
+        case_sensitive: true,
+    };
+    let games = qr.clone().filter_games_by_genre("Platformer", &st);
+    assert_eq!(games.count, 3);
+    let st = SearchType::Exact {
+        case_sensitive: true,
+    };
+    let games = qr.filter_games_by_genre("Platformer", &st);
+    assert_eq!(games.count, 1);
+    assert_eq!(games.items[0].name, "Akane the Kunoichi");
+}
+
 // Test search_item_by_name
 #[test]
 fn test_search_item_by_name_name_exists() {
@@ -471,3 +491,70 @@ fn test_search_item_by_name_name_does_not_exist() {
     let items = tag_query.filter_items_by_name("Does not exist").items;
     assert!(items.is_empty());
 }
+
+//-------------------
+// SHARED
+//-------------------
+
+// Test into_shared
+#[test]
+fn test_into_shared_keeps_games() {
+    let db = get_db_strict();
+    let st = SearchType::NotCaseSensitive;
+    let games = db.search_games_by_tag("indie", &st);
+    let shared = games.clone().into_shared(&db);
+    assert_eq!(shared.count, games.count);
+    for (game, shared_game) in games.into_iter().zip(shared) {
+        assert_eq!(game, shared_game.as_ref());
+    }
+}
+#[test]
+fn test_into_shared_does_not_clone_games() {
+    let db = get_db_strict();
+    let shared = db.get_all_games().into_shared(&db);
+    let game = shared.get(0).unwrap();
+    let game_bis = db.get_shared_game_by_id(game.uid).unwrap();
+    assert!(Arc::ptr_eq(game, &game_bis));
+}
+#[test]
+fn test_shared_query_outlives_database() {
+    let shared = {
+        let db = get_db_strict();
+        db.get_all_shared_games()
+    };
+    assert_eq!(shared.count, 9);
+}
+#[test]
+fn test_shared_games_sorted_like_games() {
+    let db = get_db_strict();
+    let games = db.get_all_games().into_inner();
+    let shared = db.get_all_shared_games().into_inner();
+    assert_eq!(games.len(), shared.len());
+    for (game, shared_game) in games.into_iter().zip(shared) {
+        assert_eq!(game, shared_game.as_ref());
+    }
+}
+#[test]
+fn test_shared_query_filter_in_another_thread() {
+    let db = get_db_strict();
+    let st = SearchType::NotCaseSensitive;
+    let shared = db.get_all_games().into_shared(&db);
+    let handle = std::thread::spawn(move || {
+        shared
+            .filter_games_by_tag("indie", &st)
+            .filter_games_by_engine("godot", &st)
+    });
+    let games = handle.join().unwrap();
+    assert_eq!(games.count, 1);
+    assert_eq!(games.get(0).unwrap().name, "The Adventures of Mr. Hat");
+}
+#[test]
+fn test_shared_query_get_game_by_name() {
+    let db = get_db_strict();
+    let st = SearchType::CaseSensitive;
+    let game = db
+        .get_all_shared_games()
+        .get_game_by_name("Airships: Conquer the Skies", &st)
+        .expect("Game with id 1595434339 exists");
+    assert_eq!(game.uid, 1595434339);
+}
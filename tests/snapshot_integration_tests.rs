@@ -0,0 +1,157 @@
+use libpobsd::db::AliasRegistry;
+use libpobsd::{GameDataBase, Parser, ParserResult, ParsingMode, SearchType};
+
+use std::fs;
+use std::path::PathBuf;
+
+// HELPER FUNCTIONS
+
+fn get_db_strict() -> GameDataBase {
+    let games = match Parser::new(ParsingMode::Strict)
+        .load_from_file("tests/data/test-games.db")
+        .expect("Could not open the file")
+    {
+        ParserResult::WithoutError(games) => games,
+        ParserResult::WithError(games, _) => games,
+    };
+    GameDataBase::new(games)
+}
+
+// helper function returning a path in the temporary
+// directory unique to the given test
+fn tmp_path(name: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("libpobsd-{}-{}", std::process::id(), name));
+    path
+}
+
+#[test]
+fn test_save_and_load_snapshot() {
+    let db = get_db_strict();
+    let snapshot = tmp_path("save-and-load.snap");
+    db.save_snapshot(&snapshot, "tests/data/test-games.db")
+        .expect("Could not save the snapshot");
+    let db2 = GameDataBase::load_snapshot(&snapshot, "tests/data/test-games.db")
+        .expect("Could not load the snapshot");
+    assert_eq!(
+        db.get_all_games().into_inner(),
+        db2.get_all_games().into_inner()
+    );
+    assert_eq!(db.get_all_tags_with_ids(), db2.get_all_tags_with_ids());
+    assert_eq!(db.get_all_devs_with_ids(), db2.get_all_devs_with_ids());
+    fs::remove_file(snapshot).unwrap();
+}
+
+#[test]
+fn test_load_snapshot_reparses_outdated_snapshot() {
+    let database = tmp_path("outdated.db");
+    let snapshot = tmp_path("outdated.snap");
+    fs::copy("tests/data/test-games.db", &database).unwrap();
+    get_db_strict().save_snapshot(&snapshot, &database).unwrap();
+    // Remove the last game from the database
+    let data = fs::read_to_string(&database).unwrap();
+    let index = data.find("Game\tAlways Sometimes Monsters").unwrap();
+    fs::write(&database, &data[..index]).unwrap();
+    let db = GameDataBase::load_snapshot(&snapshot, &database).unwrap();
+    assert_eq!(db.get_all_games().count, 8);
+    fs::remove_file(snapshot).unwrap();
+    fs::remove_file(database).unwrap();
+}
+
+#[test]
+fn test_load_snapshot_without_snapshot() {
+    let snapshot = tmp_path("missing.snap");
+    let db = GameDataBase::load_snapshot(&snapshot, "tests/data/test-games.db").unwrap();
+    assert_eq!(db.get_all_games().count, 9);
+}
+
+#[test]
+fn test_load_snapshot_with_corrupted_snapshot() {
+    let snapshot = tmp_path("corrupted.snap");
+    fs::write(&snapshot, b"POBSDSNP garbage").unwrap();
+    let db = GameDataBase::load_snapshot(&snapshot, "tests/data/test-games.db").unwrap();
+    assert_eq!(db.get_all_games().count, 9);
+    fs::remove_file(snapshot).unwrap();
+}
+
+#[test]
+fn test_load_snapshot_without_database() {
+    let snapshot = tmp_path("no-database.snap");
+    assert!(GameDataBase::load_snapshot(&snapshot, "tests/data/nothere.db").is_err());
+}
+
+#[test]
+fn test_load_snapshot_with_aliases() {
+    let snapshot = tmp_path("aliases.snap");
+    get_db_strict()
+        .save_snapshot(&snapshot, "tests/data/test-games.db")
+        .unwrap();
+    let mut aliases = AliasRegistry::new();
+    aliases.add_engines_alias("Godot Engine", "godot");
+    // from the snapshot, saved without aliases
+    let db = GameDataBase::load_snapshot_with_aliases(
+        &snapshot,
+        "tests/data/test-games.db",
+        aliases.clone(),
+    )
+    .unwrap();
+    // reparsed
+    let db2 = GameDataBase::load_snapshot_with_aliases(
+        tmp_path("aliases-missing.snap"),
+        "tests/data/test-games.db",
+        aliases.clone(),
+    )
+    .unwrap();
+    for db in [&db, &db2] {
+        assert_eq!(db.get_aliases(), &aliases);
+        assert_eq!(
+            db.match_games_by_engine("godot engine", &SearchType::CaseSensitive)
+                .count,
+            1
+        );
+    }
+    assert_eq!(
+        db.get_all_engines_with_ids(),
+        db2.get_all_engines_with_ids()
+    );
+    // the aliases saved in the snapshot are not used by load_snapshot
+    db.save_snapshot(&snapshot, "tests/data/test-games.db")
+        .unwrap();
+    let db = GameDataBase::load_snapshot(&snapshot, "tests/data/test-games.db").unwrap();
+    assert_eq!(db.get_aliases(), &AliasRegistry::new());
+    fs::remove_file(snapshot).unwrap();
+}
+
+#[test]
+fn test_load_snapshot_keeps_uid_aliases() {
+    let database = tmp_path("uid-aliases.db");
+    let snapshot = tmp_path("uid-aliases.snap");
+    fs::copy("tests/data/test-games.db", &database).unwrap();
+    let mut db = get_db_strict();
+    let hat = db
+        .get_game_by_name("The Adventures of Mr. Hat", &SearchType::CaseSensitive)
+        .unwrap()
+        .uid;
+    db.add_uid_alias(42, hat);
+    db.save_snapshot(&snapshot, &database).unwrap();
+    // from the snapshot
+    let db = GameDataBase::load_snapshot(&snapshot, &database).unwrap();
+    assert_eq!(db.resolve_uid(42), Some(hat));
+    // from an outdated snapshot, the uid of the game changing
+    let data = fs::read_to_string(&database).unwrap();
+    fs::write(
+        &database,
+        data.replace("Added\t2022-05-13", "Added\t2022-05-14"),
+    )
+    .unwrap();
+    let db = GameDataBase::load_snapshot(&snapshot, &database).unwrap();
+    let new_hat = db
+        .get_game_by_name("The Adventures of Mr. Hat", &SearchType::CaseSensitive)
+        .unwrap()
+        .uid;
+    assert_ne!(hat, new_hat);
+    assert_eq!(db.resolve_uid(42), Some(new_hat));
+    assert_eq!(db.resolve_uid(hat), Some(new_hat));
+    fs::remove_file(snapshot).unwrap();
+    fs::remove_file(database).unwrap();
+}